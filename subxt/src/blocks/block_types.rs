@@ -11,6 +11,7 @@ use crate::{
     rpc::types::ChainBlockResponse,
     runtime_api::RuntimeApi,
     storage::Storage,
+    Metadata,
 };
 
 use futures::lock::Mutex as AsyncMutex;
@@ -20,6 +21,8 @@ use std::sync::Arc;
 pub struct Block<T: Config, C> {
     header: T::Header,
     client: C,
+    // The metadata to decode this block's extrinsics, events and storage with.
+    metadata: Metadata,
     // Since we obtain the same events for every extrinsic, let's
     // cache them so that we only ever do that once:
     cached_events: CachedEvents<T>,
@@ -35,9 +38,15 @@ where
     C: OfflineClientT<T>,
 {
     pub(crate) fn new(header: T::Header, client: C) -> Self {
+        let metadata = client.metadata();
+        Block::new_with_metadata(header, client, metadata)
+    }
+
+    pub(crate) fn new_with_metadata(header: T::Header, client: C, metadata: Metadata) -> Self {
         Block {
             header,
             client,
+            metadata,
             cached_events: Default::default(),
         }
     }
@@ -56,6 +65,11 @@ where
     pub fn header(&self) -> &T::Header {
         &self.header
     }
+
+    /// Return the metadata used to decode the extrinsics, events and storage of this block.
+    pub fn metadata(&self) -> Metadata {
+        self.metadata.clone()
+    }
 }
 
 impl<T, C> Block<T, C>
@@ -65,12 +79,18 @@ where
 {
     /// Return the events associated with the block, fetching them from the node if necessary.
     pub async fn events(&self) -> Result<events::Events<T>, Error> {
-        get_events(&self.client, self.header.hash(), &self.cached_events).await
+        get_events(
+            &self.client,
+            self.header.hash(),
+            &self.metadata,
+            &self.cached_events,
+        )
+        .await
     }
 
     /// Fetch and return the block body.
     pub async fn body(&self) -> Result<BlockBody<T, C>, Error> {
-        let ids = ExtrinsicPartTypeIds::new(&self.client.metadata())?;
+        let ids = ExtrinsicPartTypeIds::new(&self.metadata)?;
         let block_hash = self.header.hash();
         let Some(block_details) = self.client.rpc().block(Some(block_hash)).await? else {
             return Err(BlockError::not_found(block_hash).into());
@@ -81,13 +101,14 @@ where
             block_details,
             self.cached_events.clone(),
             ids,
+            self.metadata.clone(),
         ))
     }
 
     /// Work with storage.
     pub fn storage(&self) -> Storage<T, C> {
         let block_hash = self.hash();
-        Storage::new(self.client.clone(), block_hash)
+        Storage::new(self.client.clone(), block_hash).with_metadata(self.metadata.clone())
     }
 
     /// Execute a runtime API call at this block.
@@ -102,6 +123,7 @@ pub struct BlockBody<T: Config, C> {
     client: C,
     cached_events: CachedEvents<T>,
     ids: ExtrinsicPartTypeIds,
+    metadata: Metadata,
 }
 
 impl<T, C> BlockBody<T, C>
@@ -114,12 +136,14 @@ where
         details: ChainBlockResponse<T>,
         cached_events: CachedEvents<T>,
         ids: ExtrinsicPartTypeIds,
+        metadata: Metadata,
     ) -> Self {
         Self {
             details,
             client,
             cached_events,
             ids,
+            metadata,
         }
     }
 
@@ -133,6 +157,7 @@ where
             self.cached_events.clone(),
             self.ids,
             self.details.block.header.hash(),
+            self.metadata.clone(),
         )
     }
 }
@@ -141,6 +166,7 @@ where
 pub(crate) async fn get_events<C, T>(
     client: &C,
     block_hash: T::Hash,
+    metadata: &Metadata,
     cached_events: &AsyncMutex<Option<events::Events<T>>>,
 ) -> Result<events::Events<T>, Error>
 where
@@ -150,13 +176,15 @@ where
     // Acquire lock on the events cache. We either get back our events or we fetch and set them
     // before unlocking, so only one fetch call should ever be made. We do this because the
     // same events can be shared across all extrinsics in the block.
-    let lock = cached_events.lock().await;
+    let mut lock = cached_events.lock().await;
     let events = match &*lock {
         Some(events) => events.clone(),
         None => {
-            events::EventsClient::new(client.clone())
-                .at(block_hash)
-                .await?
+            let events =
+                events::Events::new_from_client(metadata.clone(), block_hash, client.clone())
+                    .await?;
+            *lock = Some(events.clone());
+            events
         }
     };
 
@@ -2,11 +2,11 @@
 // This file is dual-licensed as Apache-2.0 or GPL-3.0.
 // see LICENSE for license details.
 
-use super::Block;
+use super::{historic_metadata::HistoricMetadata, Block};
 use crate::{
     client::OnlineClientT,
     config::{Config, Header},
-    error::{BlockError, Error},
+    error::{BlockError, Error, RpcError},
     utils::PhantomDataSendSync,
 };
 use derivative::Derivative;
@@ -21,17 +21,46 @@ type BlockStreamRes<T> = Result<BlockStream<T>, Error>;
 #[derivative(Clone(bound = "Client: Clone"))]
 pub struct BlocksClient<T, Client> {
     client: Client,
+    historic_metadata: HistoricMetadata,
+    use_historic_metadata: bool,
     _marker: PhantomDataSendSync<T>,
 }
 
 impl<T, Client> BlocksClient<T, Client> {
     /// Create a new [`BlocksClient`].
     pub fn new(client: Client) -> Self {
+        Self::new_with_historic_metadata(client, HistoricMetadata::default())
+    }
+
+    /// Create a new [`BlocksClient`] which caches historic metadata in the given cache.
+    pub(crate) fn new_with_historic_metadata(
+        client: Client,
+        historic_metadata: HistoricMetadata,
+    ) -> Self {
         Self {
             client,
+            historic_metadata,
+            use_historic_metadata: false,
             _marker: PhantomDataSendSync::new(),
         }
     }
+
+    /// Decode the extrinsics, events and storage of each block obtained from this client
+    /// using the metadata of the runtime that the block was produced under, rather than
+    /// the client's current metadata. This makes it possible to work with blocks from
+    /// before a runtime upgrade, at the cost of looking up the runtime version of each
+    /// block. The metadata of each runtime is fetched once and cached; every
+    /// [`BlocksClient`] obtained from the same [`crate::OnlineClient`] shares this cache.
+    pub fn use_historic_metadata(mut self, use_historic_metadata: bool) -> Self {
+        self.use_historic_metadata = use_historic_metadata;
+        self
+    }
+
+    // The cache to find the metadata of each block in, if we're using historic metadata.
+    fn historic_metadata(&self) -> Option<HistoricMetadata> {
+        self.use_historic_metadata
+            .then(|| self.historic_metadata.clone())
+    }
 }
 
 impl<T, Client> BlocksClient<T, Client>
@@ -43,9 +72,9 @@ where
     ///
     /// # Warning
     ///
-    /// This call only supports blocks produced since the most recent
-    /// runtime upgrade. You can attempt to retrieve older blocks,
-    /// but may run into errors attempting to work with them.
+    /// Unless [`BlocksClient::use_historic_metadata()`] is enabled, this call only
+    /// supports blocks produced since the most recent runtime upgrade. You can attempt
+    /// to retrieve older blocks, but may run into errors attempting to work with them.
     pub fn at(
         &self,
         block_hash: T::Hash,
@@ -67,10 +96,11 @@ where
         block_hash: Option<T::Hash>,
     ) -> impl Future<Output = Result<Block<T, Client>, Error>> + Send + 'static {
         let client = self.client.clone();
+        let historic_metadata = self.historic_metadata();
         async move {
-            // If block hash is not provided, get the hash
-            // for the latest block and use that.
-            let block_hash = match block_hash {
+            // If block hash is not provided, use the block that the client is
+            // pinned to, or failing that, get the hash for the latest block.
+            let block_hash = match block_hash.or_else(|| client.pinned_block_hash()) {
                 Some(hash) => hash,
                 None => client
                     .rpc()
@@ -84,7 +114,7 @@ where
                 None => return Err(BlockError::not_found(block_hash).into()),
             };
 
-            Ok(Block::new(block_header, client))
+            new_block(block_header, client, historic_metadata).await
         }
     }
 
@@ -164,18 +194,38 @@ where
 {
     let sub = sub.await?.then(move |header| {
         let client = blocks_client.client.clone();
+        let historic_metadata = blocks_client.historic_metadata();
         async move {
             let header = match header {
                 Ok(header) => header,
                 Err(e) => return Err(e),
             };
 
-            Ok(Block::new(header, client))
+            new_block(header, client, historic_metadata).await
         }
     });
     BlockStreamRes::Ok(Box::pin(sub))
 }
 
+/// Construct a [`Block`], looking up the metadata to decode it with if need be.
+async fn new_block<T, Client>(
+    header: T::Header,
+    client: Client,
+    historic_metadata: Option<HistoricMetadata>,
+) -> Result<Block<T, Client>, Error>
+where
+    T: Config,
+    Client: OnlineClientT<T>,
+{
+    match historic_metadata {
+        Some(historic_metadata) => {
+            let metadata = historic_metadata.metadata_for(&client, &header).await?;
+            Ok(Block::new_with_metadata(header, client, metadata))
+        }
+        None => Ok(Block::new(header, client)),
+    }
+}
+
 /// Note: This is exposed for testing but is not considered stable and may change
 /// without notice in a patch release.
 #[doc(hidden)]
@@ -196,7 +246,15 @@ where
         // Get the header, or return a stream containing just the error.
         let header = match s {
             Ok(header) => header,
-            Err(e) => return Either::Left(stream::once(async { Err(e.into()) })),
+            Err(e) => match Into::<Error>::into(e) {
+                // The subscription is being re-established after a lost connection. Skip
+                // this marker; any blocks missed in the meantime are filled in below once
+                // the next header arrives.
+                Error::Rpc(RpcError::DisconnectedWillReconnect(_)) => {
+                    return Either::Left(stream::iter(None))
+                }
+                e => return Either::Left(stream::iter(Some(Err(e)))),
+            },
         };
 
         // We want all previous details up to, but not including this current block num.
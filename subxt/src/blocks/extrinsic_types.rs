@@ -60,6 +60,7 @@ pub struct Extrinsics<T: Config, C> {
     cached_events: CachedEvents<T>,
     ids: ExtrinsicPartTypeIds,
     hash: T::Hash,
+    metadata: Metadata,
 }
 
 impl<T, C> Extrinsics<T, C>
@@ -73,6 +74,7 @@ where
         cached_events: CachedEvents<T>,
         ids: ExtrinsicPartTypeIds,
         hash: T::Hash,
+        metadata: Metadata,
     ) -> Self {
         Self {
             client,
@@ -80,6 +82,7 @@ where
             cached_events,
             ids,
             hash,
+            metadata,
         }
     }
 
@@ -111,6 +114,7 @@ where
         let hash = self.hash;
         let cached_events = self.cached_events.clone();
         let ids = self.ids;
+        let metadata = self.metadata.clone();
         let mut index = 0;
 
         std::iter::from_fn(move || {
@@ -121,6 +125,7 @@ where
                     index as u32,
                     extrinsics[index].0.clone().into(),
                     client.clone(),
+                    metadata.clone(),
                     hash,
                     cached_events.clone(),
                     ids,
@@ -205,6 +210,7 @@ where
         index: u32,
         extrinsic_bytes: Arc<[u8]>,
         client: C,
+        metadata: Metadata,
         block_hash: T::Hash,
         cached_events: CachedEvents<T>,
         ids: ExtrinsicPartTypeIds,
@@ -213,8 +219,6 @@ where
         const VERSION_MASK: u8 = 0b0111_1111;
         const LATEST_EXTRINSIC_VERSION: u8 = 4;
 
-        let metadata = client.metadata();
-
         // Extrinsic are encoded in memory in the following way:
         //   - first byte: abbbbbbb (a = 0 for unsigned, 1 for signed, b = version)
         //   - signature: [unknown TBD with metadata].
@@ -442,7 +446,13 @@ where
 {
     /// The events associated with the extrinsic.
     pub async fn events(&self) -> Result<ExtrinsicEvents<T>, Error> {
-        let events = get_events(&self.client, self.block_hash, &self.cached_events).await?;
+        let events = get_events(
+            &self.client,
+            self.block_hash,
+            &self.metadata,
+            &self.cached_events,
+        )
+        .await?;
         let ext_hash = T::Hasher::hash_of(&self.bytes);
         Ok(ExtrinsicEvents::new(ext_hash, self.index, events))
     }
@@ -786,6 +796,7 @@ mod tests {
             1,
             vec![].into(),
             client,
+            metadata,
             H256::random(),
             Default::default(),
             ids,
@@ -804,6 +815,7 @@ mod tests {
             1,
             3u8.encode().into(),
             client,
+            metadata,
             H256::random(),
             Default::default(),
             ids,
@@ -843,6 +855,7 @@ mod tests {
             1,
             tx_encoded.encoded()[1..].into(),
             client,
+            metadata,
             H256::random(),
             Default::default(),
             ids,
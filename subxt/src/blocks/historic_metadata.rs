@@ -0,0 +1,195 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use crate::{
+    client::OnlineClientT,
+    config::{Config, Header},
+    error::Error,
+    Metadata,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+/// The metadata of the runtimes that historic blocks were produced under, cached
+/// by spec version so that it's only fetched once per runtime.
+#[derive(Clone, Default)]
+pub(crate) struct HistoricMetadata(Arc<Mutex<HashMap<u32, Metadata>>>);
+
+impl HistoricMetadata {
+    /// Return the metadata to decode the extrinsics, events and storage of the block
+    /// with the given header with. This is the metadata of the runtime in place at
+    /// the block's parent, since a runtime upgrade only takes effect from the block
+    /// after the one in which it was enacted.
+    pub(crate) async fn metadata_for<T, C>(
+        &self,
+        client: &C,
+        header: &T::Header,
+    ) -> Result<Metadata, Error>
+    where
+        T: Config,
+        C: OnlineClientT<T>,
+    {
+        let at = if header.number().into() == 0 {
+            header.hash()
+        } else {
+            header.parent_hash()
+        };
+
+        let spec_version = client.rpc().runtime_version(Some(at)).await?.spec_version;
+        if spec_version == client.runtime_version().spec_version {
+            return Ok(client.metadata());
+        }
+
+        let cached = self
+            .0
+            .lock()
+            .expect("shouldn't be poisoned")
+            .get(&spec_version)
+            .cloned();
+        if let Some(metadata) = cached {
+            return Ok(metadata);
+        }
+
+        let metadata = client.rpc().metadata_legacy(Some(at)).await?;
+        self.0
+            .lock()
+            .expect("shouldn't be poisoned")
+            .insert(spec_version, metadata.clone());
+        Ok(metadata)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::substrate::{Digest, SubstrateHeader, H256},
+        rpc::{
+            mock_rpc_client::{to_raw, MockRpcClient},
+            types::{Bytes, RuntimeVersion},
+        },
+        OnlineClient, SubstrateConfig,
+    };
+    use codec::Decode;
+
+    type Requests = Arc<Mutex<Vec<(String, H256)>>>;
+
+    // Blocks are identified by hashes made from their block numbers. Blocks below 10
+    // were produced under spec version 1, and blocks from 10 to 19 under the current
+    // spec version, 3. Any other hash belongs to a block produced under spec version 2.
+    fn spec_version(at: H256) -> u32 {
+        match at.to_low_u64_be() {
+            n if at == H256::from_low_u64_be(n) && n < 10 => 1,
+            n if at == H256::from_low_u64_be(n) && n < 20 => 3,
+            _ => 2,
+        }
+    }
+
+    // A client which records the requests made to it, along with the block hash that
+    // they were made at.
+    fn client(requests: Requests) -> OnlineClient<SubstrateConfig> {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_tiny.scale").unwrap();
+        let metadata = Metadata::decode(&mut &*bytes).unwrap();
+
+        let rpc = MockRpcClient::new().on_request(move |method, params| {
+            let (at,): (H256,) = serde_json::from_str(params.unwrap().get()).unwrap();
+            requests.lock().unwrap().push((method.to_owned(), at));
+            match method {
+                "state_getRuntimeVersion" => Ok(to_raw(serde_json::json!({
+                    "specVersion": spec_version(at),
+                    "transactionVersion": 1,
+                }))),
+                "state_getMetadata" => Ok(to_raw(Bytes(bytes.clone()))),
+                _ => panic!("unexpected method {method}"),
+            }
+        });
+
+        let runtime_version = RuntimeVersion {
+            spec_version: 3,
+            transaction_version: 1,
+            other: Default::default(),
+        };
+        OnlineClient::from_rpc_client_with(H256::zero(), runtime_version, metadata, Arc::new(rpc))
+            .unwrap()
+    }
+
+    fn header(number: u32, parent: u64) -> <SubstrateConfig as Config>::Header {
+        SubstrateHeader {
+            parent_hash: H256::from_low_u64_be(parent),
+            number,
+            state_root: H256::zero(),
+            extrinsics_root: H256::zero(),
+            digest: Digest::default(),
+        }
+    }
+
+    fn metadata_requests(requests: &Requests) -> Vec<H256> {
+        let requests = requests.lock().unwrap();
+        requests
+            .iter()
+            .filter(|(method, _)| method == "state_getMetadata")
+            .map(|(_, at)| *at)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn metadata_is_fetched_at_the_parent_block() {
+        let requests = Requests::default();
+        let client = client(requests.clone());
+        let historic = HistoricMetadata::default();
+
+        // Block 10 runs the current runtime, but its parent doesn't, and so it's the
+        // parent's metadata that's needed.
+        historic
+            .metadata_for(&client, &header(10, 9))
+            .await
+            .unwrap();
+        assert_eq!(metadata_requests(&requests), vec![H256::from_low_u64_be(9)]);
+    }
+
+    #[tokio::test]
+    async fn genesis_metadata_is_fetched_at_genesis() {
+        let requests = Requests::default();
+        let client = client(requests.clone());
+        let historic = HistoricMetadata::default();
+
+        let genesis = header(0, 0);
+        historic.metadata_for(&client, &genesis).await.unwrap();
+        assert_eq!(metadata_requests(&requests), vec![genesis.hash()]);
+    }
+
+    #[tokio::test]
+    async fn metadata_is_cached_by_spec_version() {
+        let requests = Requests::default();
+        let client = client(requests.clone());
+        let historic = HistoricMetadata::default();
+
+        // Blocks under the same spec version share metadata:
+        for number in 2..5 {
+            historic
+                .metadata_for(&client, &header(number, number as u64 - 1))
+                .await
+                .unwrap();
+        }
+        assert_eq!(metadata_requests(&requests), vec![H256::from_low_u64_be(1)]);
+
+        // And blocks under the current spec version use the client's metadata:
+        historic
+            .metadata_for(&client, &header(15, 14))
+            .await
+            .unwrap();
+        assert_eq!(metadata_requests(&requests).len(), 1);
+
+        // Each block still needs its spec version to be looked up.
+        let runtime_version_requests = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(method, _)| method == "state_getRuntimeVersion")
+            .count();
+        assert_eq!(runtime_version_requests, 4);
+    }
+}
@@ -7,6 +7,9 @@
 mod block_types;
 mod blocks_client;
 mod extrinsic_types;
+mod historic_metadata;
+
+pub(crate) use historic_metadata::HistoricMetadata;
 
 pub use block_types::{Block, BlockBody};
 pub use blocks_client::{subscribe_to_block_headers_filling_in_gaps, BlocksClient};
@@ -31,6 +31,10 @@
 //! allows you to decide how Subxt will attempt to talk to a node if you'd prefer something other
 //! than the provided interfaces.
 //!
+//! For more control, [`crate::OnlineClient::builder()`] hands back a [`crate::client::OnlineClientBuilder`],
+//! which can be used to pick which metadata versions to ask the node for, provide metadata or a
+//! genesis hash up front, pin the client to a specific block, or start the runtime updater.
+//!
 //! ## Examples
 //!
 //! Defining some custom config based off the default Substrate config:
@@ -51,3 +55,8 @@
 #![doc = include_str!("../../../examples/setup_client_offline.rs")]
 //! ```
 //!
+//! An [`crate::OfflineClient`] can also be created from the raw chain spec of a chain and the
+//! metadata of its runtime using [`crate::OfflineClient::from_chain_spec_files()`], which works
+//! out the genesis hash and runtime version from these without needing a node. The state version
+//! that the chain started with must be given too, since this determines the genesis hash.
+//!
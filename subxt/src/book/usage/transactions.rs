@@ -83,6 +83,22 @@
 //!
 //! See the [`sp_core::Pair`] docs for more ways to generate them.
 //!
+//! Without `sp_core`, the `subxt::tx::sr25519::Keypair` and `subxt::tx::ed25519::Keypair` types
+//! implement [`crate::tx::Signer`] too. These are enabled by the `sr25519` and `ed25519` features
+//! (which aren't enabled by default), and can be created from a `subxt::tx::SecretUri`, which is a
+//! mnemonic phrase or hex seed followed by any derivation junctions and password, just like the
+//! secret URIs that Substrate accepts:
+//!
+//! ```rust,ignore
+//! use subxt::tx::{sr25519, SecretUri};
+//!
+//! let uri: SecretUri = "//Alice".parse().unwrap();
+//! let signer = sr25519::Keypair::from_uri(&uri).unwrap();
+//! ```
+//!
+//! For chains whose accounts are Ethereum style [`crate::utils::AccountId20`]s, such as those using
+//! [`crate::config::EthereumConfig`], use a [`crate::tx::EcdsaKeccakSigner`] instead.
+//!
 //! If this isn't suitable/available, you can either implement [`crate::tx::Signer`] yourself to use
 //! custom signing logic, or you can use some external signing logic, like so:
 //!
@@ -162,6 +178,33 @@
 //! This example doesn't wait for the transaction to be included in a block; it just submits it and
 //! hopes for the best!
 //!
+//! Rather than working out the era and mortality checkpoint by hand, you can make any of these
+//! parameters mortal with [`crate::tx::TxClient::mortal_params`], which only needs the number of
+//! blocks that the transaction should be valid for, and aligns the era to the latest finalized
+//! block. [`crate::tx::TxClient::create_signed_mortal`] does this for you:
+//!
+//! ```rust,no_run
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use subxt::{tx::PairSigner, OnlineClient, PolkadotConfig};
+//! use sp_keyring::AccountKeyring;
+//!
+//! let client = OnlineClient::<PolkadotConfig>::new().await?;
+//! let signer = PairSigner::<PolkadotConfig, _>::new(AccountKeyring::Alice.pair());
+//!
+//! let payload = subxt::dynamic::tx("System", "remark", vec![
+//!     subxt::dynamic::Value::from_bytes("Hello there")
+//! ]);
+//! // Only valid for the next 64 blocks or so:
+//! let tx = client
+//!     .tx()
+//!     .create_signed_mortal(&payload, &signer, 64, Default::default())
+//!     .await?;
+//! tx.submit().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! ### Custom handling of transaction status updates
 //!
 //! If you'd like more control or visibility over exactly which status updates are being emitted for
@@ -174,3 +217,33 @@
 //! Take a look at the API docs for [`crate::tx::TxProgress`], [`crate::tx::TxStatus`] and
 //! [`crate::tx::TxInBlock`] for more options.
 //!
+//! ### Submitting many transactions from one account
+//!
+//! Transactions created with [`crate::tx::TxClient::create_signed`] ask the node for the nonce
+//! of the account each time, and so transactions from the same account which are created
+//! concurrently are likely to end up with the same nonce. To avoid this, share a
+//! [`crate::tx::NonceManager`] between the tasks submitting them and create each transaction with
+//! [`crate::tx::TxClient::create_signed_with_nonce_manager`], which hands out nonces in sequence:
+//!
+//! ```rust,no_run
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! use subxt::{tx::{NonceManager, PairSigner}, OnlineClient, PolkadotConfig};
+//! use sp_keyring::AccountKeyring;
+//!
+//! let client = OnlineClient::<PolkadotConfig>::new().await?;
+//! let nonce_manager = NonceManager::new(&client);
+//! let signer = PairSigner::<PolkadotConfig, _>::new(AccountKeyring::Alice.pair());
+//!
+//! let payload = subxt::dynamic::tx("System", "remark", vec![
+//!     subxt::dynamic::Value::from_bytes("Hello there")
+//! ]);
+//! let tx = client
+//!     .tx()
+//!     .create_signed_with_nonce_manager(&payload, &signer, &nonce_manager, Default::default())
+//!     .await?;
+//! tx.submit().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
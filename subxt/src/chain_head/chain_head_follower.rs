@@ -0,0 +1,621 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use crate::{
+    client::OnlineClientT,
+    error::{ChainHeadError, Error},
+    metadata::DecodeWithMetadata,
+    rpc::{
+        types::{ChainBlockExtrinsic, ChainHeadEvent, FollowEvent},
+        Subscription,
+    },
+    runtime_api::{encode_runtime_api_call, RuntimeApiPayload},
+    storage::{
+        address::Yes, decode_storage_with_metadata, lookup_entry_details, utils,
+        validate_storage_address, StorageAddress,
+    },
+    Config,
+};
+use codec::Decode;
+use serde::de::DeserializeOwned;
+use std::future::Future;
+
+/// Follow the chain using the unstable `chainHead_follow` RPC subscription, keeping track
+/// of the blocks that it reports (and has therefore pinned on the node).
+///
+/// Call [`ChainHeadFollower::next()`] to drive the subscription forwards. As blocks are
+/// finalized, any blocks that have been pruned, as well as any previously finalized blocks,
+/// are automatically unpinned. This leaves the latest finalized block and any of its
+/// descendants pinned, which can then be queried using methods like
+/// [`ChainHeadFollower::body()`], [`ChainHeadFollower::storage()`] and
+/// [`ChainHeadFollower::call()`].
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use subxt::{chain_head::ChainHeadFollower, rpc::types::FollowEvent, OnlineClient, PolkadotConfig};
+///
+/// let api = OnlineClient::<PolkadotConfig>::new().await.unwrap();
+/// let mut follower = ChainHeadFollower::new(api, false).await.unwrap();
+///
+/// while let Some(event) = follower.next().await {
+///     if let FollowEvent::NewBlock(block) = event.unwrap() {
+///         let body = follower.body(block.block_hash).await.unwrap();
+///         println!("Block {:?} has {} extrinsics", block.block_hash, body.len());
+///     }
+/// }
+/// # }
+/// ```
+pub struct ChainHeadFollower<T: Config, Client> {
+    client: Client,
+    subscription: Subscription<FollowEvent<T::Hash>>,
+    subscription_id: String,
+    // The blocks that are currently pinned, along with their parents if known.
+    pinned: Vec<PinnedBlock<T::Hash>>,
+    finalized: Option<T::Hash>,
+    best: Option<T::Hash>,
+    stopped: bool,
+}
+
+impl<T: Config, Client> std::fmt::Debug for ChainHeadFollower<T, Client> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChainHeadFollower")
+            .field("subscription_id", &self.subscription_id)
+            .field("pinned", &self.pinned)
+            .field("finalized", &self.finalized)
+            .field("best", &self.best)
+            .field("stopped", &self.stopped)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PinnedBlock<Hash> {
+    hash: Hash,
+    parent: Option<Hash>,
+}
+
+impl<T, Client> ChainHeadFollower<T, Client>
+where
+    T: Config,
+    Client: OnlineClientT<T>,
+{
+    /// Start following the chain. If `runtime_updates` is true, the node will report
+    /// the runtime version of the initial finalized block and of any new blocks whose
+    /// runtime differs from that of their parent.
+    pub async fn new(client: Client, runtime_updates: bool) -> Result<Self, Error> {
+        let subscription = client
+            .rpc()
+            .chainhead_unstable_follow(runtime_updates)
+            .await?;
+        let subscription_id = subscription
+            .subscription_id()
+            .ok_or(ChainHeadError::MissingSubscriptionId)?
+            .clone();
+
+        Ok(Self {
+            client,
+            subscription,
+            subscription_id,
+            pinned: Vec::new(),
+            finalized: None,
+            best: None,
+            stopped: false,
+        })
+    }
+
+    /// Wait for the next event from the `chainHead_follow` subscription, updating the
+    /// blocks that we are tracking (and unpinning any that we no longer need) before
+    /// handing it back. This returns `None` once the subscription has stopped.
+    ///
+    /// If some blocks fail to unpin, the error is handed back instead of the event. Those
+    /// blocks are still reported as pinned, since the node still has them pinned.
+    pub async fn next(&mut self) -> Option<Result<FollowEvent<T::Hash>, Error>> {
+        if self.stopped {
+            return None;
+        }
+
+        let event = match self.subscription.next().await {
+            Some(Ok(event)) => event,
+            Some(Err(e)) => return Some(Err(e)),
+            None => {
+                self.stop();
+                return None;
+            }
+        };
+
+        match &event {
+            FollowEvent::Initialized(init) => {
+                self.pin(init.finalized_block_hash, None);
+                self.finalized = Some(init.finalized_block_hash);
+                self.best = Some(init.finalized_block_hash);
+            }
+            FollowEvent::NewBlock(block) => {
+                self.pin(block.block_hash, Some(block.parent_block_hash));
+            }
+            FollowEvent::BestBlockChanged(best) => {
+                self.best = Some(best.best_block_hash);
+            }
+            FollowEvent::Finalized(finalized) => {
+                // Everything but the latest finalized block is no longer needed.
+                let latest = finalized
+                    .finalized_block_hashes
+                    .last()
+                    .copied()
+                    .or(self.finalized);
+                let to_unpin: Vec<T::Hash> = self
+                    .finalized
+                    .iter()
+                    .chain(&finalized.finalized_block_hashes)
+                    .chain(&finalized.pruned_block_hashes)
+                    .copied()
+                    .filter(|hash| Some(*hash) != latest)
+                    .collect();
+
+                self.finalized = latest;
+                if let Err(e) = self.unpin(to_unpin).await {
+                    return Some(Err(e));
+                }
+            }
+            FollowEvent::Stop => self.stop(),
+        }
+
+        Some(Ok(event))
+    }
+
+    /// The ID of the underlying `chainHead_follow` subscription.
+    pub fn subscription_id(&self) -> &str {
+        &self.subscription_id
+    }
+
+    /// The hash of the latest finalized block, once it's been reported.
+    pub fn finalized_block_hash(&self) -> Option<T::Hash> {
+        self.finalized
+    }
+
+    /// The hash of the current best block, once it's been reported.
+    pub fn best_block_hash(&self) -> Option<T::Hash> {
+        self.best
+    }
+
+    /// The hashes of all of the blocks that are currently pinned.
+    pub fn pinned_block_hashes(&self) -> impl Iterator<Item = T::Hash> + '_ {
+        self.pinned.iter().map(|b| b.hash)
+    }
+
+    /// Is the block with the given hash currently pinned?
+    pub fn is_pinned(&self, hash: T::Hash) -> bool {
+        self.pinned.iter().any(|b| b.hash == hash)
+    }
+
+    /// The parent hash of the given pinned block. This is `None` for the first block
+    /// reported, or if the block isn't pinned.
+    pub fn parent_hash(&self, hash: T::Hash) -> Option<T::Hash> {
+        self.pinned.iter().find(|b| b.hash == hash)?.parent
+    }
+
+    /// Fetch the header of the given pinned block.
+    pub fn header(&self, hash: T::Hash) -> impl Future<Output = Result<T::Header, Error>> + 'static
+    where
+        T::Header: Decode,
+    {
+        let checked = self.check_pinned(hash);
+        let client = self.client.clone();
+        let subscription_id = self.subscription_id.clone();
+        async move {
+            checked?;
+            let header = client
+                .rpc()
+                .chainhead_unstable_header(subscription_id, hash)
+                .await?
+                .ok_or_else(|| ChainHeadError::NotPinned(hash_to_string(hash)))?;
+            let bytes = from_hex(&header)?;
+            Ok(T::Header::decode(&mut &*bytes)?)
+        }
+    }
+
+    /// Fetch the extrinsics in the body of the given pinned block.
+    pub fn body(
+        &self,
+        hash: T::Hash,
+    ) -> impl Future<Output = Result<Vec<ChainBlockExtrinsic>, Error>> + 'static {
+        let checked = self.check_pinned(hash);
+        let client = self.client.clone();
+        let subscription_id = self.subscription_id.clone();
+        async move {
+            checked?;
+            let sub = client
+                .rpc()
+                .chainhead_unstable_body(subscription_id, hash)
+                .await?;
+            let body = operation_result(sub).await?;
+            let extrinsics: Vec<Vec<u8>> = Decode::decode(&mut &*from_hex(&body)?)?;
+            Ok(extrinsics.into_iter().map(ChainBlockExtrinsic).collect())
+        }
+    }
+
+    /// Fetch the raw value stored at the given key in the given pinned block, optionally
+    /// from the child trie with the given key.
+    pub fn storage_raw(
+        &self,
+        hash: T::Hash,
+        key: &[u8],
+        child_key: Option<&[u8]>,
+    ) -> impl Future<Output = Result<Option<Vec<u8>>, Error>> + 'static {
+        let checked = self.check_pinned(hash);
+        let client = self.client.clone();
+        let subscription_id = self.subscription_id.clone();
+        let key = key.to_vec();
+        let child_key = child_key.map(|k| k.to_vec());
+        async move {
+            checked?;
+            let sub = client
+                .rpc()
+                .chainhead_unstable_storage(subscription_id, hash, &key, child_key.as_deref())
+                .await?;
+            operation_result(sub)
+                .await?
+                .map(|value| from_hex(&value))
+                .transpose()
+        }
+    }
+
+    /// Fetch and decode the value at the given storage address in the given pinned block.
+    pub fn storage<'address, Address>(
+        &self,
+        hash: T::Hash,
+        address: &'address Address,
+    ) -> impl Future<Output = Result<Option<Address::Target>, Error>> + 'address
+    where
+        Address: StorageAddress<IsFetchable = Yes> + 'address,
+    {
+        let checked = self.check_pinned(hash);
+        let client = self.client.clone();
+        let subscription_id = self.subscription_id.clone();
+        async move {
+            checked?;
+            let metadata = client.metadata();
+            let (pallet, entry) =
+                lookup_entry_details(address.pallet_name(), address.entry_name(), &metadata)?;
+            validate_storage_address(address, pallet)?;
+
+            let key = utils::storage_address_bytes(address, &metadata)?;
+            let sub = client
+                .rpc()
+                .chainhead_unstable_storage(subscription_id, hash, &key, None)
+                .await?;
+            let Some(value) = operation_result(sub).await? else {
+                return Ok(None);
+            };
+
+            let bytes = from_hex(&value)?;
+            let val =
+                decode_storage_with_metadata::<Address::Target>(&mut &*bytes, &metadata, entry)?;
+            Ok(Some(val))
+        }
+    }
+
+    /// Make a raw runtime API call in the given pinned block, handing back the
+    /// SCALE encoded result.
+    pub fn call_raw(
+        &self,
+        hash: T::Hash,
+        function: &str,
+        call_parameters: &[u8],
+    ) -> impl Future<Output = Result<Vec<u8>, Error>> + 'static {
+        let checked = self.check_pinned(hash);
+        let client = self.client.clone();
+        let subscription_id = self.subscription_id.clone();
+        let function = function.to_owned();
+        let call_parameters = call_parameters.to_vec();
+        async move {
+            checked?;
+            let sub = client
+                .rpc()
+                .chainhead_unstable_call(subscription_id, hash, function, &call_parameters)
+                .await?;
+            from_hex(&operation_result(sub).await?)
+        }
+    }
+
+    /// Make a runtime API call in the given pinned block, decoding the result.
+    pub fn call<Call: RuntimeApiPayload>(
+        &self,
+        hash: T::Hash,
+        payload: Call,
+    ) -> impl Future<Output = Result<Call::ReturnType, Error>> {
+        let checked = self.check_pinned(hash);
+        let client = self.client.clone();
+        let subscription_id = self.subscription_id.clone();
+        async move {
+            checked?;
+            let metadata = client.metadata();
+            let (call_name, params, output_ty) = encode_runtime_api_call(&payload, &metadata)?;
+
+            let sub = client
+                .rpc()
+                .chainhead_unstable_call(subscription_id, hash, call_name, &params)
+                .await?;
+            let bytes = from_hex(&operation_result(sub).await?)?;
+
+            let value = <Call::ReturnType as DecodeWithMetadata>::decode_with_metadata(
+                &mut &bytes[..],
+                output_ty,
+                &metadata,
+            )?;
+            Ok(value)
+        }
+    }
+
+    fn check_pinned(&self, hash: T::Hash) -> Result<(), Error> {
+        if self.stopped {
+            return Err(ChainHeadError::Stopped.into());
+        }
+        if !self.is_pinned(hash) {
+            return Err(ChainHeadError::NotPinned(hash_to_string(hash)).into());
+        }
+        Ok(())
+    }
+
+    fn pin(&mut self, hash: T::Hash, parent: Option<T::Hash>) {
+        if !self.is_pinned(hash) {
+            self.pinned.push(PinnedBlock { hash, parent });
+        }
+    }
+
+    // Blocks are only forgotten once the node has unpinned them, so that we keep track of
+    // any which failed to unpin. Every block is tried, and the first failure handed back.
+    async fn unpin(&mut self, hashes: Vec<T::Hash>) -> Result<(), Error> {
+        let mut result = Ok(());
+        for hash in hashes {
+            if !self.is_pinned(hash) {
+                continue;
+            }
+            let unpinned = self
+                .client
+                .rpc()
+                .chainhead_unstable_unpin(self.subscription_id.clone(), hash)
+                .await;
+            match unpinned {
+                Ok(()) => self.pinned.retain(|b| b.hash != hash),
+                Err(e) if result.is_ok() => result = Err(e),
+                Err(_) => {}
+            }
+        }
+        result
+    }
+
+    // Once stopped, the node forgets about the subscription and everything it pinned.
+    fn stop(&mut self) {
+        self.stopped = true;
+        self.pinned.clear();
+    }
+}
+
+/// Wait for the result of a `chainHead` body, storage or call operation.
+async fn operation_result<R: DeserializeOwned>(
+    mut sub: Subscription<ChainHeadEvent<R>>,
+) -> Result<R, Error> {
+    match sub.next().await {
+        Some(Ok(ChainHeadEvent::Done(done))) => Ok(done.result),
+        Some(Ok(ChainHeadEvent::Inaccessible(e))) => {
+            Err(ChainHeadError::Inaccessible(e.error).into())
+        }
+        Some(Ok(ChainHeadEvent::Error(e))) => Err(ChainHeadError::OperationFailed(e.error).into()),
+        Some(Ok(ChainHeadEvent::Disjoint)) => Err(ChainHeadError::Stopped.into()),
+        Some(Err(e)) => Err(e),
+        None => Err(ChainHeadError::NoResult.into()),
+    }
+}
+
+fn from_hex(value: &str) -> Result<Vec<u8>, Error> {
+    impl_serde::serialize::from_hex(value)
+        .map_err(|e| ChainHeadError::InvalidResponse(e.to_string()).into())
+}
+
+fn hash_to_string(hash: impl AsRef<[u8]>) -> String {
+    format!("0x{}", hex::encode(hash.as_ref()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::substrate::H256,
+        error::RpcError,
+        rpc::{
+            mock_rpc_client::{subscription, to_raw, MockRpcClient},
+            types::RuntimeVersion,
+        },
+        Metadata, OnlineClient, SubstrateConfig,
+    };
+    use codec::Encode;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    type Unpinned = Arc<Mutex<Vec<H256>>>;
+
+    fn h(n: u64) -> H256 {
+        H256::from_low_u64_be(n)
+    }
+
+    // A client whose `chainHead_follow` subscription hands back the given events. Blocks
+    // that are unpinned are recorded, except for the `failing` one, which can't be. Body,
+    // storage and call operations succeed, are inaccessible and fail respectively.
+    fn client(
+        events: Vec<serde_json::Value>,
+        unpinned: Unpinned,
+        failing: Option<H256>,
+    ) -> OnlineClient<SubstrateConfig> {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_tiny.scale").unwrap();
+        let metadata = Metadata::decode(&mut &*bytes).unwrap();
+
+        let rpc = MockRpcClient::new()
+            .on_request(move |method, params| {
+                assert_eq!(method, "chainHead_unstable_unpin");
+                let (id, hash): (String, H256) =
+                    serde_json::from_str(params.unwrap().get()).unwrap();
+                assert_eq!(id, "sub");
+                if Some(hash) == failing {
+                    return Err(RpcError::ClientError("unpin failed".into()));
+                }
+                unpinned.lock().unwrap().push(hash);
+                Ok(to_raw(()))
+            })
+            .on_subscribe(move |method| {
+                let events = match method {
+                    "chainHead_unstable_follow" => events.clone(),
+                    "chainHead_unstable_body" => {
+                        let body = vec![vec![1u8, 2], vec![3]].encode();
+                        let body = format!("0x{}", hex::encode(body));
+                        vec![json!({ "event": "done", "result": body })]
+                    }
+                    "chainHead_unstable_storage" => {
+                        vec![json!({ "event": "inaccessible", "error": "busy" })]
+                    }
+                    "chainHead_unstable_call" => {
+                        vec![json!({ "event": "error", "error": "oops" })]
+                    }
+                    _ => panic!("unexpected subscription {method}"),
+                };
+                Ok(subscription(
+                    events
+                        .into_iter()
+                        .map(|e| Ok(to_raw(e)))
+                        .collect::<Vec<_>>(),
+                ))
+            });
+
+        let runtime_version = RuntimeVersion {
+            spec_version: 1,
+            transaction_version: 1,
+            other: Default::default(),
+        };
+        OnlineClient::from_rpc_client_with(H256::zero(), runtime_version, metadata, Arc::new(rpc))
+            .unwrap()
+    }
+
+    fn initialized(n: u64) -> serde_json::Value {
+        json!({ "event": "initialized", "finalizedBlockHash": h(n) })
+    }
+
+    fn new_block(n: u64, parent: u64) -> serde_json::Value {
+        json!({ "event": "newBlock", "blockHash": h(n), "parentBlockHash": h(parent) })
+    }
+
+    fn finalized(finalized: &[u64], pruned: &[u64]) -> serde_json::Value {
+        let hashes = |ns: &[u64]| ns.iter().map(|n| h(*n)).collect::<Vec<_>>();
+        json!({
+            "event": "finalized",
+            "finalizedBlockHashes": hashes(finalized),
+            "prunedBlockHashes": hashes(pruned),
+        })
+    }
+
+    fn pinned(
+        follower: &ChainHeadFollower<SubstrateConfig, OnlineClient<SubstrateConfig>>,
+    ) -> Vec<H256> {
+        let mut pinned: Vec<_> = follower.pinned_block_hashes().collect();
+        pinned.sort();
+        pinned
+    }
+
+    #[tokio::test]
+    async fn tracks_and_unpins_blocks() {
+        let unpinned = Unpinned::default();
+        let events = vec![
+            initialized(1),
+            new_block(2, 1),
+            new_block(3, 2),
+            new_block(4, 2),
+            json!({ "event": "bestBlockChanged", "bestBlockHash": h(3) }),
+            finalized(&[2, 3], &[4]),
+            json!({ "event": "stop" }),
+        ];
+        let mut follower = ChainHeadFollower::new(client(events, unpinned.clone(), None), false)
+            .await
+            .unwrap();
+        assert_eq!(follower.subscription_id(), "sub");
+
+        for _ in 0..4 {
+            follower.next().await.unwrap().unwrap();
+        }
+        assert_eq!(pinned(&follower), vec![h(1), h(2), h(3), h(4)]);
+        assert_eq!(follower.parent_hash(h(4)), Some(h(2)));
+        assert_eq!(follower.parent_hash(h(1)), None);
+        assert_eq!(follower.finalized_block_hash(), Some(h(1)));
+
+        follower.next().await.unwrap().unwrap();
+        assert_eq!(follower.best_block_hash(), Some(h(3)));
+
+        // Everything but the latest finalized block is unpinned, including pruned forks.
+        follower.next().await.unwrap().unwrap();
+        assert_eq!(*unpinned.lock().unwrap(), vec![h(1), h(2), h(4)]);
+        assert_eq!(pinned(&follower), vec![h(3)]);
+        assert_eq!(follower.finalized_block_hash(), Some(h(3)));
+
+        // Once stopped, nothing is pinned and no more events are handed back.
+        assert_eq!(follower.next().await.unwrap().unwrap(), FollowEvent::Stop);
+        assert!(pinned(&follower).is_empty());
+        assert!(follower.next().await.is_none());
+        assert!(matches!(
+            follower.body(h(3)).await,
+            Err(Error::ChainHead(ChainHeadError::Stopped))
+        ));
+    }
+
+    #[tokio::test]
+    async fn blocks_which_fail_to_unpin_stay_pinned() {
+        let unpinned = Unpinned::default();
+        let events = vec![
+            initialized(1),
+            new_block(2, 1),
+            new_block(3, 2),
+            finalized(&[2, 3], &[]),
+        ];
+        let client = client(events, unpinned.clone(), Some(h(1)));
+        let mut follower = ChainHeadFollower::new(client, false).await.unwrap();
+        for _ in 0..3 {
+            follower.next().await.unwrap().unwrap();
+        }
+
+        // The failure is reported, but the other blocks are still unpinned.
+        assert!(follower.next().await.unwrap().is_err());
+        assert_eq!(*unpinned.lock().unwrap(), vec![h(2)]);
+        assert_eq!(pinned(&follower), vec![h(1), h(3)]);
+
+        // The subscription ending stops the follower too.
+        assert!(follower.next().await.is_none());
+        assert!(pinned(&follower).is_empty());
+    }
+
+    #[tokio::test]
+    async fn operation_results_are_handed_back() {
+        let events = vec![initialized(1)];
+        let client = client(events, Unpinned::default(), None);
+        let mut follower = ChainHeadFollower::new(client, false).await.unwrap();
+        follower.next().await.unwrap().unwrap();
+
+        let body = follower.body(h(1)).await.unwrap();
+        let body: Vec<_> = body.into_iter().map(|e| e.0).collect();
+        assert_eq!(body, vec![vec![1, 2], vec![3]]);
+
+        assert!(matches!(
+            follower.storage_raw(h(1), b"key", None).await,
+            Err(Error::ChainHead(ChainHeadError::Inaccessible(e))) if e == "busy"
+        ));
+        assert!(matches!(
+            follower.call_raw(h(1), "Core_version", &[]).await,
+            Err(Error::ChainHead(ChainHeadError::OperationFailed(e))) if e == "oops"
+        ));
+
+        // Blocks that aren't pinned can't be asked about.
+        assert!(matches!(
+            follower.body(h(2)).await,
+            Err(Error::ChainHead(ChainHeadError::NotPinned(_)))
+        ));
+    }
+}
@@ -0,0 +1,10 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! This module exposes a higher level interface over the unstable `chainHead` RPC methods,
+//! which takes care of tracking and unpinning the blocks that they report.
+
+mod chain_head_follower;
+
+pub use chain_head_follower::ChainHeadFollower;
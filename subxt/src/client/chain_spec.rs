@@ -0,0 +1,528 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Work out the genesis hash and runtime version of a chain without a node, so that an
+//! [`super::OfflineClient`] can be constructed from a chain spec and some metadata.
+
+use crate::{
+    config::Hasher,
+    error::{Error, MetadataError},
+    rpc::types::{RuntimeVersion, StorageData, StorageKey},
+    storage::ChildInfo,
+    Config, Metadata,
+};
+use codec::{Compact, Decode, Encode};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// Compute the genesis hash of a chain from its raw chain spec JSON. This is the hash of
+/// the genesis header, whose state root is the root of the genesis storage given in the
+/// chain spec.
+///
+/// The `state_version` is that of the runtime that the chain started with, which determines
+/// how the storage trie is laid out. This isn't necessarily the state version of the current
+/// runtime, since chains can migrate their storage to a new state version.
+pub fn genesis_hash_from_chain_spec<T: Config>(
+    chain_spec: &[u8],
+    state_version: u8,
+) -> Result<T::Hash, Error> {
+    let chain_spec: ChainSpec = serde_json::from_slice(chain_spec)?;
+    let Some(genesis) = chain_spec.genesis.raw else {
+        return Err(Error::Other(
+            "The chain spec must be a raw chain spec to compute the genesis hash from it"
+                .to_owned(),
+        ));
+    };
+
+    let mut top: BTreeMap<Vec<u8>, Vec<u8>> =
+        genesis.top.into_iter().map(|(k, v)| (k.0, v.0)).collect();
+
+    // The roots of any child tries are stored in the main trie, under their prefixed keys.
+    for (child_storage_key, child) in genesis.children_default {
+        if child.is_empty() {
+            continue;
+        }
+        let child: BTreeMap<Vec<u8>, Vec<u8>> =
+            child.into_iter().map(|(k, v)| (k.0, v.0)).collect();
+        let child_root = trie_root::<T::Hasher>(&child, state_version);
+        top.insert(
+            ChildInfo::new_default(child_storage_key.0).prefixed_storage_key(),
+            child_root.as_ref().to_vec(),
+        );
+    }
+
+    let state_root = trie_root::<T::Hasher>(&top, state_version);
+    // The genesis block has no extrinsics.
+    let extrinsics_root = trie_root::<T::Hasher>(&BTreeMap::new(), state_version);
+
+    // The genesis header has a zeroed parent hash, block number 0 and an empty digest.
+    let mut header = vec![0; state_root.as_ref().len()];
+    Compact(0u32).encode_to(&mut header);
+    header.extend_from_slice(state_root.as_ref());
+    header.extend_from_slice(extrinsics_root.as_ref());
+    Compact(0u32).encode_to(&mut header);
+
+    Ok(T::Hasher::hash(&header))
+}
+
+/// Obtain the [`RuntimeVersion`] of a runtime from its metadata, via the `System::Version`
+/// constant. The fields that aren't part of [`RuntimeVersion`] itself are given in
+/// [`RuntimeVersion::other`], as they would be by the `state_getRuntimeVersion` RPC method.
+pub fn runtime_version_from_metadata(metadata: &Metadata) -> Result<RuntimeVersion, Error> {
+    let version = metadata
+        .pallet_by_name_err("System")?
+        .constant_by_name("Version")
+        .ok_or_else(|| MetadataError::ConstantNameNotFound("Version".to_owned()))?;
+    let version = EncodedRuntimeVersion::decode(&mut version.value())?;
+
+    let apis: Vec<_> = version
+        .apis
+        .iter()
+        .map(|(id, version)| serde_json::json!([format!("0x{}", hex::encode(id)), version]))
+        .collect();
+    let other = HashMap::from([
+        ("specName".to_owned(), version.spec_name.into()),
+        ("implName".to_owned(), version.impl_name.into()),
+        (
+            "authoringVersion".to_owned(),
+            version.authoring_version.into(),
+        ),
+        ("implVersion".to_owned(), version.impl_version.into()),
+        ("apis".to_owned(), apis.into()),
+        ("stateVersion".to_owned(), version.state_version.into()),
+    ]);
+
+    Ok(RuntimeVersion {
+        spec_version: version.spec_version,
+        transaction_version: version.transaction_version,
+        other,
+    })
+}
+
+#[derive(Deserialize)]
+struct ChainSpec {
+    genesis: Genesis,
+}
+
+#[derive(Deserialize)]
+struct Genesis {
+    raw: Option<RawGenesis>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawGenesis {
+    top: BTreeMap<StorageKey, StorageData>,
+    #[serde(default)]
+    children_default: BTreeMap<StorageKey, BTreeMap<StorageKey, StorageData>>,
+}
+
+/// The SCALE encoding of `sp_version::RuntimeVersion`. The last two fields were added
+/// later on, and so may not be present.
+struct EncodedRuntimeVersion {
+    spec_name: String,
+    impl_name: String,
+    authoring_version: u32,
+    spec_version: u32,
+    impl_version: u32,
+    apis: Vec<([u8; 8], u32)>,
+    transaction_version: u32,
+    state_version: u8,
+}
+
+impl Decode for EncodedRuntimeVersion {
+    fn decode<I: codec::Input>(input: &mut I) -> Result<Self, codec::Error> {
+        let spec_name = String::decode(input)?;
+        let impl_name = String::decode(input)?;
+        let authoring_version = u32::decode(input)?;
+        let spec_version = u32::decode(input)?;
+        let impl_version = u32::decode(input)?;
+        let apis = Vec::decode(input)?;
+        let transaction_version = match input.remaining_len()? {
+            Some(0) => 1,
+            _ => u32::decode(input)?,
+        };
+        let state_version = match input.remaining_len()? {
+            Some(0) => 0,
+            _ => u8::decode(input)?,
+        };
+        Ok(EncodedRuntimeVersion {
+            spec_name,
+            impl_name,
+            authoring_version,
+            spec_version,
+            impl_version,
+            apis,
+            transaction_version,
+            state_version,
+        })
+    }
+}
+
+/// The encoding of an empty trie.
+const EMPTY_TRIE: u8 = 0;
+/// From state version 1, values at least this long are stored in the trie by hash.
+const TRIE_VALUE_NODE_THRESHOLD: usize = 33;
+/// Nodes whose encoding is shorter than this are inlined into their parent, rather
+/// than being referenced by hash.
+const INLINE_NODE_THRESHOLD: usize = 32;
+
+/// Compute the root of the base-16 Patricia Merkle trie holding the given entries, laid out
+/// as Substrate does for the given state version (see `sp_trie::LayoutV0` and `LayoutV1`).
+fn trie_root<H: Hasher>(entries: &BTreeMap<Vec<u8>, Vec<u8>>, state_version: u8) -> H::Output
+where
+    H::Output: AsRef<[u8]>,
+{
+    let entries: Vec<(Vec<u8>, &[u8])> = entries
+        .iter()
+        .map(|(key, value)| (nibbles(key), value.as_slice()))
+        .collect();
+    let hash_values = state_version > 0;
+    H::hash(&encode_node::<H>(&entries, 0, hash_values))
+}
+
+/// Encode the node holding the given entries, which are sorted by key and share their
+/// first `cursor` nibbles.
+fn encode_node<H: Hasher>(entries: &[(Vec<u8>, &[u8])], cursor: usize, hash_values: bool) -> Vec<u8>
+where
+    H::Output: AsRef<[u8]>,
+{
+    let Some(((first_key, first_value), rest)) = entries.split_first() else {
+        return vec![EMPTY_TRIE];
+    };
+
+    if rest.is_empty() {
+        let value = NodeValue::new::<H>(first_value, hash_values);
+        let kind = match value {
+            NodeValue::Inline(_) => NodeKind::Leaf,
+            NodeValue::Hashed(_) => NodeKind::HashedValueLeaf,
+        };
+        let mut out = node_header(kind, first_key.len() - cursor);
+        push_nibbles(&mut out, &first_key[cursor..]);
+        value.encode_to(&mut out);
+        return out;
+    }
+
+    // Everything shares the nibbles up to this point, which become the partial key of a
+    // branch. If the first key ends here, its value is the value of the branch.
+    let branch_cursor = rest
+        .iter()
+        .map(|(key, _)| shared_prefix_len(first_key, key))
+        .min()
+        .unwrap_or(cursor)
+        .max(cursor);
+    let (value, mut children) = if first_key.len() == branch_cursor {
+        (Some(NodeValue::new::<H>(first_value, hash_values)), rest)
+    } else {
+        (None, entries)
+    };
+
+    let kind = match value {
+        None => NodeKind::BranchNoValue,
+        Some(NodeValue::Inline(_)) => NodeKind::BranchWithValue,
+        Some(NodeValue::Hashed(_)) => NodeKind::HashedValueBranch,
+    };
+    let mut out = node_header(kind, branch_cursor - cursor);
+    push_nibbles(&mut out, &first_key[cursor..branch_cursor]);
+
+    let bitmap_index = out.len();
+    out.extend_from_slice(&[0, 0]);
+    if let Some(value) = value {
+        value.encode_to(&mut out);
+    }
+
+    // The entries are sorted, so each child's entries are next to each other.
+    let mut bitmap = 0u16;
+    while let Some((key, _)) = children.first() {
+        let nibble = key[branch_cursor];
+        let count = children
+            .iter()
+            .take_while(|(key, _)| key[branch_cursor] == nibble)
+            .count();
+        let (child, remaining) = children.split_at(count);
+        children = remaining;
+
+        bitmap |= 1 << nibble;
+        let child = encode_node::<H>(child, branch_cursor + 1, hash_values);
+        if child.len() < INLINE_NODE_THRESHOLD {
+            child.encode_to(&mut out);
+        } else {
+            H::hash(&child).as_ref().encode_to(&mut out);
+        }
+    }
+    out[bitmap_index..bitmap_index + 2].copy_from_slice(&bitmap.to_le_bytes());
+
+    out
+}
+
+enum NodeValue<'a, H> {
+    Inline(&'a [u8]),
+    Hashed(H),
+}
+
+impl<'a, H: AsRef<[u8]>> NodeValue<'a, H> {
+    fn new<Hs: Hasher<Output = H>>(value: &'a [u8], hash_values: bool) -> Self {
+        if hash_values && value.len() >= TRIE_VALUE_NODE_THRESHOLD {
+            NodeValue::Hashed(Hs::hash(value))
+        } else {
+            NodeValue::Inline(value)
+        }
+    }
+
+    fn encode_to(&self, out: &mut Vec<u8>) {
+        match self {
+            NodeValue::Inline(value) => value.encode_to(out),
+            NodeValue::Hashed(hash) => out.extend_from_slice(hash.as_ref()),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum NodeKind {
+    Leaf,
+    BranchNoValue,
+    BranchWithValue,
+    HashedValueLeaf,
+    HashedValueBranch,
+}
+
+/// Encode the header of a node, which gives its kind and the number of nibbles in its
+/// partial key. The count is spread over subsequent bytes if it doesn't fit in the first.
+fn node_header(kind: NodeKind, nibble_count: usize) -> Vec<u8> {
+    let (prefix, prefix_bits) = match kind {
+        NodeKind::Leaf => (0b01 << 6, 2),
+        NodeKind::BranchNoValue => (0b10 << 6, 2),
+        NodeKind::BranchWithValue => (0b11 << 6, 2),
+        NodeKind::HashedValueLeaf => (0b001 << 5, 3),
+        NodeKind::HashedValueBranch => (0b0001 << 4, 4),
+    };
+    let max = (u8::MAX >> prefix_bits) as usize;
+
+    if nibble_count < max {
+        return vec![prefix | nibble_count as u8];
+    }
+
+    let mut out = vec![prefix | max as u8];
+    let mut remaining = nibble_count - (max - 1);
+    while remaining >= 256 {
+        out.push(u8::MAX);
+        remaining -= 255;
+    }
+    out.push((remaining - 1) as u8);
+    out
+}
+
+fn nibbles(key: &[u8]) -> Vec<u8> {
+    key.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect()
+}
+
+/// Pack nibbles two to a byte. An odd nibble out is placed on its own at the start.
+fn push_nibbles(out: &mut Vec<u8>, nibbles: &[u8]) {
+    let (odd, rest) = nibbles.split_at(nibbles.len() % 2);
+    out.extend_from_slice(odd);
+    out.extend(rest.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+}
+
+fn shared_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{config::substrate::BlakeTwo256, PolkadotConfig};
+    use sp_runtime::{
+        generic::Header as SpHeader,
+        traits::{BlakeTwo256 as SpBlakeTwo256, Hash as _, Header as _},
+        StateVersion,
+    };
+
+    type Entries = BTreeMap<Vec<u8>, Vec<u8>>;
+
+    fn sp_state_version(state_version: u8) -> StateVersion {
+        match state_version {
+            0 => StateVersion::V0,
+            _ => StateVersion::V1,
+        }
+    }
+
+    // The root that Substrate itself computes for the given entries.
+    fn sp_trie_root(entries: &Entries, state_version: u8) -> [u8; 32] {
+        let entries = entries.clone().into_iter().collect();
+        SpBlakeTwo256::trie_root(entries, sp_state_version(state_version)).0
+    }
+
+    fn assert_trie_root_matches(entries: &Entries) {
+        for state_version in [0, 1] {
+            assert_eq!(
+                trie_root::<BlakeTwo256>(entries, state_version).0,
+                sp_trie_root(entries, state_version),
+                "state version {state_version}, entries {entries:?}"
+            );
+        }
+    }
+
+    fn entries<'a>(entries: impl IntoIterator<Item = (&'a [u8], Vec<u8>)>) -> Entries {
+        entries.into_iter().map(|(k, v)| (k.to_vec(), v)).collect()
+    }
+
+    #[test]
+    fn empty_trie_root() {
+        let root = trie_root::<BlakeTwo256>(&BTreeMap::new(), 1);
+        assert_eq!(
+            hex::encode(root),
+            "03170a2e7597b7b7e3d84c05391d139a62b157e78786d8c082f29dcf4c111314"
+        );
+    }
+
+    #[test]
+    fn node_header_spills_long_partial_keys() {
+        assert_eq!(node_header(NodeKind::Leaf, 62), vec![0x40 | 62]);
+        assert_eq!(node_header(NodeKind::Leaf, 63), vec![0x40 | 63, 0]);
+        assert_eq!(node_header(NodeKind::Leaf, 64), vec![0x40 | 63, 1]);
+        assert_eq!(
+            node_header(NodeKind::Leaf, 62 + 256),
+            vec![0x40 | 63, 255, 0]
+        );
+        assert_eq!(node_header(NodeKind::HashedValueBranch, 3), vec![0x10 | 3]);
+    }
+
+    #[test]
+    fn trie_root_matches_substrate_for_long_partial_keys() {
+        // A single leaf whose partial key is 80 nibbles long.
+        assert_trie_root_matches(&entries([(&[0xab; 40][..], vec![1])]));
+
+        // A branch whose partial key is 70 nibbles long, with leaves that are too.
+        let mut a = [0x12; 70];
+        let mut b = [0x12; 70];
+        a[35] = 0x30;
+        b[35] = 0x40;
+        assert_trie_root_matches(&entries([(&a[..], vec![1]), (&b[..], vec![2])]));
+
+        // And longer still, spilling the partial key length over multiple bytes.
+        assert_trie_root_matches(&entries([(&[0x01; 300][..], vec![3]), (&[][..], vec![4])]));
+    }
+
+    #[test]
+    fn trie_root_matches_substrate_around_the_value_threshold() {
+        for len in [0, 1, 31, 32, 33, 34, 100] {
+            // As a leaf on its own..
+            assert_trie_root_matches(&entries([(&b"key"[..], vec![7; len])]));
+            // ..and as the value of a branch, with leaves of either side of the threshold.
+            assert_trie_root_matches(&entries([
+                (&b"key"[..], vec![7; len]),
+                (&b"key1"[..], vec![8; 32]),
+                (&b"key2"[..], vec![9; 33]),
+                (&b"key3"[..], vec![10; 34]),
+            ]));
+        }
+    }
+
+    #[test]
+    fn trie_root_matches_substrate_for_nested_branches() {
+        // Branches with and without values at several depths, including the root, and
+        // children which are small enough to be inlined as well as those which aren't.
+        assert_trie_root_matches(&entries([
+            (&b""[..], vec![0]),
+            (&b"a"[..], vec![1]),
+            (&b"ab"[..], vec![2; 40]),
+            (&b"abc"[..], vec![3]),
+            (&b"abd"[..], vec![4; 33]),
+            (&b"abdd"[..], vec![5]),
+            (&b"ac"[..], vec![]),
+            (&b"b"[..], vec![6; 10]),
+            (&b"\xff\xff"[..], vec![7]),
+        ]));
+
+        // Lots of keys and values of various lengths.
+        let many = (0u32..500)
+            .map(|n| {
+                let hash = sp_core_hashing::blake2_256(&n.encode());
+                let key = hash[..1 + hash[0] as usize % 31].to_vec();
+                let value = vec![hash[1]; hash[2] as usize % 70];
+                (key, value)
+            })
+            .collect();
+        assert_trie_root_matches(&many);
+    }
+
+    #[test]
+    fn genesis_hash_matches_substrate() {
+        let top = entries([(&b":code"[..], vec![1, 2, 3]), (&b"foo"[..], vec![4; 40])]);
+        let child = entries([(&b"bar"[..], vec![5; 33]), (&b"baz"[..], vec![6])]);
+        let to_json = |entries: &Entries| -> serde_json::Map<String, serde_json::Value> {
+            entries
+                .iter()
+                .map(|(k, v)| (to_hex(k), to_hex(v).into()))
+                .collect()
+        };
+        let chain_spec = serde_json::json!({
+            "name": "Test",
+            "genesis": {
+                "raw": {
+                    "top": to_json(&top),
+                    "childrenDefault": {
+                        to_hex(b"child"): to_json(&child),
+                        to_hex(b"empty"): {},
+                    },
+                },
+            },
+        });
+        let chain_spec = serde_json::to_vec(&chain_spec).unwrap();
+
+        for state_version in [0, 1] {
+            // Substrate stores the root of each child trie in the main trie.
+            let mut top = top.clone();
+            let child_key = ChildInfo::new_default(b"child".to_vec()).prefixed_storage_key();
+            top.insert(child_key, sp_trie_root(&child, state_version).to_vec());
+
+            let header = SpHeader::<u32, SpBlakeTwo256>::new(
+                0,
+                sp_trie_root(&Entries::new(), state_version).into(),
+                sp_trie_root(&top, state_version).into(),
+                Default::default(),
+                Default::default(),
+            );
+            let hash =
+                genesis_hash_from_chain_spec::<PolkadotConfig>(&chain_spec, state_version).unwrap();
+            assert_eq!(hash.0, header.hash().0);
+        }
+    }
+
+    fn to_hex(bytes: &[u8]) -> String {
+        format!("0x{}", hex::encode(bytes))
+    }
+
+    #[test]
+    fn genesis_hash_from_raw_chain_spec() {
+        let chain_spec = br#"{
+            "name": "Test",
+            "genesis": { "raw": { "top": { "0x3a636f6465": "0x0102" }, "childrenDefault": {} } }
+        }"#;
+        let hash = genesis_hash_from_chain_spec::<PolkadotConfig>(chain_spec, 0).unwrap();
+
+        // The genesis hash changes with the genesis storage.
+        let other_chain_spec = br#"{
+            "name": "Test",
+            "genesis": { "raw": { "top": { "0x3a636f6465": "0x0103" }, "childrenDefault": {} } }
+        }"#;
+        let other_hash =
+            genesis_hash_from_chain_spec::<PolkadotConfig>(other_chain_spec, 0).unwrap();
+        assert_ne!(hash, other_hash);
+
+        // A chain spec that isn't raw can't be used.
+        let chain_spec = br#"{ "name": "Test", "genesis": { "runtime": {} } }"#;
+        assert!(genesis_hash_from_chain_spec::<PolkadotConfig>(chain_spec, 0).is_err());
+    }
+
+    #[test]
+    fn runtime_version_from_system_version_constant() {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_small.scale").unwrap();
+        let metadata = Metadata::decode(&mut &*bytes).unwrap();
+        let runtime_version = runtime_version_from_metadata(&metadata).unwrap();
+
+        assert!(runtime_version.spec_version > 0);
+        assert!(runtime_version.other.contains_key("specName"));
+    }
+}
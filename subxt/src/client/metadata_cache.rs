@@ -0,0 +1,132 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use super::MetadataVersion;
+use crate::{error::Error, Metadata};
+use codec::Decode;
+use std::path::{Path, PathBuf};
+
+/// A cache of SCALE encoded metadata in some directory on disk, keyed by the genesis hash
+/// of the chain, the spec version of the runtime that the metadata belongs to and the
+/// [`MetadataVersion`] that was fetched.
+///
+/// Handing this to [`crate::OnlineClient::from_rpc_client_with_metadata_cache()`] means
+/// that the metadata only needs downloading from the node the first time that a client
+/// sees a given runtime, rather than every time that a client is constructed.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use std::sync::Arc;
+/// use subxt::{client::MetadataCache, OnlineClient, PolkadotConfig};
+///
+/// let rpc_client = subxt::client::default_rpc_client("ws://127.0.0.1:9944")
+///     .await
+///     .unwrap();
+/// let cache = MetadataCache::new("/tmp/subxt-metadata");
+///
+/// let api = OnlineClient::<PolkadotConfig>::from_rpc_client_with_metadata_cache(
+///     Arc::new(rpc_client),
+///     cache,
+/// )
+/// .await
+/// .unwrap();
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct MetadataCache {
+    dir: PathBuf,
+}
+
+impl MetadataCache {
+    /// Cache metadata in the given directory. The directory is created when
+    /// something is first stored in it, if it doesn't exist already.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// The directory that metadata is cached in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Load the metadata for the given chain, spec version and metadata version, if it's
+    /// been cached.
+    pub fn load(
+        &self,
+        genesis_hash: &[u8],
+        spec_version: u32,
+        version: MetadataVersion,
+    ) -> Result<Option<Metadata>, Error> {
+        let bytes = match std::fs::read(self.path(genesis_hash, spec_version, version)) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let metadata = Metadata::decode(&mut &*bytes)?;
+        Ok(Some(metadata))
+    }
+
+    /// Store the given SCALE encoded metadata for the given chain, spec version and metadata
+    /// version, replacing anything that was cached for them already.
+    pub fn store(
+        &self,
+        genesis_hash: &[u8],
+        spec_version: u32,
+        version: MetadataVersion,
+        metadata_bytes: &[u8],
+    ) -> Result<(), Error> {
+        std::fs::create_dir_all(&self.dir)?;
+
+        // Write to a temporary file and move it into place, so that another process
+        // reading from the cache at the same time never sees a partially written file.
+        let path = self.path(genesis_hash, spec_version, version);
+        let tmp_path = path.with_extension(format!("scale.{}.tmp", std::process::id()));
+        std::fs::write(&tmp_path, metadata_bytes)?;
+        std::fs::rename(&tmp_path, &path)?;
+        Ok(())
+    }
+
+    fn path(&self, genesis_hash: &[u8], spec_version: u32, version: MetadataVersion) -> PathBuf {
+        let version = match version {
+            MetadataVersion::V14 => "v14",
+            MetadataVersion::V15 => "v15",
+            MetadataVersion::Unstable => "unstable",
+        };
+        self.dir.join(format!(
+            "{}-{spec_version}-{version}.scale",
+            hex::encode(genesis_hash)
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stores_and_loads_metadata() {
+        let dir = std::env::temp_dir().join(format!("subxt-metadata-{}", std::process::id()));
+        let cache = MetadataCache::new(&dir);
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_tiny.scale").unwrap();
+
+        let v14 = MetadataVersion::V14;
+
+        assert!(cache.load(&[1; 32], 100, v14).unwrap().is_none());
+        cache.store(&[1; 32], 100, v14, &bytes).unwrap();
+        assert!(cache.load(&[1; 32], 100, v14).unwrap().is_some());
+
+        // Other spec versions, chains and metadata versions aren't affected.
+        assert!(cache.load(&[1; 32], 101, v14).unwrap().is_none());
+        assert!(cache.load(&[2; 32], 100, v14).unwrap().is_none());
+        assert!(cache
+            .load(&[1; 32], 100, MetadataVersion::V15)
+            .unwrap()
+            .is_none());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -8,16 +8,25 @@
 //! require network access. The [`OnlineClient`] requires network
 //! access.
 
+mod chain_spec;
+mod metadata_cache;
 mod offline_client;
 mod online_client;
+mod online_client_builder;
 
+pub use chain_spec::{genesis_hash_from_chain_spec, runtime_version_from_metadata};
+pub use metadata_cache::MetadataCache;
 pub use offline_client::{OfflineClient, OfflineClientT};
 pub use online_client::{
     ClientRuntimeUpdater, OnlineClient, OnlineClientT, RuntimeUpdaterStream, Update, UpgradeError,
 };
+pub use online_client_builder::{MetadataVersion, OnlineClientBuilder};
 
 #[cfg(any(
     feature = "jsonrpsee-ws",
     all(feature = "jsonrpsee-web", target_arch = "wasm32")
 ))]
 pub use online_client::default_rpc_client;
+
+#[cfg(any(feature = "reconnecting-rpc-client", feature = "failover-rpc-client"))]
+pub(crate) use online_client::jsonrpsee_helpers;
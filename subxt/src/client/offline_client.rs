@@ -2,13 +2,15 @@
 // This file is dual-licensed as Apache-2.0 or GPL-3.0.
 // see LICENSE for license details.
 
+use super::chain_spec;
 use crate::{
-    blocks::BlocksClient, constants::ConstantsClient, events::EventsClient,
+    blocks::BlocksClient, constants::ConstantsClient, error::Error, events::EventsClient,
     rpc::types::RuntimeVersion, runtime_api::RuntimeApiClient, storage::StorageClient,
     tx::TxClient, Config, Metadata,
 };
+use codec::Decode;
 use derivative::Derivative;
-use std::sync::Arc;
+use std::{path::Path, sync::Arc};
 
 /// A trait representing a client that can perform
 /// offline-only actions.
@@ -84,6 +86,43 @@ impl<T: Config> OfflineClient<T> {
         }
     }
 
+    /// Construct a new [`OfflineClient`] from the raw chain spec JSON of a chain and the
+    /// metadata of its current runtime, for instance on a machine that signs transactions
+    /// but can't reach a node.
+    ///
+    /// The genesis hash is computed from the genesis storage in the chain spec, and the
+    /// runtime version is taken from the `System::Version` constant in the metadata.
+    ///
+    /// The genesis storage is laid out according to `genesis_state_version`, which is the
+    /// state version of the runtime that the chain *started* with. This can differ from that
+    /// of the current runtime; Polkadot, Kusama and Westend, for instance, started with state
+    /// version 0 and migrated to state version 1 later on. Giving the wrong state version
+    /// leads to the wrong genesis hash, and so to transactions with invalid signatures.
+    pub fn from_chain_spec(
+        chain_spec: &[u8],
+        genesis_state_version: u8,
+        metadata: impl Into<Metadata>,
+    ) -> Result<OfflineClient<T>, Error> {
+        let metadata = metadata.into();
+        let runtime_version = chain_spec::runtime_version_from_metadata(&metadata)?;
+        let genesis_hash =
+            chain_spec::genesis_hash_from_chain_spec::<T>(chain_spec, genesis_state_version)?;
+        Ok(OfflineClient::new(genesis_hash, runtime_version, metadata))
+    }
+
+    /// Construct a new [`OfflineClient`] as [`OfflineClient::from_chain_spec()`] does,
+    /// reading the raw chain spec JSON and the SCALE encoded metadata from the given files.
+    pub fn from_chain_spec_files(
+        chain_spec_path: impl AsRef<Path>,
+        genesis_state_version: u8,
+        metadata_path: impl AsRef<Path>,
+    ) -> Result<OfflineClient<T>, Error> {
+        let chain_spec = std::fs::read(chain_spec_path)?;
+        let metadata_bytes = std::fs::read(metadata_path)?;
+        let metadata = Metadata::decode(&mut &*metadata_bytes)?;
+        Self::from_chain_spec(&chain_spec, genesis_state_version, metadata)
+    }
+
     /// Return the genesis hash.
     pub fn genesis_hash(&self) -> T::Hash {
         self.inner.genesis_hash
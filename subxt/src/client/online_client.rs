@@ -2,14 +2,15 @@
 // This file is dual-licensed as Apache-2.0 or GPL-3.0.
 // see LICENSE for license details.
 
-use super::{OfflineClient, OfflineClientT};
+use super::{MetadataCache, MetadataVersion, OfflineClient, OfflineClientT, OnlineClientBuilder};
 use crate::{
-    blocks::BlocksClient,
+    blocks::{BlocksClient, HistoricMetadata},
     constants::ConstantsClient,
-    error::Error,
+    error::{Error, RpcError},
     events::EventsClient,
+    metadata::MetadataDiff,
     rpc::{
-        types::{RuntimeVersion, Subscription},
+        types::{RpcMethods, RuntimeVersion, Subscription},
         Rpc, RpcClientT,
     },
     runtime_api::RuntimeApiClient,
@@ -17,8 +18,8 @@ use crate::{
     tx::TxClient,
     Config, Metadata,
 };
+use codec::{Decode, Encode};
 use derivative::Derivative;
-use futures::future;
 use std::sync::{Arc, RwLock};
 
 /// A trait representing a client that can perform
@@ -26,6 +27,12 @@ use std::sync::{Arc, RwLock};
 pub trait OnlineClientT<T: Config>: OfflineClientT<T> {
     /// Return an RPC client that can be used to communicate with a node.
     fn rpc(&self) -> &Rpc<T>;
+
+    /// The block that calls like `at_latest()` should use in place of the latest block,
+    /// if the client has been pinned to one (see [`OnlineClientBuilder::pin_to_block()`]).
+    fn pinned_block_hash(&self) -> Option<T::Hash> {
+        None
+    }
 }
 
 /// A client that can be used to perform API calls (that is, either those
@@ -35,6 +42,37 @@ pub trait OnlineClientT<T: Config>: OfflineClientT<T> {
 pub struct OnlineClient<T: Config> {
     inner: Arc<RwLock<Inner<T>>>,
     rpc: Rpc<T>,
+    options: Arc<ClientOptions<T>>,
+    // Shared by the blocks clients that we hand out, so that they only fetch the
+    // metadata of each historic runtime once.
+    historic_metadata: HistoricMetadata,
+}
+
+/// The metadata versions that are asked for by default.
+#[cfg(feature = "unstable-metadata")]
+pub(super) const DEFAULT_METADATA_VERSIONS: &[MetadataVersion] =
+    &[MetadataVersion::Unstable, MetadataVersion::V14];
+/// The metadata versions that are asked for by default.
+#[cfg(not(feature = "unstable-metadata"))]
+pub(super) const DEFAULT_METADATA_VERSIONS: &[MetadataVersion] = &[MetadataVersion::V14];
+
+// Settings which are fixed when a client is built, and shared between its clones.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub(super) struct ClientOptions<T: Config> {
+    pub(super) metadata_versions: Vec<MetadataVersion>,
+    pub(super) metadata_cache: Option<MetadataCache>,
+    pub(super) pinned_block_hash: Option<T::Hash>,
+}
+
+impl<T: Config> Default for ClientOptions<T> {
+    fn default() -> Self {
+        Self {
+            metadata_versions: DEFAULT_METADATA_VERSIONS.to_vec(),
+            metadata_cache: None,
+            pinned_block_hash: None,
+        }
+    }
 }
 
 #[derive(Derivative)]
@@ -50,6 +88,7 @@ impl<T: Config> std::fmt::Debug for OnlineClient<T> {
         f.debug_struct("Client")
             .field("rpc", &"RpcClient")
             .field("inner", &self.inner)
+            .field("options", &self.options)
             .finish()
     }
 }
@@ -87,20 +126,35 @@ impl<T: Config> OnlineClient<T> {
 }
 
 impl<T: Config> OnlineClient<T> {
+    /// Configure and construct a new [`OnlineClient`]. See [`OnlineClientBuilder`].
+    pub fn builder() -> OnlineClientBuilder<T> {
+        OnlineClientBuilder::new()
+    }
+
     /// Construct a new [`OnlineClient`] by providing an underlying [`RpcClientT`]
     /// implementation to drive the connection.
     pub async fn from_rpc_client<R: RpcClientT>(
         rpc_client: Arc<R>,
     ) -> Result<OnlineClient<T>, Error> {
-        let rpc = Rpc::<T>::new(rpc_client.clone());
-        let (genesis_hash, runtime_version, metadata) = future::join3(
-            rpc.genesis_hash(),
-            rpc.runtime_version(None),
-            OnlineClient::fetch_metadata(&rpc),
-        )
-        .await;
+        OnlineClientBuilder::new().build(rpc_client).await
+    }
 
-        OnlineClient::from_rpc_client_with(genesis_hash?, runtime_version?, metadata?, rpc_client)
+    /// Construct a new [`OnlineClient`] by providing an underlying [`RpcClientT`]
+    /// implementation to drive the connection, and a [`MetadataCache`] to load metadata
+    /// from.
+    ///
+    /// The runtime version is fetched first, and if metadata for it is found in the cache
+    /// then it is used rather than being downloaded from the node. Otherwise, it's downloaded
+    /// and stored in the cache for next time. Metadata for any runtime upgrades that are
+    /// seen by the [`ClientRuntimeUpdater`] is cached in the same way.
+    pub async fn from_rpc_client_with_metadata_cache<R: RpcClientT>(
+        rpc_client: Arc<R>,
+        metadata_cache: MetadataCache,
+    ) -> Result<OnlineClient<T>, Error> {
+        OnlineClientBuilder::new()
+            .metadata_cache(metadata_cache)
+            .build(rpc_client)
+            .await
     }
 
     /// Construct a new [`OnlineClient`] by providing all of the underlying details needed
@@ -121,31 +175,124 @@ impl<T: Config> OnlineClient<T> {
         metadata: impl Into<Metadata>,
         rpc_client: Arc<R>,
     ) -> Result<OnlineClient<T>, Error> {
-        Ok(OnlineClient {
+        Ok(OnlineClient::from_parts(
+            genesis_hash,
+            runtime_version,
+            metadata.into(),
+            Rpc::new(rpc_client),
+            ClientOptions::default(),
+        ))
+    }
+
+    pub(super) fn from_parts(
+        genesis_hash: T::Hash,
+        runtime_version: RuntimeVersion,
+        metadata: Metadata,
+        rpc: Rpc<T>,
+        options: ClientOptions<T>,
+    ) -> OnlineClient<T> {
+        OnlineClient {
             inner: Arc::new(RwLock::new(Inner {
                 genesis_hash,
                 runtime_version,
-                metadata: metadata.into(),
+                metadata,
             })),
-            rpc: Rpc::new(rpc_client),
-        })
+            rpc,
+            options: Arc::new(options),
+            historic_metadata: HistoricMetadata::default(),
+        }
     }
 
-    /// Fetch the metadata from substrate using the runtime API.
-    async fn fetch_metadata(rpc: &Rpc<T>) -> Result<Metadata, Error> {
-        #[cfg(feature = "unstable-metadata")]
-        {
-            // Try to fetch the latest unstable metadata, if that fails fall back to
-            // fetching the latest stable metadata.
-            const V15_METADATA_VERSION: u32 = u32::MAX;
-            match rpc.metadata_at_version(V15_METADATA_VERSION).await {
-                Ok(bytes) => Ok(bytes),
-                Err(_) => rpc.metadata().await,
+    /// Fetch the metadata from substrate using the runtime API, trying each of the
+    /// given versions in turn.
+    pub(super) async fn fetch_metadata(
+        rpc: &Rpc<T>,
+        versions: &[MetadataVersion],
+        at: Option<T::Hash>,
+    ) -> Result<Metadata, Error> {
+        let (metadata, _) = OnlineClient::fetch_metadata_and_bytes(rpc, versions, at).await?;
+        Ok(metadata)
+    }
+
+    /// Fetch the metadata from substrate using the runtime API, trying each of the
+    /// given versions in turn, and hand back the SCALE encoded bytes alongside it.
+    async fn fetch_metadata_and_bytes(
+        rpc: &Rpc<T>,
+        versions: &[MetadataVersion],
+        at: Option<T::Hash>,
+    ) -> Result<(Metadata, Vec<u8>), Error> {
+        let mut last_err = None;
+        for &version in versions {
+            let bytes = match version.at_version() {
+                None => rpc
+                    .state_call::<frame_metadata::OpaqueMetadata>("Metadata_metadata", None, at)
+                    .await
+                    .map(|opaque| opaque.0),
+                Some(n) => rpc
+                    .state_call::<Option<frame_metadata::OpaqueMetadata>>(
+                        "Metadata_metadata_at_version",
+                        Some(&n.encode()),
+                        at,
+                    )
+                    .await
+                    .and_then(|opaque| {
+                        opaque
+                            .map(|opaque| opaque.0)
+                            .ok_or_else(|| Error::Other("Metadata version not found".into()))
+                    }),
+            };
+            let res = bytes.and_then(|bytes| {
+                let metadata = Metadata::decode(&mut &*bytes)?;
+                Ok((metadata, bytes))
+            });
+
+            match res {
+                Ok(res) => return Ok(res),
+                Err(e) => {
+                    tracing::debug!("Failed to fetch {version:?} metadata: {e}");
+                    last_err = Some(e);
+                }
             }
         }
+        Err(last_err.unwrap_or_else(|| Error::Other("No metadata versions to fetch".into())))
+    }
+
+    /// Load the metadata for the given runtime from the cache, or failing that, fetch it
+    /// from the node and cache it. Each of the given versions is tried in turn, first from
+    /// the cache and then from the node, so that a version that's preferred is never passed
+    /// over in favour of a less preferred version which happens to be cached.
+    pub(super) async fn fetch_metadata_cached(
+        rpc: &Rpc<T>,
+        metadata_cache: &MetadataCache,
+        versions: &[MetadataVersion],
+        genesis_hash: T::Hash,
+        spec_version: u32,
+        at: Option<T::Hash>,
+    ) -> Result<Metadata, Error> {
+        let mut last_err = None;
+        for &version in versions {
+            match metadata_cache.load(genesis_hash.as_ref(), spec_version, version) {
+                Ok(Some(metadata)) => return Ok(metadata),
+                Ok(None) => {}
+                // A broken cache entry is no reason to fail; we'll just overwrite it.
+                Err(e) => tracing::warn!("Failed to load cached metadata: {e}"),
+            }
 
-        #[cfg(not(feature = "unstable-metadata"))]
-        rpc.metadata().await
+            let (metadata, bytes) =
+                match OnlineClient::fetch_metadata_and_bytes(rpc, &[version], at).await {
+                    Ok(res) => res,
+                    Err(e) => {
+                        last_err = Some(e);
+                        continue;
+                    }
+                };
+            let stored = metadata_cache.store(genesis_hash.as_ref(), spec_version, version, &bytes);
+            if let Err(e) = stored {
+                tracing::warn!("Failed to cache metadata: {e}");
+            }
+            return Ok(metadata);
+        }
+        Err(last_err.unwrap_or_else(|| Error::Other("No metadata versions to fetch".into())))
     }
 
     /// Create an object which can be used to keep the runtime up to date
@@ -249,6 +396,16 @@ impl<T: Config> OnlineClient<T> {
         &self.rpc
     }
 
+    /// Return the RPC methods that the node exposes. These are fetched the first time
+    /// that this is called and cached from then on; see [`Rpc::supported_methods()`].
+    ///
+    /// APIs like [`crate::tx::SubmittableExtrinsic::dry_run()`] use these to pick between
+    /// the RPC methods that they could call, failing with
+    /// [`RpcError::MethodNotSupported`] if the node exposes none of them.
+    pub async fn rpc_methods(&self) -> Result<Arc<RpcMethods>, Error> {
+        self.rpc.supported_methods().await
+    }
+
     /// Return an offline client with the same configuration as this.
     pub fn offline(&self) -> OfflineClient<T> {
         let inner = self.inner.read().expect("shouldn't be poisoned");
@@ -303,12 +460,18 @@ impl<T: Config> OfflineClientT<T> for OnlineClient<T> {
     fn runtime_version(&self) -> RuntimeVersion {
         self.runtime_version()
     }
+    fn blocks(&self) -> BlocksClient<T, Self> {
+        BlocksClient::new_with_historic_metadata(self.clone(), self.historic_metadata.clone())
+    }
 }
 
 impl<T: Config> OnlineClientT<T> for OnlineClient<T> {
     fn rpc(&self) -> &Rpc<T> {
         &self.rpc
     }
+    fn pinned_block_hash(&self) -> Option<T::Hash> {
+        self.options.pinned_block_hash
+    }
 }
 
 /// Client wrapper for performing runtime updates. See [`OnlineClient::updater()`]
@@ -381,23 +544,64 @@ pub struct RuntimeUpdaterStream<T: Config> {
 
 impl<T: Config> RuntimeUpdaterStream<T> {
     /// Get the next element of the stream.
+    ///
+    /// The metadata and runtime version of each update are both taken from the latest
+    /// block, and so the runtime version may be newer than the one that the node reported.
     pub async fn next(&mut self) -> Option<Result<Update, Error>> {
-        let maybe_runtime_version = self.stream.next().await?;
-
-        let runtime_version = match maybe_runtime_version {
-            Ok(runtime_version) => runtime_version,
-            Err(err) => return Some(Err(err)),
+        let runtime_version = loop {
+            match self.stream.next().await? {
+                Ok(runtime_version) => break runtime_version,
+                // The subscription will be re-established and begin by handing back
+                // the current runtime version, so there's nothing to do here.
+                Err(Error::Rpc(RpcError::DisconnectedWillReconnect(_))) => continue,
+                Err(err) => return Some(Err(err)),
+            }
         };
 
-        let metadata = match self.client.rpc().metadata().await {
-            Ok(metadata) => metadata,
-            Err(err) => return Some(Err(err)),
+        Some(self.fetch_update(runtime_version).await)
+    }
+
+    // The subscription may report a runtime version for a block which is no longer the
+    // latest by the time we get here, so the metadata is fetched at a specific block and
+    // the runtime version is checked again there. This way the metadata that's handed back
+    // (and cached) always belongs to the runtime version that it's handed back with.
+    async fn fetch_update(&self, reported: RuntimeVersion) -> Result<Update, Error> {
+        let rpc = self.client.rpc();
+        let at = rpc
+            .block_hash(None)
+            .await?
+            .expect("didn't pass a block number; qed");
+        let runtime_version = rpc.runtime_version(Some(at)).await?;
+        if runtime_version != reported {
+            tracing::debug!(
+                "Runtime version {} was reported, but block {at:?} has version {}",
+                reported.spec_version,
+                runtime_version.spec_version
+            );
+        }
+
+        let options = &self.client.options;
+        let metadata = match &options.metadata_cache {
+            Some(metadata_cache) => {
+                OnlineClient::fetch_metadata_cached(
+                    rpc,
+                    metadata_cache,
+                    &options.metadata_versions,
+                    self.client.genesis_hash(),
+                    runtime_version.spec_version,
+                    Some(at),
+                )
+                .await?
+            }
+            None => OnlineClient::fetch_metadata(rpc, &options.metadata_versions, Some(at)).await?,
         };
 
-        Some(Ok(Update {
+        let diff = MetadataDiff::new(&self.client.metadata(), &metadata);
+        Ok(Update {
             metadata,
             runtime_version,
-        }))
+            diff,
+        })
     }
 }
 
@@ -413,6 +617,7 @@ pub enum UpgradeError {
 pub struct Update {
     runtime_version: RuntimeVersion,
     metadata: Metadata,
+    diff: MetadataDiff,
 }
 
 impl Update {
@@ -425,11 +630,17 @@ impl Update {
     pub fn metadata(&self) -> &Metadata {
         &self.metadata
     }
+
+    /// Get a summary of how the metadata differs from the metadata that the client
+    /// had when this update was obtained.
+    pub fn diff(&self) -> &MetadataDiff {
+        &self.diff
+    }
 }
 
 // helpers for a jsonrpsee specific OnlineClient.
 #[cfg(feature = "jsonrpsee-ws")]
-mod jsonrpsee_helpers {
+pub(crate) mod jsonrpsee_helpers {
     pub use jsonrpsee::{
         client_transport::ws::{InvalidUri, Receiver, Sender, Uri, WsTransportClientBuilder},
         core::{
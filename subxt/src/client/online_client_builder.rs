@@ -0,0 +1,494 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use super::{
+    online_client::{ClientOptions, DEFAULT_METADATA_VERSIONS},
+    MetadataCache, OnlineClient,
+};
+use crate::{
+    error::Error,
+    rpc::{Rpc, RpcClientT},
+    Config, Metadata,
+};
+use futures::future;
+use std::sync::Arc;
+
+/// A version of the metadata that can be asked for from a node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum MetadataVersion {
+    /// V14 metadata. This is fetched using the `Metadata_metadata` runtime API, which
+    /// every runtime that Subxt can talk to exposes.
+    V14,
+    /// V15 metadata, fetched using the `Metadata_metadata_at_version` runtime API.
+    V15,
+    /// The latest unstable metadata, fetched using the `Metadata_metadata_at_version`
+    /// runtime API. This is subject to breaking changes, and Subxt may fail to decode it.
+    Unstable,
+}
+
+impl MetadataVersion {
+    /// The version to hand to `Metadata_metadata_at_version` to obtain this metadata,
+    /// or `None` if it's obtained using `Metadata_metadata` instead.
+    pub(super) fn at_version(self) -> Option<u32> {
+        match self {
+            MetadataVersion::V14 => None,
+            MetadataVersion::V15 => Some(15),
+            MetadataVersion::Unstable => Some(u32::MAX),
+        }
+    }
+}
+
+/// Configure and construct an [`OnlineClient`].
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use subxt::{client::MetadataVersion, OnlineClient, PolkadotConfig};
+///
+/// let api = OnlineClient::<PolkadotConfig>::builder()
+///     // Ask for V15 metadata, falling back to V14 if the node can't provide it.
+///     .metadata_versions([MetadataVersion::V15, MetadataVersion::V14])
+///     .build_from_url("ws://127.0.0.1:9944")
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub struct OnlineClientBuilder<T: Config> {
+    metadata_versions: Vec<MetadataVersion>,
+    metadata: Option<Metadata>,
+    genesis_hash: Option<T::Hash>,
+    pinned_block_hash: Option<T::Hash>,
+    metadata_cache: Option<MetadataCache>,
+    #[cfg(feature = "runtime-updater-task")]
+    start_runtime_updater: bool,
+}
+
+impl<T: Config> std::fmt::Debug for OnlineClientBuilder<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OnlineClientBuilder")
+            .field("metadata_versions", &self.metadata_versions)
+            .field("metadata", &self.metadata.as_ref().map(|_| "Metadata"))
+            .field("genesis_hash", &self.genesis_hash)
+            .field("pinned_block_hash", &self.pinned_block_hash)
+            .field("metadata_cache", &self.metadata_cache)
+            .finish()
+    }
+}
+
+impl<T: Config> Default for OnlineClientBuilder<T> {
+    fn default() -> Self {
+        Self {
+            metadata_versions: DEFAULT_METADATA_VERSIONS.to_vec(),
+            metadata: None,
+            genesis_hash: None,
+            pinned_block_hash: None,
+            metadata_cache: None,
+            #[cfg(feature = "runtime-updater-task")]
+            start_runtime_updater: false,
+        }
+    }
+}
+
+impl<T: Config> OnlineClientBuilder<T> {
+    /// Create a new builder, which constructs a client in the same way that
+    /// [`OnlineClient::from_rpc_client()`] does until configured otherwise.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The metadata versions to ask the node for, in order of preference. Each is tried
+    /// in turn until one is obtained and decoded successfully. This is also used to fetch
+    /// the metadata for any runtime upgrades seen by the [`super::ClientRuntimeUpdater`].
+    ///
+    /// By default, only [`MetadataVersion::V14`] is asked for (or, if the `unstable-metadata`
+    /// feature is enabled, [`MetadataVersion::Unstable`] followed by [`MetadataVersion::V14`]).
+    pub fn metadata_versions(
+        mut self,
+        versions: impl IntoIterator<Item = MetadataVersion>,
+    ) -> Self {
+        self.metadata_versions = versions.into_iter().collect();
+        self
+    }
+
+    /// Use the given metadata rather than fetching it from the node.
+    pub fn metadata(mut self, metadata: impl Into<Metadata>) -> Self {
+        self.metadata = Some(metadata.into());
+        self
+    }
+
+    /// Use the given genesis hash rather than fetching it from the node.
+    pub fn genesis_hash(mut self, genesis_hash: T::Hash) -> Self {
+        self.genesis_hash = Some(genesis_hash);
+        self
+    }
+
+    /// Pin the client to the given block. The runtime version and metadata are fetched at
+    /// this block, and it is used in place of the latest block by all `at_latest()` calls
+    /// (for instance [`crate::storage::StorageClient::at_latest()`]).
+    ///
+    /// Since the runtime is not expected to change, you probably don't want to combine
+    /// this with starting the runtime updater.
+    pub fn pin_to_block(mut self, block_hash: T::Hash) -> Self {
+        self.pinned_block_hash = Some(block_hash);
+        self
+    }
+
+    /// Load metadata from, and store metadata in, the given [`MetadataCache`]. See
+    /// [`OnlineClient::from_rpc_client_with_metadata_cache()`] for more.
+    pub fn metadata_cache(mut self, metadata_cache: MetadataCache) -> Self {
+        self.metadata_cache = Some(metadata_cache);
+        self
+    }
+
+    /// Spawn a tokio task which keeps the client's metadata and runtime version up to
+    /// date as runtime upgrades happen, as described in [`OnlineClient::updater()`]. The
+    /// task runs until the node stops sending runtime version updates.
+    #[cfg(feature = "runtime-updater-task")]
+    pub fn start_runtime_updater(mut self, start: bool) -> Self {
+        self.start_runtime_updater = start;
+        self
+    }
+
+    /// Build an [`OnlineClient`], connecting to the given URL with the default RPC client.
+    #[cfg(any(
+        feature = "jsonrpsee-ws",
+        all(feature = "jsonrpsee-web", target_arch = "wasm32")
+    ))]
+    pub async fn build_from_url(self, url: impl AsRef<str>) -> Result<OnlineClient<T>, Error> {
+        let client = super::default_rpc_client(url).await?;
+        self.build(Arc::new(client)).await
+    }
+
+    /// Build an [`OnlineClient`] which uses the given [`RpcClientT`] implementation to
+    /// drive the connection.
+    pub async fn build<R: RpcClientT>(self, rpc_client: Arc<R>) -> Result<OnlineClient<T>, Error> {
+        let rpc = Rpc::<T>::new(rpc_client);
+
+        // If metadata is being cached, look at a specific block so that the metadata we
+        // store is certain to belong to the runtime version that we store it against.
+        let at = match self.pinned_block_hash {
+            Some(block_hash) => Some(block_hash),
+            None if self.metadata.is_none() && self.metadata_cache.is_some() => Some(
+                rpc.block_hash(None)
+                    .await?
+                    .expect("didn't pass a block number; qed"),
+            ),
+            None => None,
+        };
+
+        let (genesis_hash, runtime_version, metadata) = {
+            let given_genesis_hash = self.genesis_hash;
+            let genesis_hash = async {
+                match given_genesis_hash {
+                    Some(genesis_hash) => Ok(genesis_hash),
+                    None => rpc.genesis_hash().await,
+                }
+            };
+            let runtime_version = rpc.runtime_version(at);
+
+            match (self.metadata, &self.metadata_cache) {
+                (Some(metadata), _) => {
+                    let (genesis_hash, runtime_version) =
+                        future::join(genesis_hash, runtime_version).await;
+                    (genesis_hash?, runtime_version?, metadata)
+                }
+                (None, None) => {
+                    let (genesis_hash, runtime_version, metadata) = future::join3(
+                        genesis_hash,
+                        runtime_version,
+                        OnlineClient::fetch_metadata(&rpc, &self.metadata_versions, at),
+                    )
+                    .await;
+                    (genesis_hash?, runtime_version?, metadata?)
+                }
+                (None, Some(metadata_cache)) => {
+                    let (genesis_hash, runtime_version) =
+                        future::join(genesis_hash, runtime_version).await;
+                    let (genesis_hash, runtime_version) = (genesis_hash?, runtime_version?);
+                    let metadata = OnlineClient::fetch_metadata_cached(
+                        &rpc,
+                        metadata_cache,
+                        &self.metadata_versions,
+                        genesis_hash,
+                        runtime_version.spec_version,
+                        at,
+                    )
+                    .await?;
+                    (genesis_hash, runtime_version, metadata)
+                }
+            }
+        };
+
+        let client = OnlineClient::from_parts(
+            genesis_hash,
+            runtime_version,
+            metadata,
+            rpc,
+            ClientOptions {
+                metadata_versions: self.metadata_versions,
+                metadata_cache: self.metadata_cache,
+                pinned_block_hash: self.pinned_block_hash,
+            },
+        );
+
+        #[cfg(feature = "runtime-updater-task")]
+        if self.start_runtime_updater {
+            let updater = client.updater();
+            tokio::spawn(async move {
+                if let Err(e) = updater.perform_runtime_updates().await {
+                    tracing::warn!("Runtime updater stopped: {e}");
+                }
+            });
+        }
+
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        client::OnlineClientT,
+        config::substrate::H256,
+        rpc::{
+            mock_rpc_client::{subscription, to_raw, MockRpcClient},
+            types::Bytes,
+        },
+        SubstrateConfig,
+    };
+    use codec::Encode;
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    };
+
+    type Requests = Arc<Mutex<Vec<(String, serde_json::Value)>>>;
+
+    // A node whose latest block is numbered `latest`. Blocks are identified by hashes made
+    // from their block numbers, and each block runs the spec version equal to its number.
+    // Only V14 metadata is available from it.
+    fn rpc(requests: Requests, latest: Arc<AtomicU64>) -> MockRpcClient {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_tiny.scale").unwrap();
+
+        MockRpcClient::new().on_request(move |method, params| {
+            let params: serde_json::Value = serde_json::from_str(params.unwrap().get()).unwrap();
+            requests
+                .lock()
+                .unwrap()
+                .push((method.to_owned(), params.clone()));
+            let at = |n: usize| -> u64 {
+                match serde_json::from_value::<Option<H256>>(params[n].clone()).unwrap() {
+                    Some(hash) => hash.to_low_u64_be(),
+                    None => latest.load(Ordering::SeqCst),
+                }
+            };
+            match method {
+                "chain_getBlockHash" => match params[0].as_u64() {
+                    Some(number) => Ok(to_raw(H256::from_low_u64_be(number))),
+                    None => Ok(to_raw(H256::from_low_u64_be(latest.load(Ordering::SeqCst)))),
+                },
+                "state_getRuntimeVersion" => Ok(to_raw(runtime_version(at(0)))),
+                "state_call" => match params[0].as_str().unwrap() {
+                    "Metadata_metadata" => Ok(to_raw(Bytes(bytes.encode()))),
+                    "Metadata_metadata_at_version" => Ok(to_raw(Bytes(None::<()>.encode()))),
+                    f => panic!("unexpected runtime call {f}"),
+                },
+                _ => panic!("unexpected method {method}"),
+            }
+        })
+    }
+
+    fn runtime_version(spec_version: u64) -> serde_json::Value {
+        serde_json::json!({
+            "specVersion": spec_version,
+            "transactionVersion": 1,
+        })
+    }
+
+    // The runtime calls that were made, along with the block that each was made at.
+    fn runtime_calls(requests: &Requests) -> Vec<(String, Option<H256>)> {
+        let requests = requests.lock().unwrap();
+        requests
+            .iter()
+            .filter(|(method, _)| method == "state_call")
+            .map(|(_, params)| {
+                let function = params[0].as_str().unwrap().to_owned();
+                let at = serde_json::from_value(params[2].clone()).unwrap();
+                (function, at)
+            })
+            .collect()
+    }
+
+    fn cache(name: &str) -> MetadataCache {
+        let dir = std::env::temp_dir().join(format!("subxt-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        MetadataCache::new(dir)
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_next_metadata_version() {
+        let requests = Requests::default();
+        let client = OnlineClient::<SubstrateConfig>::builder()
+            .metadata_versions([MetadataVersion::V15, MetadataVersion::V14])
+            .build(Arc::new(rpc(requests.clone(), Arc::new(3.into()))))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            runtime_calls(&requests),
+            vec![
+                ("Metadata_metadata_at_version".to_owned(), None),
+                ("Metadata_metadata".to_owned(), None),
+            ]
+        );
+        assert_eq!(client.runtime_version().spec_version, 3);
+        assert_eq!(client.genesis_hash(), H256::zero());
+    }
+
+    #[tokio::test]
+    async fn fails_when_no_metadata_version_is_available() {
+        let requests = Requests::default();
+        let res = OnlineClient::<SubstrateConfig>::builder()
+            .metadata_versions([MetadataVersion::V15])
+            .build(Arc::new(rpc(requests, Arc::new(3.into()))))
+            .await;
+
+        assert!(res.is_err());
+    }
+
+    #[tokio::test]
+    async fn pinned_clients_look_at_the_pinned_block() {
+        let requests = Requests::default();
+        let pinned = H256::from_low_u64_be(1);
+        let client = OnlineClient::<SubstrateConfig>::builder()
+            .metadata_versions([MetadataVersion::V14])
+            .pin_to_block(pinned)
+            .build(Arc::new(rpc(requests.clone(), Arc::new(3.into()))))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            runtime_calls(&requests),
+            vec![("Metadata_metadata".to_owned(), Some(pinned))]
+        );
+        assert_eq!(client.runtime_version().spec_version, 1);
+        assert_eq!(client.pinned_block_hash(), Some(pinned));
+    }
+
+    #[tokio::test]
+    async fn cached_metadata_is_not_fetched_again() {
+        let cache = cache("builder-cache");
+        let latest = H256::from_low_u64_be(3);
+
+        // The first client fetches the metadata at the latest block, and caches it under
+        // the spec version of that block.
+        let requests = Requests::default();
+        OnlineClient::<SubstrateConfig>::builder()
+            .metadata_versions([MetadataVersion::V14])
+            .metadata_cache(cache.clone())
+            .build(Arc::new(rpc(requests.clone(), Arc::new(3.into()))))
+            .await
+            .unwrap();
+        assert_eq!(
+            runtime_calls(&requests),
+            vec![("Metadata_metadata".to_owned(), Some(latest))]
+        );
+        assert!(cache
+            .load(H256::zero().as_ref(), 3, MetadataVersion::V14)
+            .unwrap()
+            .is_some());
+
+        // The second client finds it in the cache.
+        let requests = Requests::default();
+        let client = OnlineClient::<SubstrateConfig>::builder()
+            .metadata_versions([MetadataVersion::V14])
+            .metadata_cache(cache.clone())
+            .build(Arc::new(rpc(requests.clone(), Arc::new(3.into()))))
+            .await
+            .unwrap();
+        assert!(runtime_calls(&requests).is_empty());
+        assert_eq!(client.runtime_version().spec_version, 3);
+
+        let _ = std::fs::remove_dir_all(cache.dir());
+    }
+
+    #[tokio::test]
+    async fn updates_belong_to_the_block_that_metadata_is_fetched_at() {
+        let cache = cache("updater-cache");
+        let requests = Requests::default();
+
+        // Spec version 2 is reported, but the latest block has since moved on to
+        // spec version 3.
+        let rpc = rpc(requests.clone(), Arc::new(3.into()))
+            .on_subscribe(|_| Ok(subscription([Ok(to_raw(runtime_version(2)))])));
+        let client = OnlineClient::<SubstrateConfig>::builder()
+            .metadata_versions([MetadataVersion::V14])
+            .metadata_cache(cache.clone())
+            .build(Arc::new(rpc))
+            .await
+            .unwrap();
+        requests.lock().unwrap().clear();
+
+        let mut updates = client.updater().runtime_updates().await.unwrap();
+        let update = updates.next().await.unwrap().unwrap();
+
+        assert_eq!(update.runtime_version().spec_version, 3);
+        assert_eq!(
+            runtime_calls(&requests),
+            vec![(
+                "Metadata_metadata".to_owned(),
+                Some(H256::from_low_u64_be(3))
+            )]
+        );
+        let genesis_hash = H256::zero();
+        let v14 = MetadataVersion::V14;
+        assert!(cache.load(genesis_hash.as_ref(), 3, v14).unwrap().is_some());
+        assert!(cache.load(genesis_hash.as_ref(), 2, v14).unwrap().is_none());
+        assert!(updates.next().await.is_none());
+
+        let _ = std::fs::remove_dir_all(cache.dir());
+    }
+
+    #[cfg(feature = "runtime-updater-task")]
+    #[tokio::test]
+    async fn runtime_updater_task_applies_updates() {
+        use crate::rpc::RpcSubscription;
+        use futures::StreamExt;
+
+        let latest = Arc::new(AtomicU64::new(3));
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let rx = Mutex::new(Some(rx));
+        let rpc = rpc(Requests::default(), latest.clone()).on_subscribe(move |_| {
+            let rx = rx.lock().unwrap().take().expect("only subscribed once");
+            Ok(RpcSubscription {
+                stream: rx.map(Ok).boxed(),
+                id: Some("sub".to_owned()),
+            })
+        });
+        let client = OnlineClient::<SubstrateConfig>::builder()
+            .metadata_versions([MetadataVersion::V14])
+            .start_runtime_updater(true)
+            .build(Arc::new(rpc))
+            .await
+            .unwrap();
+        assert_eq!(client.runtime_version().spec_version, 3);
+
+        // A runtime upgrade happens:
+        latest.store(4, Ordering::SeqCst);
+        tx.unbounded_send(to_raw(runtime_version(4))).unwrap();
+
+        let upgraded = async {
+            while client.runtime_version().spec_version != 4 {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(5), upgraded)
+            .await
+            .expect("the runtime updater should apply the update");
+    }
+}
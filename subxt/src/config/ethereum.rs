@@ -0,0 +1,43 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Configuration for Substrate based chains whose accounts are Ethereum style, 20 byte
+//! ECDSA accounts, such as EVM compatible chains.
+
+use super::{
+    polkadot::PolkadotExtrinsicParams,
+    substrate::{BlakeTwo256, SubstrateHeader},
+    Config,
+};
+
+pub use crate::utils::{AccountId20, EthereumSignature};
+pub use primitive_types::{H256, U256};
+
+/// Default set of commonly used types by Substrate based chains with Ethereum style
+/// accounts. Accounts are [`AccountId20`]s, which are also used as the address of the
+/// sender of a transaction, and transactions are signed with an [`EthereumSignature`]
+/// (see [`crate::tx::EcdsaKeccakSigner`]).
+pub enum EthereumConfig {}
+
+impl Config for EthereumConfig {
+    type Index = u32;
+    type Hash = H256;
+    type AccountId = AccountId20;
+    type Address = AccountId20;
+    type Signature = EthereumSignature;
+    type Hasher = BlakeTwo256;
+    type Header = SubstrateHeader<u32, BlakeTwo256>;
+    type ExtrinsicParams = EthereumExtrinsicParams<Self>;
+}
+
+/// A struct representing the signed extra and additional parameters required
+/// to construct a transaction for a chain with Ethereum style accounts.
+pub type EthereumExtrinsicParams<T> = PolkadotExtrinsicParams<T>;
+
+/// A builder which leads to [`EthereumExtrinsicParams`] being constructed.
+/// This is what you provide to methods like `sign_and_submit()`.
+pub type EthereumExtrinsicParamsBuilder<T> = super::polkadot::PolkadotExtrinsicParamsBuilder<T>;
+
+// Because Era is one of the args to our extrinsic params.
+pub use super::extrinsic_params::Era;
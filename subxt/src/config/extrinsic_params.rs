@@ -7,7 +7,7 @@
 //! implementation of the trait is provided ([`BaseExtrinsicParams`]) which is
 //! used by the provided Substrate and Polkadot configuration.
 
-use crate::{utils::Encoded, Config};
+use crate::{error::ExtrinsicParamsError, utils::Encoded, Config, Metadata};
 use codec::{Compact, Decode, Encode};
 use core::fmt::Debug;
 use derivative::Derivative;
@@ -32,6 +32,33 @@ pub trait ExtrinsicParams<Index, Hash>: Debug + 'static {
         other_params: Self::OtherParams,
     ) -> Self;
 
+    /// Construct a new instance of our [`ExtrinsicParams`], given the metadata of the
+    /// runtime that the extrinsic is for. This is what [`crate::tx::TxClient`] calls, and
+    /// allows the parameters to be constructed according to the signed extensions that
+    /// the metadata says are expected (see [`super::signed_extensions`]).
+    ///
+    /// By default, the metadata is ignored and [`ExtrinsicParams::new()`] is called.
+    fn new_with_metadata(
+        spec_version: u32,
+        tx_version: u32,
+        nonce: Index,
+        genesis_hash: Hash,
+        metadata: &Metadata,
+        other_params: Self::OtherParams,
+    ) -> Result<Self, ExtrinsicParamsError>
+    where
+        Self: Sized,
+    {
+        let _ = metadata;
+        Ok(Self::new(
+            spec_version,
+            tx_version,
+            nonce,
+            genesis_hash,
+            other_params,
+        ))
+    }
+
     /// This is expected to SCALE encode the "signed extra" parameters
     /// to some buffer that has been provided. These are the parameters
     /// which are sent along with the transaction, as well as taken into
@@ -122,6 +149,14 @@ impl<T: Config, Tip: Default> Default for BaseExtrinsicParamsBuilder<T, Tip> {
     }
 }
 
+impl<T: Config, Tip> MortalParams<T::Hash> for BaseExtrinsicParamsBuilder<T, Tip> {
+    fn mortal_from(mut self, era: Era, checkpoint: T::Hash) -> Self {
+        self.era = era;
+        self.mortality_checkpoint = Some(checkpoint);
+        self
+    }
+}
+
 impl<T: Config, Tip: Debug + Encode + 'static> ExtrinsicParams<T::Index, T::Hash>
     for BaseExtrinsicParams<T, Tip>
 {
@@ -165,6 +200,16 @@ impl<T: Config, Tip: Debug + Encode + 'static> ExtrinsicParams<T::Index, T::Hash
     }
 }
 
+/// Implemented by the [`ExtrinsicParams::OtherParams`] of [`ExtrinsicParams`] which can
+/// make a transaction mortal. This allows [`crate::tx::TxClient::mortal_params()`] to work
+/// out the era and checkpoint of a transaction given only the number of blocks that it
+/// should be valid for.
+pub trait MortalParams<Hash>: Sized {
+    /// Make the transaction valid for the given [`Era`], beginning at the block with the
+    /// given hash. This overrides any era that was set before.
+    fn mortal_from(self, era: Era, checkpoint: Hash) -> Self;
+}
+
 // Dev note: This and related bits taken from `sp_runtime::generic::Era`
 /// An era to describe the longevity of a transaction.
 #[derive(PartialEq, Eq, Clone, Copy, Debug, Serialize, Deserialize)]
@@ -221,6 +266,16 @@ impl Era {
     pub fn immortal() -> Self {
         Self::Immortal
     }
+
+    /// Get the block number of the start of the era whose properties this object describes
+    /// that `current` belongs to. The hash of this block is the mortality checkpoint of a
+    /// transaction created with this era.
+    pub fn birth(self, current: u64) -> u64 {
+        match self {
+            Self::Immortal => 0,
+            Self::Mortal(period, phase) => (current.max(phase) - phase) / period * period + phase,
+        }
+    }
 }
 
 // Both copied from `sp_runtime::generic::Era`; this is the wire interface and so
@@ -256,3 +311,23 @@ impl Decode for Era {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mortal_eras_begin_at_or_shortly_before_the_current_block() {
+        assert_eq!(Era::Immortal.birth(1234), 0);
+
+        // Short periods begin exactly at the current block.
+        let era = Era::mortal(64, 1234);
+        assert_eq!(era, Era::Mortal(64, 1234 % 64));
+        assert_eq!(era.birth(1234), 1234);
+
+        // The phase of long periods is quantized, so they may begin a little earlier.
+        let era = Era::mortal(1 << 16, 100_003);
+        assert_eq!(era.birth(100_003), 100_000);
+        assert_eq!(Era::decode(&mut &*era.encode()).unwrap(), era);
+    }
+}
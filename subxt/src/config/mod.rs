@@ -8,15 +8,18 @@
 //! default Substrate node implementation, and [`PolkadotConfig`] for a
 //! Polkadot node.
 
+pub mod ethereum;
 pub mod extrinsic_params;
 pub mod polkadot;
+pub mod signed_extensions;
 pub mod substrate;
 
 use codec::{Decode, Encode};
 use core::fmt::Debug;
 use serde::{de::DeserializeOwned, Serialize};
 
-pub use extrinsic_params::ExtrinsicParams;
+pub use ethereum::EthereumConfig;
+pub use extrinsic_params::{ExtrinsicParams, MortalParams};
 pub use polkadot::PolkadotConfig;
 pub use substrate::SubstrateConfig;
 
@@ -86,6 +89,9 @@ pub trait Header: Sized + Encode {
     /// Return the block number of this header.
     fn number(&self) -> Self::Number;
 
+    /// Return the hash of the parent of the block with this header.
+    fn parent_hash(&self) -> <Self::Hasher as Hasher>::Output;
+
     /// Hash this header.
     fn hash(&self) -> <Self::Hasher as Hasher>::Output {
         Self::Hasher::hash_of(self)
@@ -122,7 +128,7 @@ mod substrate_impls {
     where
         Self: Encode,
         N: Copy + Into<U256> + Into<u64> + TryFrom<U256>,
-        H: sp_runtime::traits::Hash + Hasher,
+        H: sp_runtime::traits::Hash + Hasher<Output = <H as sp_runtime::traits::Hash>::Output>,
     {
         type Number = N;
         type Hasher = H;
@@ -130,6 +136,10 @@ mod substrate_impls {
         fn number(&self) -> Self::Number {
             self.number
         }
+
+        fn parent_hash(&self) -> <H as Hasher>::Output {
+            self.parent_hash
+        }
     }
 
     impl Hasher for sp_core::Blake2Hasher {
@@ -0,0 +1,671 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! An implementation of [`ExtrinsicParams`] which encodes the "signed extra" and "additional"
+//! parameters of an extrinsic one signed extension at a time, in the order that the metadata
+//! says that the chain expects them.
+//!
+//! Each signed extension that the metadata lists is handed to an encoder which implements
+//! [`SignedExtension`], chosen according to its identifier. Encoders are provided for the
+//! signed extensions that Substrate and Polkadot use (see [`builtin_signed_extension()`]),
+//! and encoders for any others can be registered using
+//! [`DynamicExtrinsicParamsBuilder::signed_extension()`]. Signed extensions without an
+//! encoder whose extra and additional types are both empty are encoded automatically, and
+//! any others lead to an [`ExtrinsicParamsError::UnknownSignedExtension`] error.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! use subxt::config::{
+//!     signed_extensions::{DynamicExtrinsicParams, ExtensionParams, SignedExtension},
+//!     Config, PolkadotConfig, WithExtrinsicParams,
+//! };
+//!
+//! // Our chain has a custom signed extension which sends a single byte along with
+//! // each transaction.
+//! #[derive(Debug)]
+//! struct CheckFoo(u8);
+//!
+//! impl<T: Config> SignedExtension<T> for CheckFoo {
+//!     fn encode_extra_to(&self, _params: &ExtensionParams<T>, v: &mut Vec<u8>) {
+//!         v.push(self.0);
+//!     }
+//!     fn encode_additional_to(&self, _params: &ExtensionParams<T>, _v: &mut Vec<u8>) {}
+//! }
+//!
+//! type MyConfig = WithExtrinsicParams<PolkadotConfig, DynamicExtrinsicParams<PolkadotConfig>>;
+//!
+//! let params = DynamicExtrinsicParams::<PolkadotConfig>::builder()
+//!     .tip(1_000)
+//!     .signed_extension("CheckFoo", CheckFoo(1));
+//! ```
+
+use super::{
+    extrinsic_params::{Era, MortalParams},
+    Config, ExtrinsicParams,
+};
+use crate::{error::ExtrinsicParamsError, Metadata};
+use codec::{Compact, Encode};
+use derivative::Derivative;
+use scale_info::{PortableRegistry, TypeDef};
+use std::{collections::HashMap, fmt::Debug, sync::Arc};
+
+/// Encodes the "signed extra" and "additional" data for a single signed extension.
+pub trait SignedExtension<T: Config>: Debug + Send + Sync + 'static {
+    /// SCALE encode the "signed extra" data of this signed extension, which is sent along
+    /// with the transaction, to the buffer provided.
+    fn encode_extra_to(&self, params: &ExtensionParams<T>, v: &mut Vec<u8>);
+
+    /// SCALE encode the "additional" data of this signed extension, which is signed but
+    /// not sent along with the transaction, to the buffer provided.
+    fn encode_additional_to(&self, params: &ExtensionParams<T>, v: &mut Vec<u8>);
+}
+
+/// The details of a transaction which signed extensions are encoded from.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct ExtensionParams<T: Config> {
+    spec_version: u32,
+    transaction_version: u32,
+    nonce: T::Index,
+    genesis_hash: T::Hash,
+    era: Era,
+    mortality_checkpoint: Option<T::Hash>,
+    tip: u128,
+    tip_asset_id: Option<Vec<u8>>,
+}
+
+impl<T: Config> ExtensionParams<T> {
+    /// The spec version of the runtime.
+    pub fn spec_version(&self) -> u32 {
+        self.spec_version
+    }
+
+    /// The transaction version of the runtime.
+    pub fn transaction_version(&self) -> u32 {
+        self.transaction_version
+    }
+
+    /// The nonce of the account that is sending the transaction.
+    pub fn nonce(&self) -> T::Index {
+        self.nonce
+    }
+
+    /// The genesis hash of the chain.
+    pub fn genesis_hash(&self) -> T::Hash {
+        self.genesis_hash
+    }
+
+    /// The era that the transaction is valid for.
+    pub fn era(&self) -> Era {
+        self.era
+    }
+
+    /// The hash of the block that the era of the transaction begins at. This is the
+    /// genesis hash for immortal transactions.
+    pub fn mortality_checkpoint(&self) -> T::Hash {
+        self.mortality_checkpoint.unwrap_or(self.genesis_hash)
+    }
+
+    /// The tip to give to the block author.
+    pub fn tip(&self) -> u128 {
+        self.tip
+    }
+
+    /// The SCALE encoded ID of the asset that the tip is paid in, or `None` if it's
+    /// paid in the native currency of the chain.
+    pub fn tip_asset_id(&self) -> Option<&[u8]> {
+        self.tip_asset_id.as_deref()
+    }
+}
+
+/// An implementation of [`ExtrinsicParams`] which is driven by the signed extensions listed
+/// in the metadata. See [the module docs](self) for more.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""))]
+pub struct DynamicExtrinsicParams<T: Config> {
+    extra: Vec<u8>,
+    additional: Vec<u8>,
+    marker: std::marker::PhantomData<T>,
+}
+
+impl<T: Config> DynamicExtrinsicParams<T> {
+    /// Begin building the parameters to provide when constructing a transaction.
+    pub fn builder() -> DynamicExtrinsicParamsBuilder<T> {
+        DynamicExtrinsicParamsBuilder::new()
+    }
+
+    fn from_extensions(
+        extensions: &[Arc<dyn SignedExtension<T>>],
+        params: &ExtensionParams<T>,
+    ) -> Self {
+        let mut extra = Vec::new();
+        let mut additional = Vec::new();
+        for extension in extensions {
+            extension.encode_extra_to(params, &mut extra);
+            extension.encode_additional_to(params, &mut additional);
+        }
+        DynamicExtrinsicParams {
+            extra,
+            additional,
+            marker: std::marker::PhantomData,
+        }
+    }
+}
+
+/// The signed extensions that Substrate based chains tend to use, in order. These are
+/// what is assumed if [`DynamicExtrinsicParams`] is constructed without any metadata.
+const DEFAULT_SIGNED_EXTENSIONS: &[&str] = &[
+    "CheckNonZeroSender",
+    "CheckSpecVersion",
+    "CheckTxVersion",
+    "CheckGenesis",
+    "CheckMortality",
+    "CheckNonce",
+    "CheckWeight",
+    "ChargeTransactionPayment",
+];
+
+impl<T: Config> ExtrinsicParams<T::Index, T::Hash> for DynamicExtrinsicParams<T> {
+    type OtherParams = DynamicExtrinsicParamsBuilder<T>;
+
+    /// Without any metadata, the signed extensions that Substrate based chains tend to use
+    /// are assumed. Encoders registered for any of these take precedence as usual, but
+    /// there's nowhere to put any others, and so registering them is a bug which this
+    /// panics on in debug builds (use [`ExtrinsicParams::new_with_metadata()`] instead).
+    fn new(
+        spec_version: u32,
+        transaction_version: u32,
+        nonce: T::Index,
+        genesis_hash: T::Hash,
+        other_params: Self::OtherParams,
+    ) -> Self {
+        let registered = other_params.signed_extensions.clone();
+        debug_assert!(
+            registered
+                .keys()
+                .all(|id| DEFAULT_SIGNED_EXTENSIONS.contains(&id.as_str())),
+            "signed extensions were registered which aren't used without metadata: {:?}",
+            registered.keys().collect::<Vec<_>>()
+        );
+
+        let params =
+            other_params.into_params(spec_version, transaction_version, nonce, genesis_hash);
+        let extensions: Vec<Arc<dyn SignedExtension<T>>> = DEFAULT_SIGNED_EXTENSIONS
+            .iter()
+            .filter_map(|id| match registered.get(*id) {
+                Some(encoder) => Some(encoder.clone()),
+                None => builtin_signed_extension::<T>(id).map(Arc::from),
+            })
+            .collect();
+        Self::from_extensions(&extensions, &params)
+    }
+
+    fn new_with_metadata(
+        spec_version: u32,
+        transaction_version: u32,
+        nonce: T::Index,
+        genesis_hash: T::Hash,
+        metadata: &Metadata,
+        other_params: Self::OtherParams,
+    ) -> Result<Self, ExtrinsicParamsError> {
+        let registered = other_params.signed_extensions.clone();
+        let params =
+            other_params.into_params(spec_version, transaction_version, nonce, genesis_hash);
+
+        let mut extensions: Vec<Arc<dyn SignedExtension<T>>> = Vec::new();
+        for extension in metadata.extrinsic().signed_extensions() {
+            let id = extension.identifier();
+            if let Some(encoder) = registered.get(id) {
+                extensions.push(encoder.clone());
+            } else if let Some(encoder) = builtin_signed_extension::<T>(id) {
+                extensions.push(encoder.into());
+            } else if !is_empty_type(metadata.types(), extension.extra_ty())
+                || !is_empty_type(metadata.types(), extension.additional_ty())
+            {
+                return Err(ExtrinsicParamsError::UnknownSignedExtension(id.to_owned()));
+            }
+        }
+
+        Ok(Self::from_extensions(&extensions, &params))
+    }
+
+    fn encode_extra_to(&self, v: &mut Vec<u8>) {
+        v.extend_from_slice(&self.extra);
+    }
+
+    fn encode_additional_to(&self, v: &mut Vec<u8>) {
+        v.extend_from_slice(&self.additional);
+    }
+}
+
+/// This builder allows you to provide the parameters that can be configured in order to
+/// construct a [`DynamicExtrinsicParams`] value, and to register encoders for any signed
+/// extensions that Subxt doesn't know about. This implements [`Default`], which allows
+/// [`DynamicExtrinsicParams`] to be used with convenience methods like
+/// `sign_and_submit_default()`.
+#[derive(Derivative)]
+#[derivative(Debug(bound = ""), Clone(bound = ""), Default(bound = ""))]
+pub struct DynamicExtrinsicParamsBuilder<T: Config> {
+    #[derivative(Default(value = "Era::Immortal"))]
+    era: Era,
+    mortality_checkpoint: Option<T::Hash>,
+    tip: u128,
+    tip_asset_id: Option<Vec<u8>>,
+    signed_extensions: HashMap<String, Arc<dyn SignedExtension<T>>>,
+}
+
+impl<T: Config> DynamicExtrinsicParamsBuilder<T> {
+    /// Instantiate the default set of [`DynamicExtrinsicParamsBuilder`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the [`Era`], which defines how long the transaction will be valid for
+    /// (it can be either immortal, or it can be mortal and expire after a certain amount
+    /// of time). The second argument is the block hash after which the transaction
+    /// becomes valid, and must align with the era phase (see the [`Era::Mortal`] docs
+    /// for more detail on that).
+    pub fn era(mut self, era: Era, checkpoint: T::Hash) -> Self {
+        self.era = era;
+        self.mortality_checkpoint = Some(checkpoint);
+        self
+    }
+
+    /// Set the tip you'd like to give to the block author for this transaction.
+    pub fn tip(mut self, tip: u128) -> Self {
+        self.tip = tip;
+        self
+    }
+
+    /// Set the tip you'd like to give to the block author for this transaction, paid in
+    /// the given asset. This is only possible on chains which use `ChargeAssetTxPayment`.
+    pub fn tip_of_asset(mut self, tip: u128, asset_id: impl Encode) -> Self {
+        self.tip = tip;
+        self.tip_asset_id = Some(asset_id.encode());
+        self
+    }
+
+    /// Encode the signed extension with the given identifier using the given encoder. This
+    /// takes precedence over any encoder that Subxt provides for the same identifier.
+    pub fn signed_extension(
+        mut self,
+        identifier: impl Into<String>,
+        encoder: impl SignedExtension<T>,
+    ) -> Self {
+        self.signed_extensions
+            .insert(identifier.into(), Arc::new(encoder));
+        self
+    }
+
+    fn into_params(
+        self,
+        spec_version: u32,
+        transaction_version: u32,
+        nonce: T::Index,
+        genesis_hash: T::Hash,
+    ) -> ExtensionParams<T> {
+        ExtensionParams {
+            spec_version,
+            transaction_version,
+            nonce,
+            genesis_hash,
+            era: self.era,
+            mortality_checkpoint: self.mortality_checkpoint,
+            tip: self.tip,
+            tip_asset_id: self.tip_asset_id,
+        }
+    }
+}
+
+impl<T: Config> MortalParams<T::Hash> for DynamicExtrinsicParamsBuilder<T> {
+    fn mortal_from(self, era: Era, checkpoint: T::Hash) -> Self {
+        self.era(era, checkpoint)
+    }
+}
+
+/// The encoder that Subxt provides for the signed extension with the given identifier,
+/// if there is one. Encoders are provided for:
+///
+/// - `CheckSpecVersion`: [`CheckSpecVersion`]
+/// - `CheckTxVersion`: [`CheckTxVersion`]
+/// - `CheckGenesis`: [`CheckGenesis`]
+/// - `CheckMortality` (and `CheckEra`, its name in older runtimes): [`CheckMortality`]
+/// - `CheckNonce`: [`CheckNonce`]
+/// - `ChargeTransactionPayment`: [`ChargeTransactionPayment`]
+/// - `ChargeAssetTxPayment`: [`ChargeAssetTxPayment`]
+/// - `CheckNonZeroSender` and `CheckWeight`, which encode nothing.
+pub fn builtin_signed_extension<T: Config>(
+    identifier: &str,
+) -> Option<Box<dyn SignedExtension<T>>> {
+    let encoder: Box<dyn SignedExtension<T>> = match identifier {
+        "CheckSpecVersion" => Box::new(CheckSpecVersion),
+        "CheckTxVersion" => Box::new(CheckTxVersion),
+        "CheckGenesis" => Box::new(CheckGenesis),
+        "CheckMortality" | "CheckEra" => Box::new(CheckMortality),
+        "CheckNonce" => Box::new(CheckNonce),
+        "ChargeTransactionPayment" => Box::new(ChargeTransactionPayment),
+        "ChargeAssetTxPayment" => Box::new(ChargeAssetTxPayment),
+        "CheckNonZeroSender" | "CheckWeight" => Box::new(Empty),
+        _ => return None,
+    };
+    Some(encoder)
+}
+
+/// Encodes the spec version of the runtime as additional data.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CheckSpecVersion;
+
+impl<T: Config> SignedExtension<T> for CheckSpecVersion {
+    fn encode_extra_to(&self, _params: &ExtensionParams<T>, _v: &mut Vec<u8>) {}
+    fn encode_additional_to(&self, params: &ExtensionParams<T>, v: &mut Vec<u8>) {
+        params.spec_version.encode_to(v);
+    }
+}
+
+/// Encodes the transaction version of the runtime as additional data.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CheckTxVersion;
+
+impl<T: Config> SignedExtension<T> for CheckTxVersion {
+    fn encode_extra_to(&self, _params: &ExtensionParams<T>, _v: &mut Vec<u8>) {}
+    fn encode_additional_to(&self, params: &ExtensionParams<T>, v: &mut Vec<u8>) {
+        params.transaction_version.encode_to(v);
+    }
+}
+
+/// Encodes the genesis hash of the chain as additional data.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CheckGenesis;
+
+impl<T: Config> SignedExtension<T> for CheckGenesis {
+    fn encode_extra_to(&self, _params: &ExtensionParams<T>, _v: &mut Vec<u8>) {}
+    fn encode_additional_to(&self, params: &ExtensionParams<T>, v: &mut Vec<u8>) {
+        params.genesis_hash.encode_to(v);
+    }
+}
+
+/// Encodes the era of the transaction as extra data, and the hash of the block that
+/// the era begins at as additional data.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CheckMortality;
+
+impl<T: Config> SignedExtension<T> for CheckMortality {
+    fn encode_extra_to(&self, params: &ExtensionParams<T>, v: &mut Vec<u8>) {
+        params.era.encode_to(v);
+    }
+    fn encode_additional_to(&self, params: &ExtensionParams<T>, v: &mut Vec<u8>) {
+        params.mortality_checkpoint().encode_to(v);
+    }
+}
+
+/// Encodes the nonce of the account sending the transaction as extra data.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CheckNonce;
+
+impl<T: Config> SignedExtension<T> for CheckNonce {
+    fn encode_extra_to(&self, params: &ExtensionParams<T>, v: &mut Vec<u8>) {
+        let nonce: u64 = params.nonce.into();
+        Compact(nonce).encode_to(v);
+    }
+    fn encode_additional_to(&self, _params: &ExtensionParams<T>, _v: &mut Vec<u8>) {}
+}
+
+/// Encodes the tip as extra data.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChargeTransactionPayment;
+
+impl<T: Config> SignedExtension<T> for ChargeTransactionPayment {
+    fn encode_extra_to(&self, params: &ExtensionParams<T>, v: &mut Vec<u8>) {
+        Compact(params.tip).encode_to(v);
+    }
+    fn encode_additional_to(&self, _params: &ExtensionParams<T>, _v: &mut Vec<u8>) {}
+}
+
+/// Encodes the tip, and the asset that it's paid in, as extra data.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ChargeAssetTxPayment;
+
+impl<T: Config> SignedExtension<T> for ChargeAssetTxPayment {
+    fn encode_extra_to(&self, params: &ExtensionParams<T>, v: &mut Vec<u8>) {
+        Compact(params.tip).encode_to(v);
+        match &params.tip_asset_id {
+            None => v.push(0),
+            Some(asset_id) => {
+                v.push(1);
+                v.extend_from_slice(asset_id);
+            }
+        }
+    }
+    fn encode_additional_to(&self, _params: &ExtensionParams<T>, _v: &mut Vec<u8>) {}
+}
+
+/// Encodes nothing, for signed extensions whose extra and additional types are empty.
+#[derive(Clone, Copy, Debug, Default)]
+struct Empty;
+
+impl<T: Config> SignedExtension<T> for Empty {
+    fn encode_extra_to(&self, _params: &ExtensionParams<T>, _v: &mut Vec<u8>) {}
+    fn encode_additional_to(&self, _params: &ExtensionParams<T>, _v: &mut Vec<u8>) {}
+}
+
+/// Does the type with the given ID encode to nothing?
+fn is_empty_type(types: &PortableRegistry, id: u32) -> bool {
+    let Some(ty) = types.resolve(id) else {
+        return false;
+    };
+    match &ty.type_def {
+        TypeDef::Composite(composite) => composite
+            .fields
+            .iter()
+            .all(|field| is_empty_type(types, field.ty.id)),
+        TypeDef::Tuple(tuple) => tuple.fields.iter().all(|ty| is_empty_type(types, ty.id)),
+        TypeDef::Array(array) => array.len == 0 || is_empty_type(types, array.type_param.id),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{config::substrate::H256, PolkadotConfig};
+    use codec::Decode;
+    use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed};
+
+    fn params(
+        other_params: DynamicExtrinsicParamsBuilder<PolkadotConfig>,
+    ) -> ExtensionParams<PolkadotConfig> {
+        other_params.into_params(1, 2, 3, H256::repeat_byte(4))
+    }
+
+    fn polkadot_metadata() -> Metadata {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_small.scale").unwrap();
+        Metadata::decode(&mut &*bytes).unwrap()
+    }
+
+    fn new_with_metadata(
+        metadata: &Metadata,
+        other_params: DynamicExtrinsicParamsBuilder<PolkadotConfig>,
+    ) -> Result<DynamicExtrinsicParams<PolkadotConfig>, ExtrinsicParamsError> {
+        <DynamicExtrinsicParams<PolkadotConfig> as ExtrinsicParams<_, _>>::new_with_metadata(
+            1,
+            2,
+            3,
+            H256::repeat_byte(4),
+            metadata,
+            other_params,
+        )
+    }
+
+    // The extra and additional bytes that the given params encode to.
+    fn encoded<P: ExtrinsicParams<u32, H256>>(params: &P) -> (Vec<u8>, Vec<u8>) {
+        let mut extra = Vec::new();
+        params.encode_extra_to(&mut extra);
+        let mut additional = Vec::new();
+        params.encode_additional_to(&mut additional);
+        (extra, additional)
+    }
+
+    fn base_extrinsic_params(
+        era: Era,
+        checkpoint: H256,
+    ) -> crate::config::extrinsic_params::BaseExtrinsicParams<
+        PolkadotConfig,
+        crate::config::polkadot::PlainTip,
+    > {
+        use crate::config::polkadot::{PlainTip, PolkadotExtrinsicParamsBuilder};
+        ExtrinsicParams::<u32, H256>::new(
+            1,
+            2,
+            3,
+            H256::repeat_byte(4),
+            PolkadotExtrinsicParamsBuilder::new()
+                .era(era, checkpoint)
+                .tip(PlainTip::new(6)),
+        )
+    }
+
+    // Spec version 99, rather than the real one, as additional data.
+    #[derive(Debug)]
+    struct FakeSpecVersion;
+
+    impl SignedExtension<PolkadotConfig> for FakeSpecVersion {
+        fn encode_extra_to(&self, _params: &ExtensionParams<PolkadotConfig>, _v: &mut Vec<u8>) {}
+        fn encode_additional_to(&self, _params: &ExtensionParams<PolkadotConfig>, v: &mut Vec<u8>) {
+            99u32.encode_to(v);
+        }
+    }
+
+    #[test]
+    fn builtin_extensions_match_base_extrinsic_params() {
+        let checkpoint = H256::repeat_byte(5);
+        let era = Era::mortal(32, 100);
+        let dynamic = <DynamicExtrinsicParams<PolkadotConfig> as ExtrinsicParams<_, _>>::new(
+            1,
+            2,
+            3,
+            H256::repeat_byte(4),
+            DynamicExtrinsicParams::builder()
+                .era(era, checkpoint)
+                .tip(6),
+        );
+
+        assert_eq!(
+            encoded(&dynamic),
+            encoded(&base_extrinsic_params(era, checkpoint))
+        );
+    }
+
+    #[test]
+    fn polkadot_metadata_extensions_match_base_extrinsic_params() {
+        let checkpoint = H256::repeat_byte(5);
+        let era = Era::mortal(32, 100);
+        let dynamic = new_with_metadata(
+            &polkadot_metadata(),
+            DynamicExtrinsicParams::builder()
+                .era(era, checkpoint)
+                .tip(6),
+        )
+        .unwrap();
+
+        assert_eq!(
+            encoded(&dynamic),
+            encoded(&base_extrinsic_params(era, checkpoint))
+        );
+    }
+
+    #[test]
+    fn unknown_extensions_with_types_are_errors() {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_small.scale").unwrap();
+        let mut prefixed = RuntimeMetadataPrefixed::decode(&mut &*bytes).unwrap();
+        let RuntimeMetadata::V15(v15) = &mut prefixed.1 else {
+            panic!("expected V15 metadata");
+        };
+        let spec_version = v15
+            .extrinsic
+            .signed_extensions
+            .iter_mut()
+            .find(|e| e.identifier == "CheckSpecVersion")
+            .unwrap();
+        spec_version.identifier = "CheckUnknown".to_owned();
+        let metadata = Metadata::try_from(prefixed).unwrap();
+
+        let err = new_with_metadata(&metadata, DynamicExtrinsicParams::builder()).unwrap_err();
+        assert_eq!(
+            err,
+            ExtrinsicParamsError::UnknownSignedExtension("CheckUnknown".to_owned())
+        );
+
+        // Registering an encoder for it fixes things.
+        let res = new_with_metadata(
+            &metadata,
+            DynamicExtrinsicParams::builder().signed_extension("CheckUnknown", CheckSpecVersion),
+        );
+        assert!(res.is_ok());
+    }
+
+    #[test]
+    fn registered_extensions_take_precedence_over_builtin_ones() {
+        let other_params =
+            DynamicExtrinsicParams::builder().signed_extension("CheckSpecVersion", FakeSpecVersion);
+        let with_metadata = new_with_metadata(&polkadot_metadata(), other_params.clone()).unwrap();
+        let without_metadata =
+            <DynamicExtrinsicParams<PolkadotConfig> as ExtrinsicParams<_, _>>::new(
+                1,
+                2,
+                3,
+                H256::repeat_byte(4),
+                other_params,
+            );
+
+        // The spec version is the first additional data, and would otherwise be 1.
+        for params in [with_metadata, without_metadata] {
+            let (_, additional) = encoded(&params);
+            assert_eq!(additional[..4], 99u32.encode());
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "aren't used without metadata")]
+    fn extensions_registered_without_metadata_must_be_known() {
+        <DynamicExtrinsicParams<PolkadotConfig> as ExtrinsicParams<_, _>>::new(
+            1,
+            2,
+            3,
+            H256::repeat_byte(4),
+            DynamicExtrinsicParams::builder().signed_extension("CheckFoo", FakeSpecVersion),
+        );
+    }
+
+    #[test]
+    fn asset_tips_are_encoded() {
+        let params = params(DynamicExtrinsicParams::builder().tip_of_asset(1, 7u32));
+        let mut v = Vec::new();
+        SignedExtension::<PolkadotConfig>::encode_extra_to(&ChargeAssetTxPayment, &params, &mut v);
+        assert_eq!(v, (Compact(1u128), Some(7u32)).encode());
+    }
+
+    #[test]
+    fn empty_unknown_extensions_are_encoded_automatically() {
+        let metadata = polkadot_metadata();
+
+        // Polkadot uses `PrevalidateAttests`, which Subxt has no encoder for, but is empty.
+        let result = new_with_metadata(&metadata, DynamicExtrinsicParams::builder());
+        assert!(result.is_ok());
+
+        // The metadata type of the spec version isn't empty.
+        let spec_version = metadata
+            .extrinsic()
+            .signed_extensions()
+            .iter()
+            .find(|e| e.identifier() == "CheckSpecVersion")
+            .unwrap();
+        assert!(!is_empty_type(
+            metadata.types(),
+            spec_version.additional_ty()
+        ));
+        assert!(is_empty_type(metadata.types(), spec_version.extra_ty()));
+    }
+}
@@ -109,6 +109,7 @@ impl<N, H> Header for SubstrateHeader<N, H>
 where
     N: Copy + Into<u64> + Into<U256> + TryFrom<U256> + Encode,
     H: Hasher + Encode,
+    H::Output: Clone,
     SubstrateHeader<N, H>: Encode,
 {
     type Number = N;
@@ -116,6 +117,9 @@ where
     fn number(&self) -> Self::Number {
         self.number
     }
+    fn parent_hash(&self) -> H::Output {
+        self.parent_hash.clone()
+    }
 }
 
 /// Generic header digest. From `sp_runtime::generic::digest`.
@@ -5,6 +5,7 @@
 //! Types representing the errors that can be returned.
 
 mod dispatch_error;
+mod transaction_validity;
 
 use core::fmt::Debug;
 
@@ -13,6 +14,9 @@ pub use dispatch_error::{
     ArithmeticError, DispatchError, ModuleError, RawModuleError, TokenError, TransactionalError,
 };
 
+// Re-export transaction validity error types:
+pub use transaction_validity::{InvalidTransaction, TransactionValidityError, UnknownTransaction};
+
 // Re-expose the errors we use from other crates here:
 pub use crate::metadata::Metadata;
 pub use scale_decode::Error as DecodeError;
@@ -61,6 +65,15 @@ pub enum Error {
     /// An error encoding a storage address.
     #[error("Error encoding storage address: {0}")]
     StorageAddress(#[from] StorageAddressError),
+    /// An error following the chain head.
+    #[error("Chain head error: {0}")]
+    ChainHead(#[from] ChainHeadError),
+    /// An error using the `archive` RPC methods.
+    #[error("Archive error: {0}")]
+    Archive(#[from] ArchiveError),
+    /// An error constructing the signed extra and additional parameters of an extrinsic.
+    #[error("Extrinsic params error: {0}")]
+    ExtrinsicParams(#[from] ExtrinsicParamsError),
     /// The bytes representing an error that we were unable to decode.
     #[error("An error occurred but it could not be decoded: {0:?}")]
     Unknown(Vec<u8>),
@@ -69,6 +82,19 @@ pub enum Error {
     Other(String),
 }
 
+impl Error {
+    /// Classify this error, to help decide whether an operation that failed with it
+    /// is worth retrying.
+    pub fn classify(&self) -> ErrorClass {
+        match self {
+            Error::Io(_) => ErrorClass::Transport,
+            Error::Rpc(e) => e.classify(),
+            Error::Transaction(TransactionError::Validity(e)) => e.classify(),
+            _ => ErrorClass::Fatal,
+        }
+    }
+}
+
 impl<'a> From<&'a str> for Error {
     fn from(error: &'a str) -> Self {
         Error::Other(error.into())
@@ -94,6 +120,117 @@ pub enum RpcError {
     /// The RPC subscription dropped.
     #[error("RPC error: subscription dropped.")]
     SubscriptionDropped,
+    /// The connection to the node was lost and is being re-established. This is handed
+    /// back in place of any subscription items that may have been missed in the meantime.
+    #[error("RPC error: disconnected, will reconnect: {0}")]
+    DisconnectedWillReconnect(String),
+    /// The RPC client does not support the subscription that was asked for (for
+    /// instance, because it talks to the node over HTTP).
+    #[error("RPC error: subscriptions are not supported by this client (subscribing to {0})")]
+    SubscriptionsUnsupported(String),
+    /// The node handed back a JSON-RPC error in response to a call.
+    #[error("RPC error: {0}")]
+    Call(JsonRpcError),
+    /// No response was received within the configured time limit.
+    #[error("RPC error: request timed out after {0:?}")]
+    RequestTimeout(std::time::Duration),
+    /// The node does not expose the RPC method that was needed.
+    #[error("RPC error: the node does not support the `{0}` method")]
+    MethodNotSupported(String),
+}
+
+impl RpcError {
+    /// Classify this error, to help decide whether an operation that failed with it
+    /// is worth retrying.
+    pub fn classify(&self) -> ErrorClass {
+        match self {
+            RpcError::ClientError(e) => classify_client_error(&**e),
+            RpcError::SubscriptionDropped
+            | RpcError::DisconnectedWillReconnect(_)
+            | RpcError::RequestTimeout(_) => ErrorClass::Transport,
+            RpcError::SubscriptionsUnsupported(_) | RpcError::MethodNotSupported(_) => {
+                ErrorClass::Fatal
+            }
+            RpcError::Call(e) => e.classify(),
+        }
+    }
+}
+
+#[cfg(feature = "jsonrpsee")]
+fn classify_client_error(e: &(dyn std::error::Error + Send + Sync + 'static)) -> ErrorClass {
+    use crate::JsonRpseeError;
+    match e.downcast_ref::<JsonRpseeError>() {
+        Some(
+            JsonRpseeError::Transport(_)
+            | JsonRpseeError::RestartNeeded(_)
+            | JsonRpseeError::RequestTimeout
+            | JsonRpseeError::MaxSlotsExceeded,
+        ) => ErrorClass::Transport,
+        _ => ErrorClass::Fatal,
+    }
+}
+
+#[cfg(not(feature = "jsonrpsee"))]
+fn classify_client_error(_e: &(dyn std::error::Error + Send + Sync + 'static)) -> ErrorClass {
+    ErrorClass::Fatal
+}
+
+/// How an error should be treated by anything that wants to retry on failure.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ErrorClass {
+    /// Communicating with the node failed. The same request may succeed if it is
+    /// sent again, perhaps once a connection has been re-established.
+    Transport,
+    /// The node understood and refused the request, but may accept the same request
+    /// later on (for instance, a transaction whose priority was too low or whose
+    /// nonce is in the future).
+    Retryable,
+    /// Sending the same request again is not expected to succeed.
+    Fatal,
+}
+
+/// A JSON-RPC error object handed back by the node.
+#[derive(Clone, Debug, Eq, thiserror::Error, PartialEq)]
+#[error("{code}: {message}{}", .data.as_ref().map(|d| format!(" ({d})")).unwrap_or_default())]
+pub struct JsonRpcError {
+    /// The error code.
+    pub code: i32,
+    /// A short description of the error.
+    pub message: String,
+    /// Any additional information about the error that the node provided.
+    pub data: Option<serde_json::Value>,
+}
+
+impl JsonRpcError {
+    /// JSON-RPC error code: the method does not exist or is not available.
+    pub const METHOD_NOT_FOUND: i32 = -32601;
+    /// Transaction pool error code: the transaction is temporarily banned.
+    pub const POOL_TEMPORARILY_BANNED: i32 = 1012;
+    /// Transaction pool error code: the transaction's priority is too low.
+    pub const POOL_TOO_LOW_PRIORITY: i32 = 1014;
+    /// Transaction pool error code: the transaction was immediately dropped.
+    pub const POOL_IMMEDIATELY_DROPPED: i32 = 1016;
+
+    /// If this error was handed back for a transaction that was found to be invalid, or
+    /// whose validity could not be determined, then this decodes the reason that the
+    /// node gave for refusing it.
+    pub fn transaction_validity_error(&self) -> Option<TransactionValidityError> {
+        TransactionValidityError::from_json_rpc_error(self)
+    }
+
+    /// Classify this error, to help decide whether the call that produced it is worth
+    /// retrying.
+    pub fn classify(&self) -> ErrorClass {
+        match self.code {
+            Self::POOL_TEMPORARILY_BANNED
+            | Self::POOL_TOO_LOW_PRIORITY
+            | Self::POOL_IMMEDIATELY_DROPPED => ErrorClass::Retryable,
+            _ => self
+                .transaction_validity_error()
+                .map(|e| e.classify())
+                .unwrap_or(ErrorClass::Fatal),
+        }
+    }
 }
 
 /// Block error
@@ -103,6 +240,9 @@ pub enum BlockError {
     /// An error containing the hash of the block that was not found.
     #[error("Could not find a block with hash {0} (perhaps it was on a non-finalized fork?)")]
     NotFound(String),
+    /// No block with the given number was found.
+    #[error("Could not find a block with number {0}")]
+    NumberNotFound(u64),
     /// Extrinsic type ID cannot be resolved with the provided metadata.
     #[error("Extrinsic type ID cannot be resolved with the provided metadata. Make sure this is a valid metadata")]
     MissingType,
@@ -144,6 +284,67 @@ pub enum TransactionError {
     /// The transaction was dropped because of some limit
     #[error("The transaction was dropped from the pool because of a limit.")]
     Dropped,
+    /// The node could not process the transaction, and has stopped tracking it.
+    #[error("The transaction could not be processed: {0}")]
+    Error(String),
+    /// The node refused the transaction because it is invalid, or its validity could
+    /// not be determined.
+    #[error("The transaction was refused: {0}")]
+    Validity(TransactionValidityError),
+}
+
+/// Something went wrong using the `chainHead` RPC methods.
+#[derive(Clone, Debug, Eq, thiserror::Error, PartialEq)]
+#[non_exhaustive]
+pub enum ChainHeadError {
+    /// The `chainHead_follow` subscription did not hand back a subscription ID.
+    #[error("The chainHead follow subscription has no subscription ID")]
+    MissingSubscriptionId,
+    /// The block is not pinned by the `chainHead_follow` subscription.
+    #[error("Block {0} is not pinned by the chainHead follow subscription")]
+    NotPinned(String),
+    /// The `chainHead_follow` subscription has stopped, or the node no longer recognises it.
+    #[error("The chainHead follow subscription has stopped")]
+    Stopped,
+    /// The resources requested are inaccessible. Trying again later might succeed.
+    #[error("The requested resources are inaccessible: {0}")]
+    Inaccessible(String),
+    /// The operation failed.
+    #[error("The operation failed: {0}")]
+    OperationFailed(String),
+    /// The operation ended without producing a result.
+    #[error("The operation ended without producing a result")]
+    NoResult,
+    /// The node handed back a result that could not be understood.
+    #[error("Invalid response from the node: {0}")]
+    InvalidResponse(String),
+}
+
+/// Something went wrong using the `archive` RPC methods.
+#[derive(Clone, Debug, Eq, thiserror::Error, PartialEq)]
+#[non_exhaustive]
+pub enum ArchiveError {
+    /// A runtime API call made using `archive_unstable_call` failed.
+    #[error("Runtime API call {function} failed: {reason}")]
+    CallFailed {
+        /// The runtime API function that was called.
+        function: String,
+        /// Why the call failed.
+        reason: String,
+    },
+    /// The node discarded a query made using `archive_unstable_storage`.
+    #[error("The node discarded the archive_unstable_storage query")]
+    QueryDiscarded,
+}
+
+/// Something went wrong constructing the signed extra and additional parameters of
+/// an extrinsic (see [`crate::config::ExtrinsicParams`]).
+#[derive(Clone, Debug, Eq, thiserror::Error, PartialEq)]
+#[non_exhaustive]
+pub enum ExtrinsicParamsError {
+    /// The chain expects a signed extension that we don't know how to encode.
+    #[error("The chain expects a signed extension with the identifier {0}, but no encoder was registered for it")]
+    UnknownSignedExtension(String),
 }
 
 /// Something went wrong trying to encode a storage address.
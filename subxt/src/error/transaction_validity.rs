@@ -0,0 +1,204 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! A representation of the reasons that a node can give for refusing a transaction
+//! submitted to it via `author_submitExtrinsic` (or similar).
+
+use super::{ErrorClass, JsonRpcError};
+
+/// The transaction pool error code for an invalid transaction.
+const POOL_INVALID_TX: i32 = 1010;
+/// The transaction pool error code for a transaction whose validity can't be determined.
+const POOL_UNKNOWN_VALIDITY: i32 = 1011;
+
+/// The node refused a transaction because it was found to be invalid or its validity
+/// could not be determined.
+#[derive(Clone, Debug, Eq, thiserror::Error, PartialEq)]
+#[non_exhaustive]
+pub enum TransactionValidityError {
+    /// The transaction is invalid.
+    #[error("Invalid transaction: {0}")]
+    Invalid(InvalidTransaction),
+    /// The validity of the transaction could not be determined.
+    #[error("Unknown transaction validity: {0}")]
+    Unknown(UnknownTransaction),
+}
+
+impl TransactionValidityError {
+    /// Attempt to decode the reason that a transaction was refused from the error that
+    /// a node returned for a transaction pool RPC call like `author_submitExtrinsic`.
+    /// Returns `None` if the error is not a transaction validity error, or the reason
+    /// it gives isn't recognised.
+    pub fn from_json_rpc_error(err: &JsonRpcError) -> Option<Self> {
+        let data = err.data.as_ref()?.as_str()?;
+        match err.code {
+            POOL_INVALID_TX => InvalidTransaction::from_message(data).map(Self::Invalid),
+            POOL_UNKNOWN_VALIDITY => UnknownTransaction::from_message(data).map(Self::Unknown),
+            _ => None,
+        }
+    }
+
+    /// Could submitting the same transaction again later succeed?
+    pub fn classify(&self) -> ErrorClass {
+        match self {
+            Self::Invalid(InvalidTransaction::Future | InvalidTransaction::ExhaustsResources)
+            | Self::Unknown(UnknownTransaction::CannotLookup) => ErrorClass::Retryable,
+            _ => ErrorClass::Fatal,
+        }
+    }
+}
+
+/// The reasons that a transaction can be invalid. This mirrors `InvalidTransaction`
+/// from `sp_runtime`.
+#[derive(Clone, Copy, Debug, Eq, thiserror::Error, PartialEq)]
+#[non_exhaustive]
+pub enum InvalidTransaction {
+    /// The call of the transaction is not expected.
+    #[error("Transaction call is not expected")]
+    Call,
+    /// General error to do with the inability to pay some fees (e.g. account balance too low).
+    #[error("Inability to pay some fees (e.g. account balance too low)")]
+    Payment,
+    /// General error to do with the transaction not yet being valid (e.g. nonce too high).
+    #[error("Transaction will be valid in the future")]
+    Future,
+    /// General error to do with the transaction being outdated (e.g. nonce too low).
+    #[error("Transaction is outdated")]
+    Stale,
+    /// General error to do with the transaction's proofs (e.g. signature).
+    #[error("Transaction has a bad signature")]
+    BadProof,
+    /// The transaction birth block is ancient.
+    #[error("Transaction has an ancient birth block")]
+    AncientBirthBlock,
+    /// The transaction would exhaust the resources of current block.
+    #[error("Transaction would exhaust the block limits")]
+    ExhaustsResources,
+    /// Any other custom invalid validity that is not covered by this enum. The custom
+    /// error code is not always given by the node, in which case this is `None`.
+    #[error("InvalidTransaction custom error")]
+    Custom(Option<u8>),
+    /// An extrinsic with a mandatory dispatch resulted in an error.
+    #[error("A call was labelled as mandatory, but resulted in an Error.")]
+    BadMandatory,
+    /// An extrinsic with a mandatory dispatch tried to be validated.
+    #[error("Transaction dispatch is mandatory; transactions must not be validated.")]
+    MandatoryValidation,
+    /// The sending address is disabled or known to be invalid.
+    #[error("Invalid signing address")]
+    BadSigner,
+}
+
+impl InvalidTransaction {
+    // Substrate hands back the `&'static str` representation of the error, except for
+    // custom errors, which are handed back as "Custom error: {code}".
+    fn from_message(msg: &str) -> Option<Self> {
+        if let Some(code) = msg.strip_prefix("Custom error: ") {
+            return Some(Self::Custom(code.trim().parse().ok()));
+        }
+        let err = match msg {
+            "Transaction call is not expected" => Self::Call,
+            "Inability to pay some fees (e.g. account balance too low)" => Self::Payment,
+            "Transaction will be valid in the future" => Self::Future,
+            "Transaction is outdated" => Self::Stale,
+            "Transaction has a bad signature" => Self::BadProof,
+            "Transaction has an ancient birth block" => Self::AncientBirthBlock,
+            "Transaction would exhaust the block limits" => Self::ExhaustsResources,
+            "InvalidTransaction custom error" => Self::Custom(None),
+            "A call was labelled as mandatory, but resulted in an Error." => Self::BadMandatory,
+            "Transaction dispatch is mandatory; transactions must not be validated." => {
+                Self::MandatoryValidation
+            }
+            "Invalid signing address" => Self::BadSigner,
+            _ => return None,
+        };
+        Some(err)
+    }
+}
+
+/// The reasons that the validity of a transaction could not be determined. This
+/// mirrors `UnknownTransaction` from `sp_runtime`.
+#[derive(Clone, Copy, Debug, Eq, thiserror::Error, PartialEq)]
+#[non_exhaustive]
+pub enum UnknownTransaction {
+    /// Could not lookup some information that is required to validate the transaction.
+    #[error("Could not lookup information required to validate the transaction")]
+    CannotLookup,
+    /// No validator found for the given unsigned transaction.
+    #[error("Could not find an unsigned validator for the unsigned transaction")]
+    NoUnsignedValidator,
+    /// Any other custom unknown validity that is not covered by this enum.
+    #[error("UnknownTransaction custom error")]
+    Custom(u8),
+}
+
+impl UnknownTransaction {
+    // Substrate hands back the `Debug` representation of the error.
+    fn from_message(msg: &str) -> Option<Self> {
+        let err = match msg {
+            "CannotLookup" => Self::CannotLookup,
+            "NoUnsignedValidator" => Self::NoUnsignedValidator,
+            _ => {
+                let code = msg.strip_prefix("Custom(")?.strip_suffix(')')?;
+                Self::Custom(code.parse().ok()?)
+            }
+        };
+        Some(err)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn json_rpc_error(code: i32, data: &str) -> JsonRpcError {
+        JsonRpcError {
+            code,
+            message: "Some message".to_owned(),
+            data: Some(serde_json::Value::String(data.to_owned())),
+        }
+    }
+
+    #[test]
+    fn decodes_invalid_transaction() {
+        let cases = [
+            ("Transaction is outdated", InvalidTransaction::Stale),
+            (
+                "Transaction has a bad signature",
+                InvalidTransaction::BadProof,
+            ),
+            ("Custom error: 3", InvalidTransaction::Custom(Some(3))),
+        ];
+        for (data, expected) in cases {
+            let err = json_rpc_error(1010, data);
+            assert_eq!(
+                TransactionValidityError::from_json_rpc_error(&err),
+                Some(TransactionValidityError::Invalid(expected))
+            );
+        }
+    }
+
+    #[test]
+    fn decodes_unknown_transaction() {
+        let cases = [
+            ("CannotLookup", UnknownTransaction::CannotLookup),
+            ("Custom(12)", UnknownTransaction::Custom(12)),
+        ];
+        for (data, expected) in cases {
+            let err = json_rpc_error(1011, data);
+            assert_eq!(
+                TransactionValidityError::from_json_rpc_error(&err),
+                Some(TransactionValidityError::Unknown(expected))
+            );
+        }
+    }
+
+    #[test]
+    fn ignores_other_errors() {
+        let err = json_rpc_error(1014, "Transaction is outdated");
+        assert_eq!(TransactionValidityError::from_json_rpc_error(&err), None);
+        let err = json_rpc_error(1010, "Something else entirely");
+        assert_eq!(TransactionValidityError::from_json_rpc_error(&err), None);
+    }
+}
@@ -57,9 +57,9 @@ where
         // return a Future that's Send + 'static, rather than tied to &self.
         let client = self.client.clone();
         async move {
-            // If block hash is not provided, get the hash
-            // for the latest block and use that.
-            let block_hash = match block_hash {
+            // If block hash is not provided, use the block that the client is
+            // pinned to, or failing that, get the hash for the latest block.
+            let block_hash = match block_hash.or_else(|| client.pinned_block_hash()) {
                 Some(hash) => hash,
                 None => client
                     .rpc()
@@ -51,6 +51,7 @@ std::compile_error!(
 );
 
 pub mod blocks;
+pub mod chain_head;
 pub mod client;
 pub mod config;
 pub mod constants;
@@ -68,7 +69,7 @@ pub mod utils;
 // but leave most types behind their respective modules.
 pub use crate::{
     client::{OfflineClient, OnlineClient},
-    config::{Config, PolkadotConfig, SubstrateConfig},
+    config::{Config, EthereumConfig, PolkadotConfig, SubstrateConfig},
     error::Error,
     metadata::Metadata,
 };
@@ -0,0 +1,342 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use super::Metadata;
+use std::collections::HashMap;
+use subxt_metadata::{PalletMetadata, RuntimeApiMetadata};
+
+/// A summary of what changed between two versions of the metadata, for instance
+/// across a runtime upgrade.
+///
+/// Items are compared using the same validation hashes that statically generated
+/// code checks against, so an item is only reported as changed if code generated
+/// for it would no longer be valid. A pallet whose hash changed in a way not covered
+/// by any of its items (for instance, in its events or errors) is itself reported
+/// as a changed item of kind [`ItemKind::Pallet`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MetadataDiff {
+    pallets_added: Vec<String>,
+    pallets_removed: Vec<String>,
+    items: Vec<ItemDiff>,
+}
+
+impl MetadataDiff {
+    /// Compare the `old` metadata with the `new` metadata.
+    pub fn new(old: &Metadata, new: &Metadata) -> Self {
+        let mut diff = MetadataDiff::default();
+
+        let pallet_names = names_in_either(
+            old.pallets().map(|p| p.name()),
+            new.pallets().map(|p| p.name()),
+        );
+        for pallet_name in pallet_names {
+            let old_pallet = old.pallet_by_name(pallet_name);
+            let new_pallet = new.pallet_by_name(pallet_name);
+            match (&old_pallet, &new_pallet) {
+                (None, Some(_)) => diff.pallets_added.push(pallet_name.to_owned()),
+                (Some(_), None) => diff.pallets_removed.push(pallet_name.to_owned()),
+                (Some(o), Some(n)) if o.hash() == n.hash() => continue,
+                _ => {}
+            }
+
+            let items_before = diff.items.len();
+            for kind in [ItemKind::Call, ItemKind::StorageEntry, ItemKind::Constant] {
+                diff.push_items(
+                    kind,
+                    pallet_name,
+                    pallet_item_hashes(old_pallet, kind),
+                    pallet_item_hashes(new_pallet, kind),
+                );
+            }
+
+            // The pallet hash differs, but none of the items we compare do, so the
+            // change is somewhere else in the pallet.
+            let is_changed = old_pallet.is_some() && new_pallet.is_some();
+            if is_changed && diff.items.len() == items_before {
+                diff.items.push(ItemDiff::new(
+                    ItemKind::Pallet,
+                    pallet_name,
+                    pallet_name,
+                    Change::Changed,
+                ));
+            }
+        }
+
+        let trait_names = names_in_either(
+            old.runtime_api_traits().map(|t| t.name().to_owned()),
+            new.runtime_api_traits().map(|t| t.name().to_owned()),
+        );
+        for trait_name in &trait_names {
+            diff.push_items(
+                ItemKind::RuntimeApiMethod,
+                trait_name,
+                runtime_api_method_hashes(old.runtime_api_trait_by_name(trait_name)),
+                runtime_api_method_hashes(new.runtime_api_trait_by_name(trait_name)),
+            );
+        }
+
+        diff
+    }
+
+    /// Were there no changes at all?
+    pub fn is_empty(&self) -> bool {
+        self.pallets_added.is_empty() && self.pallets_removed.is_empty() && self.items.is_empty()
+    }
+
+    /// The names of pallets which exist in the new metadata but not in the old.
+    pub fn pallets_added(&self) -> &[String] {
+        &self.pallets_added
+    }
+
+    /// The names of pallets which exist in the old metadata but not in the new.
+    pub fn pallets_removed(&self) -> &[String] {
+        &self.pallets_removed
+    }
+
+    /// The calls, storage entries, constants and runtime API methods which were added,
+    /// removed or changed. The items in any pallets added or removed are included, as
+    /// are pallets which changed in some other way.
+    pub fn items(&self) -> &[ItemDiff] {
+        &self.items
+    }
+
+    /// Is statically generated code which was valid for the old metadata also valid for
+    /// the new metadata? This is the case if nothing was removed or changed.
+    ///
+    /// Code generated with the `#[subxt]` macro can also be checked against the new
+    /// metadata as a whole with its `is_codegen_valid_for()` function, which ignores
+    /// any pallets and runtime APIs that the code wasn't generated for.
+    pub fn is_codegen_compatible(&self) -> bool {
+        self.pallets_removed.is_empty() && self.items.iter().all(|i| i.is_codegen_compatible())
+    }
+
+    fn push_items(
+        &mut self,
+        kind: ItemKind,
+        parent: &str,
+        old: Vec<(&str, [u8; 32])>,
+        new: Vec<(&str, [u8; 32])>,
+    ) {
+        let mut old: HashMap<_, _> = old.into_iter().collect();
+
+        for (name, new_hash) in new {
+            let change = match old.remove(name) {
+                None => Change::Added,
+                Some(old_hash) if old_hash != new_hash => Change::Changed,
+                Some(_) => continue,
+            };
+            self.items.push(ItemDiff::new(kind, parent, name, change));
+        }
+
+        // Whatever is left over doesn't exist in the new metadata. Sort it so that
+        // the order doesn't depend on the hashing.
+        let mut removed: Vec<_> = old.into_keys().collect();
+        removed.sort_unstable();
+        for name in removed {
+            self.items
+                .push(ItemDiff::new(kind, parent, name, Change::Removed));
+        }
+    }
+}
+
+/// A single call, storage entry, constant, runtime API method or pallet which differs
+/// between two versions of the metadata.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ItemDiff {
+    kind: ItemKind,
+    parent: String,
+    name: String,
+    change: Change,
+}
+
+impl ItemDiff {
+    fn new(kind: ItemKind, parent: &str, name: &str, change: Change) -> Self {
+        ItemDiff {
+            kind,
+            parent: parent.to_owned(),
+            name: name.to_owned(),
+            change,
+        }
+    }
+
+    /// What sort of item this is.
+    pub fn kind(&self) -> ItemKind {
+        self.kind
+    }
+
+    /// The name of the pallet, or for runtime API methods, the runtime API trait,
+    /// that this item belongs to. For pallets, this is the pallet name.
+    pub fn parent(&self) -> &str {
+        &self.parent
+    }
+
+    /// The name of the item.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// How the item has changed.
+    pub fn change(&self) -> Change {
+        self.change
+    }
+
+    /// Is statically generated code which uses this item still valid? Generated code
+    /// validates each item against the hash it was generated with, and so an item that
+    /// was removed or changed will be rejected at runtime. Adding an item is harmless.
+    pub fn is_codegen_compatible(&self) -> bool {
+        self.change == Change::Added
+    }
+}
+
+/// The kind of item that an [`ItemDiff`] refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ItemKind {
+    /// A pallet call.
+    Call,
+    /// A pallet storage entry.
+    StorageEntry,
+    /// A pallet constant.
+    Constant,
+    /// A runtime API method.
+    RuntimeApiMethod,
+    /// A pallet which changed in a way that none of its calls, storage entries or
+    /// constants did, for instance in its events or errors. Only ever [`Change::Changed`].
+    Pallet,
+}
+
+/// How an item differs between two versions of the metadata.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Change {
+    /// The item only exists in the new metadata.
+    Added,
+    /// The item only exists in the old metadata.
+    Removed,
+    /// The item exists in both, but its validation hash has changed.
+    Changed,
+}
+
+/// The names given by `new`, followed by those only given by `old`.
+fn names_in_either<N: PartialEq>(
+    old: impl Iterator<Item = N>,
+    new: impl Iterator<Item = N>,
+) -> Vec<N> {
+    let mut names: Vec<N> = new.collect();
+    let old: Vec<N> = old.filter(|name| !names.contains(name)).collect();
+    names.extend(old);
+    names
+}
+
+fn pallet_item_hashes(pallet: Option<PalletMetadata<'_>>, kind: ItemKind) -> Vec<(&str, [u8; 32])> {
+    let Some(pallet) = pallet else {
+        return Vec::new();
+    };
+
+    let names: Vec<&str> = match kind {
+        ItemKind::Call => pallet
+            .call_variants()
+            .map(|variants| variants.iter().map(|v| v.name.as_str()).collect())
+            .unwrap_or_default(),
+        ItemKind::StorageEntry => pallet
+            .storage()
+            .map(|storage| storage.entries().map(|e| e.name()).collect())
+            .unwrap_or_default(),
+        ItemKind::Constant => pallet.constants().map(|c| c.name()).collect(),
+        ItemKind::RuntimeApiMethod | ItemKind::Pallet => Vec::new(),
+    };
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let hash = match kind {
+                ItemKind::Call => pallet.call_hash(name),
+                ItemKind::StorageEntry => pallet.storage_hash(name),
+                ItemKind::Constant => pallet.constant_hash(name),
+                ItemKind::RuntimeApiMethod | ItemKind::Pallet => None,
+            };
+            Some((name, hash?))
+        })
+        .collect()
+}
+
+fn runtime_api_method_hashes(api: Option<RuntimeApiMetadata<'_>>) -> Vec<(&str, [u8; 32])> {
+    let Some(api) = api else {
+        return Vec::new();
+    };
+
+    api.methods()
+        .filter_map(|m| Some((m.name(), api.method_hash(m.name())?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use codec::Decode;
+    use frame_metadata::{RuntimeMetadata, RuntimeMetadataPrefixed};
+
+    fn load_metadata(path: &str) -> Metadata {
+        let bytes = std::fs::read(path).unwrap();
+        Metadata::decode(&mut &*bytes).unwrap()
+    }
+
+    #[test]
+    fn diffs_pallets_and_items() {
+        let small = load_metadata("../artifacts/polkadot_metadata_small.scale");
+        let full = load_metadata("../artifacts/polkadot_metadata_full.scale");
+
+        assert!(MetadataDiff::new(&small, &small).is_empty());
+
+        // Going from the small metadata to the full one only adds things.
+        let diff = MetadataDiff::new(&small, &full);
+        assert!(diff.pallets_added().iter().any(|p| p == "Timestamp"));
+        assert!(diff.pallets_removed().is_empty());
+        assert!(diff
+            .items()
+            .iter()
+            .any(|i| i.parent() == "Timestamp" && i.kind() == ItemKind::Call));
+        assert!(diff.items().iter().all(|i| i.parent() != "Balances"));
+        assert!(diff.is_codegen_compatible());
+
+        // And the other way around only removes things.
+        let diff = MetadataDiff::new(&full, &small);
+        assert!(diff.pallets_removed().iter().any(|p| p == "Timestamp"));
+        assert!(diff
+            .items()
+            .iter()
+            .filter(|i| i.parent() == "Timestamp")
+            .all(|i| i.change() == Change::Removed && !i.is_codegen_compatible()));
+        assert!(!diff.is_codegen_compatible());
+    }
+
+    #[test]
+    fn diffs_pallets_changed_outside_of_items() {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_small.scale").unwrap();
+        let old = load_metadata("../artifacts/polkadot_metadata_small.scale");
+
+        // Drop the events of one pallet, which no call, storage entry or constant covers.
+        let mut prefixed = RuntimeMetadataPrefixed::decode(&mut &*bytes).unwrap();
+        let RuntimeMetadata::V15(v15) = &mut prefixed.1 else {
+            panic!("expected V15 metadata");
+        };
+        let balances = v15
+            .pallets
+            .iter_mut()
+            .find(|p| p.name == "Balances")
+            .unwrap();
+        assert!(balances.event.take().is_some());
+        let new = Metadata::try_from(prefixed).unwrap();
+
+        let diff = MetadataDiff::new(&old, &new);
+        assert_eq!(
+            diff.items(),
+            &[ItemDiff::new(
+                ItemKind::Pallet,
+                "Balances",
+                "Balances",
+                Change::Changed
+            )]
+        );
+        assert!(!diff.is_codegen_compatible());
+    }
+}
@@ -5,9 +5,11 @@
 //! Types representing the metadata obtained from a node.
 
 mod decode_encode_traits;
+mod metadata_diff;
 mod metadata_type;
 
 pub use decode_encode_traits::{DecodeWithMetadata, EncodeWithMetadata};
+pub use metadata_diff::{Change, ItemDiff, ItemKind, MetadataDiff};
 pub use metadata_type::Metadata;
 
 // Expose metadata types under a sub module in case somebody needs to reference them:
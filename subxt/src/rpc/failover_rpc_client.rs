@@ -0,0 +1,837 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! An [`RpcClientT`] implementation which spreads requests over a pool of nodes,
+//! and fails over between them when one of them errors or becomes unhealthy.
+
+use super::{Rpc, RpcClientT, RpcFuture, RpcSubscription, RpcSubscriptionStream};
+use crate::{client::jsonrpsee_helpers, error::RpcError, SubstrateConfig};
+use futures::{lock::Mutex as AsyncMutex, StreamExt};
+use jsonrpsee::core::Error as JsonRpseeError;
+use serde_json::value::RawValue;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// How a [`FailoverRpcClient`] chooses which healthy node to send each request to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoadBalancing {
+    /// Send requests to each healthy node in turn.
+    #[default]
+    RoundRobin,
+    /// Send requests to whichever healthy node has been responding fastest.
+    LowestLatency,
+}
+
+/// A builder to configure and construct a [`FailoverRpcClient`].
+#[derive(Debug, Clone)]
+pub struct FailoverRpcClientBuilder {
+    load_balancing: LoadBalancing,
+    health_check_interval: Duration,
+    health_check_timeout: Duration,
+}
+
+impl Default for FailoverRpcClientBuilder {
+    fn default() -> Self {
+        Self {
+            load_balancing: LoadBalancing::RoundRobin,
+            health_check_interval: Duration::from_secs(30),
+            health_check_timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl FailoverRpcClientBuilder {
+    /// Create a new builder with the default settings; requests are sent round-robin,
+    /// and nodes are health-checked at most every 30 seconds, with a node that takes
+    /// longer than 10 seconds to respond to a health check considered unhealthy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How to choose which healthy node to send each request to.
+    pub fn load_balancing(mut self, load_balancing: LoadBalancing) -> Self {
+        self.load_balancing = load_balancing;
+        self
+    }
+
+    /// How often to check the health of each node. Health checks happen as part of
+    /// handling requests, and so no checks are made while the client is idle.
+    pub fn health_check_interval(mut self, interval: Duration) -> Self {
+        self.health_check_interval = interval;
+        self
+    }
+
+    /// How long to wait for a node to respond to a health check before deciding that it's
+    /// unhealthy. Requests wait for any health check that's due to finish before being
+    /// sent, so this limits how long a node which has stopped responding can hold them up.
+    pub fn health_check_timeout(mut self, timeout: Duration) -> Self {
+        self.health_check_timeout = timeout;
+        self
+    }
+
+    /// Connect to each of the nodes at the given URLs. Nodes that we fail to connect
+    /// to are left out of the pool, and an error is returned if we can't connect to
+    /// any of them.
+    pub async fn build<U: AsRef<str>>(
+        self,
+        urls: impl IntoIterator<Item = U>,
+    ) -> Result<FailoverRpcClient, RpcError> {
+        let mut clients = Vec::new();
+        let mut last_err = None;
+        for url in urls {
+            let url = url.as_ref();
+            match jsonrpsee_helpers::client(url).await {
+                Ok(client) => clients.push((url.to_owned(), client)),
+                Err(e) => {
+                    tracing::warn!("Failed to connect to {url}; leaving it out of the pool: {e}");
+                    last_err = Some(RpcError::ClientError(Box::new(e)));
+                }
+            }
+        }
+
+        if clients.is_empty() {
+            return Err(last_err.unwrap_or_else(no_endpoints));
+        }
+        self.build_from_clients(clients)
+    }
+
+    /// Build a [`FailoverRpcClient`] from some already constructed clients, each given
+    /// alongside a name (for instance, its URL) which is used when logging.
+    pub fn build_from_clients<C: RpcClientT>(
+        self,
+        clients: impl IntoIterator<Item = (impl Into<String>, C)>,
+    ) -> Result<FailoverRpcClient, RpcError> {
+        let endpoints: Vec<_> = clients
+            .into_iter()
+            .map(|(name, client)| Endpoint {
+                name: name.into(),
+                rpc: Rpc::new(Arc::new(client)),
+                state: Mutex::new(EndpointState {
+                    healthy: true,
+                    latency: None,
+                }),
+            })
+            .collect();
+
+        if endpoints.is_empty() {
+            return Err(no_endpoints());
+        }
+
+        Ok(FailoverRpcClient {
+            inner: Arc::new(Inner {
+                endpoints,
+                load_balancing: self.load_balancing,
+                health_check_interval: self.health_check_interval,
+                health_check_timeout: self.health_check_timeout,
+                next: AtomicUsize::new(0),
+                last_health_check: Mutex::new(None),
+                health_check_lock: AsyncMutex::new(()),
+            }),
+        })
+    }
+}
+
+/// An [`RpcClientT`] implementation which talks to a pool of nodes.
+///
+/// - Requests are sent to a healthy node chosen according to [`LoadBalancing`]. If the
+///   node fails to respond, it is marked as unhealthy and the request is retried on the
+///   next node. Errors returned by a node in response to the request itself are handed
+///   back as normal.
+/// - Nodes are periodically health-checked using [`Rpc::system_health()`]. A node is
+///   healthy if it responds, is not syncing, and has peers (if it should have them).
+/// - Subscriptions are pinned to one healthy node. If that node fails, the subscription
+///   is re-established on another node. In order to signal that some items may have been
+///   missed in the meantime, the subscription stream will first emit an
+///   [`RpcError::DisconnectedWillReconnect`] error, and then carry on producing items
+///   from the new subscription.
+/// - Submitting a transaction (using `author_submitExtrinsic` or
+///   `author_submitAndWatchExtrinsic`) is never retried on another node after the node
+///   that it was sent to fails, because the first node may have received it already.
+///   The error is handed back instead, and a watch subscription ends after emitting it.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use std::sync::Arc;
+/// use subxt::{
+///     rpc::{FailoverRpcClient, LoadBalancing},
+///     OnlineClient, PolkadotConfig,
+/// };
+///
+/// let rpc_client = FailoverRpcClient::builder()
+///     .load_balancing(LoadBalancing::LowestLatency)
+///     .build(["ws://10.0.0.1:9944", "ws://10.0.0.2:9944"])
+///     .await
+///     .unwrap();
+///
+/// let api = OnlineClient::<PolkadotConfig>::from_rpc_client(Arc::new(rpc_client))
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct FailoverRpcClient {
+    inner: Arc<Inner>,
+}
+
+impl std::fmt::Debug for FailoverRpcClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let endpoints: Vec<_> = self.inner.endpoints.iter().map(|e| &e.name).collect();
+        f.debug_struct("FailoverRpcClient")
+            .field("endpoints", &endpoints)
+            .field("load_balancing", &self.inner.load_balancing)
+            .field("health_check_interval", &self.inner.health_check_interval)
+            .field("health_check_timeout", &self.inner.health_check_timeout)
+            .finish()
+    }
+}
+
+impl FailoverRpcClient {
+    /// Configure and build a new [`FailoverRpcClient`].
+    pub fn builder() -> FailoverRpcClientBuilder {
+        FailoverRpcClientBuilder::new()
+    }
+
+    /// Connect to each of the nodes at the given URLs using the default settings.
+    pub async fn new<U: AsRef<str>>(urls: impl IntoIterator<Item = U>) -> Result<Self, RpcError> {
+        Self::builder().build(urls).await
+    }
+
+    /// The names of the nodes that are currently considered healthy.
+    pub fn healthy_endpoints(&self) -> Vec<String> {
+        self.inner
+            .endpoints
+            .iter()
+            .filter(|e| e.state().healthy)
+            .map(|e| e.name.clone())
+            .collect()
+    }
+}
+
+struct Inner {
+    endpoints: Vec<Endpoint>,
+    load_balancing: LoadBalancing,
+    health_check_interval: Duration,
+    health_check_timeout: Duration,
+    // Used to pick the next endpoint when load balancing round-robin.
+    next: AtomicUsize,
+    last_health_check: Mutex<Option<Instant>>,
+    // Held while health checking so that only one check happens at a time.
+    health_check_lock: AsyncMutex<()>,
+}
+
+impl Inner {
+    fn health_check_due(&self) -> bool {
+        let last = *self
+            .last_health_check
+            .lock()
+            .expect("shouldn't be poisoned");
+        last.map_or(true, |t| t.elapsed() >= self.health_check_interval)
+    }
+
+    async fn maybe_check_health(&self) {
+        if !self.health_check_due() {
+            return;
+        }
+        let _guard = self.health_check_lock.lock().await;
+        // Another task may have done the check while we waited for the lock.
+        if !self.health_check_due() {
+            return;
+        }
+
+        let timeout = self.health_check_timeout;
+        futures::future::join_all(self.endpoints.iter().map(|e| e.check_health(timeout))).await;
+        *self
+            .last_health_check
+            .lock()
+            .expect("shouldn't be poisoned") = Some(Instant::now());
+    }
+
+    /// The indexes of the endpoints to try, in the order that we should try them. Healthy
+    /// endpoints are ordered according to the load balancing strategy, and unhealthy ones
+    /// come last, as a last resort. The `avoid` endpoint, if given, is always tried last.
+    fn endpoint_order(&self, avoid: Option<usize>) -> Vec<usize> {
+        let (mut healthy, mut unhealthy): (Vec<usize>, Vec<usize>) = (0..self.endpoints.len())
+            .filter(|&idx| Some(idx) != avoid)
+            .partition(|&idx| self.endpoints[idx].state().healthy);
+
+        match self.load_balancing {
+            LoadBalancing::RoundRobin => {
+                if !healthy.is_empty() {
+                    let start = self.next.fetch_add(1, Ordering::Relaxed) % healthy.len();
+                    healthy.rotate_left(start);
+                }
+            }
+            LoadBalancing::LowestLatency => {
+                healthy.sort_by_key(|&idx| {
+                    self.endpoints[idx].state().latency.unwrap_or(Duration::MAX)
+                });
+            }
+        }
+
+        healthy.append(&mut unhealthy);
+        healthy.extend(avoid);
+        healthy
+    }
+
+    /// Run the given request against each endpoint in turn until one of them responds. If
+    /// `retry` is false, the request is only tried on the first endpoint.
+    async fn with_failover<'a, T>(
+        &'a self,
+        desc: &str,
+        retry: bool,
+        f: impl Fn(&'a Endpoint) -> RpcFuture<'a, T>,
+    ) -> Result<T, RpcError> {
+        self.maybe_check_health().await;
+
+        let mut last_err = None;
+        for idx in self.endpoint_order(None) {
+            let endpoint = &self.endpoints[idx];
+            let started = Instant::now();
+            match f(endpoint).await {
+                Err(e) if is_endpoint_failure(&e) => {
+                    endpoint.mark_unhealthy();
+                    if !retry {
+                        tracing::debug!("{desc} failed on {}: {e}", endpoint.name);
+                        return Err(e);
+                    }
+                    tracing::debug!(
+                        "{desc} failed on {}; trying another node: {e}",
+                        endpoint.name
+                    );
+                    last_err = Some(e);
+                }
+                res => {
+                    endpoint.record_latency(started.elapsed());
+                    return res;
+                }
+            }
+        }
+        Err(last_err.expect("there is always at least one endpoint; qed"))
+    }
+
+    async fn subscribe(
+        &self,
+        sub: &str,
+        params: Option<Box<RawValue>>,
+        unsub: &str,
+        avoid: Option<usize>,
+    ) -> Result<(usize, RpcSubscription), RpcError> {
+        self.maybe_check_health().await;
+
+        let mut last_err = None;
+        for idx in self.endpoint_order(avoid) {
+            let endpoint = &self.endpoints[idx];
+            match endpoint.rpc.subscribe_raw(sub, params.clone(), unsub).await {
+                Ok(subscription) => return Ok((idx, subscription)),
+                Err(e) if is_endpoint_failure(&e) && !is_idempotent(sub) => {
+                    tracing::debug!("`{sub}` subscription failed on {}: {e}", endpoint.name);
+                    endpoint.mark_unhealthy();
+                    return Err(e);
+                }
+                Err(e) if is_endpoint_failure(&e) => {
+                    tracing::debug!(
+                        "`{sub}` subscription failed on {}; trying another node: {e}",
+                        endpoint.name
+                    );
+                    endpoint.mark_unhealthy();
+                    last_err = Some(e);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(last_err.expect("there is always at least one endpoint; qed"))
+    }
+}
+
+struct Endpoint {
+    name: String,
+    // We only use this to make requests which don't depend on the config.
+    rpc: Rpc<SubstrateConfig>,
+    state: Mutex<EndpointState>,
+}
+
+#[derive(Clone, Copy)]
+struct EndpointState {
+    healthy: bool,
+    // A moving average of how long the node takes to respond.
+    latency: Option<Duration>,
+}
+
+impl Endpoint {
+    fn state(&self) -> EndpointState {
+        *self.state.lock().expect("shouldn't be poisoned")
+    }
+
+    fn mark_unhealthy(&self) {
+        self.state.lock().expect("shouldn't be poisoned").healthy = false;
+    }
+
+    fn record_latency(&self, latency: Duration) {
+        let mut state = self.state.lock().expect("shouldn't be poisoned");
+        state.latency = Some(match state.latency {
+            Some(avg) => (avg * 4 + latency) / 5,
+            None => latency,
+        });
+    }
+
+    async fn check_health(&self, timeout: Duration) {
+        let started = Instant::now();
+        let health = tokio::time::timeout(timeout, self.rpc.system_health()).await;
+        let healthy = match health {
+            Ok(Ok(health)) => !health.is_syncing && (health.peers > 0 || !health.should_have_peers),
+            Ok(Err(e)) => {
+                tracing::debug!("Health check failed for {}: {e}", self.name);
+                false
+            }
+            Err(_) => {
+                tracing::debug!("Health check for {} timed out after {timeout:?}", self.name);
+                false
+            }
+        };
+
+        self.state.lock().expect("shouldn't be poisoned").healthy = healthy;
+        if healthy {
+            self.record_latency(started.elapsed());
+        } else {
+            tracing::debug!("{} is unhealthy", self.name);
+        }
+    }
+}
+
+impl RpcClientT for FailoverRpcClient {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            let desc = format!("`{method}` request");
+            self.inner
+                .with_failover(&desc, is_idempotent(method), |endpoint| {
+                    endpoint.rpc.request_raw(method, params.clone())
+                })
+                .await
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RpcFuture<'a, RpcSubscription> {
+        Box::pin(async move {
+            let (endpoint, subscription) = self
+                .inner
+                .subscribe(sub, params.clone(), unsub, None)
+                .await?;
+
+            let state = MovingSubscriptionState {
+                inner: self.inner.clone(),
+                endpoint,
+                stream: Some(subscription.stream),
+                sub: sub.to_owned(),
+                params,
+                unsub: unsub.to_owned(),
+                done: false,
+            };
+
+            let stream: RpcSubscriptionStream =
+                futures::stream::unfold(state, |mut state| async move {
+                    let item = state.next().await?;
+                    Some((item, state))
+                })
+                .boxed();
+
+            Ok(RpcSubscription {
+                stream,
+                id: subscription.id,
+            })
+        })
+    }
+
+    fn batch_request_raw<'a>(
+        &'a self,
+        batch: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        Box::pin(async move {
+            self.inner
+                .with_failover("batch request", true, |endpoint| {
+                    endpoint.rpc.batch_request_raw(batch.clone())
+                })
+                .await
+        })
+    }
+}
+
+// The state needed to move a subscription to another node.
+struct MovingSubscriptionState {
+    inner: Arc<Inner>,
+    // The index of the endpoint that we're subscribed on.
+    endpoint: usize,
+    // This is `None` if we need to resubscribe before we can hand back more items.
+    stream: Option<RpcSubscriptionStream>,
+    sub: String,
+    params: Option<Box<RawValue>>,
+    unsub: String,
+    // Set once we hit an error that we can't recover from.
+    done: bool,
+}
+
+impl MovingSubscriptionState {
+    async fn next(&mut self) -> Option<Result<Box<RawValue>, RpcError>> {
+        if self.done {
+            return None;
+        }
+
+        if self.stream.is_none() {
+            let res = self
+                .inner
+                .subscribe(
+                    &self.sub,
+                    self.params.clone(),
+                    &self.unsub,
+                    Some(self.endpoint),
+                )
+                .await;
+            match res {
+                Ok((endpoint, subscription)) => {
+                    self.endpoint = endpoint;
+                    self.stream = Some(subscription.stream);
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        let stream = self.stream.as_mut().expect("stream was set above; qed");
+
+        match stream.next().await {
+            Some(Err(e)) if is_endpoint_failure(&e) => {
+                self.inner.endpoints[self.endpoint].mark_unhealthy();
+                if !is_idempotent(&self.sub) {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+                self.stream = None;
+                Some(Err(self.moving(e.to_string())))
+            }
+            None => {
+                // Subscriptions also end when the connection is lost, so check whether
+                // the node is still there before deciding that the server ended it.
+                let endpoint = &self.inner.endpoints[self.endpoint];
+                endpoint.check_health(self.inner.health_check_timeout).await;
+                if endpoint.state().healthy {
+                    return None;
+                }
+                if !is_idempotent(&self.sub) {
+                    self.done = true;
+                    return Some(Err(RpcError::SubscriptionDropped));
+                }
+                self.stream = None;
+                Some(Err(self.moving("subscription closed".to_owned())))
+            }
+            item => item,
+        }
+    }
+
+    fn moving(&self, reason: String) -> RpcError {
+        let from = &self.inner.endpoints[self.endpoint].name;
+        tracing::debug!(
+            "Subscription `{}` on {from} interrupted ({reason}); moving it to another node",
+            self.sub
+        );
+        RpcError::DisconnectedWillReconnect(format!(
+            "subscription `{}` on {from} interrupted ({reason}); it will be re-established on another node",
+            self.sub
+        ))
+    }
+}
+
+// Was this error caused by the node failing, rather than by the request itself?
+fn is_endpoint_failure(err: &RpcError) -> bool {
+    match err {
+        RpcError::ClientError(e) => !matches!(
+            e.downcast_ref::<JsonRpseeError>(),
+            Some(JsonRpseeError::Call(_))
+        ),
+        RpcError::Call(_)
+        | RpcError::SubscriptionsUnsupported(_)
+        | RpcError::MethodNotSupported(_) => false,
+        _ => true,
+    }
+}
+
+// Can this request or subscription be sent again on another node if the node that it was
+// sent to fails? Submitting a transaction twice is not harmless, since the first node may
+// have received it, and so those aren't retried.
+fn is_idempotent(method: &str) -> bool {
+    !matches!(
+        method,
+        "author_submitExtrinsic" | "author_submitAndWatchExtrinsic"
+    )
+}
+
+fn no_endpoints() -> RpcError {
+    RpcError::ClientError("no RPC endpoints were given".into())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        error::JsonRpcError,
+        rpc::{
+            mock_rpc_client::{subscription, to_raw, MockRpcClient},
+            rpc_params, RpcClient,
+        },
+    };
+    use std::sync::atomic::AtomicBool;
+
+    // A node which answers requests with its name, and which stops responding while `down`
+    // is set. Requests to the method "bad" are rejected by the node itself.
+    fn node(name: &'static str, down: Arc<AtomicBool>) -> (&'static str, MockRpcClient) {
+        let client = MockRpcClient::new().on_request(move |method, _params| {
+            if down.load(Ordering::SeqCst) {
+                return Err(RpcError::ClientError("connection refused".into()));
+            }
+            match method {
+                "system_health" => Ok(to_raw(serde_json::json!({
+                    "peers": 1,
+                    "isSyncing": false,
+                    "shouldHavePeers": true,
+                }))),
+                "bad" => Err(RpcError::Call(JsonRpcError {
+                    code: -32602,
+                    message: "Invalid params".to_owned(),
+                    data: None,
+                })),
+                _ => Ok(to_raw(name)),
+            }
+        });
+        (name, client)
+    }
+
+    fn failover_client(
+        health_check_interval: Duration,
+        nodes: [(&'static str, Arc<AtomicBool>); 2],
+    ) -> (FailoverRpcClient, RpcClient) {
+        let client = FailoverRpcClient::builder()
+            .health_check_interval(health_check_interval)
+            .build_from_clients(nodes.map(|(name, down)| node(name, down)))
+            .unwrap();
+        (client.clone(), RpcClient::new(Arc::new(client)))
+    }
+
+    // A node which, if `stalled`, never responds to anything.
+    struct StallingNode {
+        client: MockRpcClient,
+        stalled: bool,
+    }
+
+    impl RpcClientT for StallingNode {
+        fn request_raw<'a>(
+            &'a self,
+            method: &'a str,
+            params: Option<Box<RawValue>>,
+        ) -> RpcFuture<'a, Box<RawValue>> {
+            if self.stalled {
+                return Box::pin(futures::future::pending());
+            }
+            self.client.request_raw(method, params)
+        }
+
+        fn subscribe_raw<'a>(
+            &'a self,
+            sub: &'a str,
+            params: Option<Box<RawValue>>,
+            unsub: &'a str,
+        ) -> RpcFuture<'a, RpcSubscription> {
+            if self.stalled {
+                return Box::pin(futures::future::pending());
+            }
+            self.client.subscribe_raw(sub, params, unsub)
+        }
+    }
+
+    #[tokio::test]
+    async fn fails_over_on_transport_errors() {
+        let a_down = Arc::new(AtomicBool::new(false));
+        let (failover, client) = failover_client(
+            Duration::from_secs(3600),
+            [
+                ("a", a_down.clone()),
+                ("b", Arc::new(AtomicBool::new(false))),
+            ],
+        );
+
+        // Both nodes pass the initial health check, and then "a" goes away. Requests are
+        // sent round-robin, so some of these go to "a" first and have to fail over to "b".
+        let _: String = client.request("foo", rpc_params![]).await.unwrap();
+        a_down.store(true, Ordering::SeqCst);
+        for _ in 0..3 {
+            let res: String = client.request("foo", rpc_params![]).await.unwrap();
+            assert_eq!(res, "b");
+        }
+        assert_eq!(failover.healthy_endpoints(), vec!["b"]);
+    }
+
+    #[tokio::test]
+    async fn does_not_fail_over_on_call_errors() {
+        let (failover, client) = failover_client(
+            Duration::from_secs(3600),
+            [
+                ("a", Arc::new(AtomicBool::new(false))),
+                ("b", Arc::new(AtomicBool::new(false))),
+            ],
+        );
+
+        let err = client
+            .request::<String>("bad", rpc_params![])
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, crate::Error::Rpc(RpcError::Call(e)) if e.code == -32602),
+            "unexpected error: {err}"
+        );
+        assert_eq!(failover.healthy_endpoints(), vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn unhealthy_nodes_recover() {
+        let a_down = Arc::new(AtomicBool::new(true));
+        // Check the health of the nodes before every request.
+        let (failover, client) = failover_client(
+            Duration::ZERO,
+            [
+                ("a", a_down.clone()),
+                ("b", Arc::new(AtomicBool::new(false))),
+            ],
+        );
+
+        let res: String = client.request("foo", rpc_params![]).await.unwrap();
+        assert_eq!(res, "b");
+        assert_eq!(failover.healthy_endpoints(), vec!["b"]);
+
+        // Once "a" is back, the next health check marks it healthy, and requests are
+        // sent to it again.
+        a_down.store(false, Ordering::SeqCst);
+        let mut responses = Vec::new();
+        for _ in 0..2 {
+            responses.push(
+                client
+                    .request::<String>("foo", rpc_params![])
+                    .await
+                    .unwrap(),
+            );
+        }
+        assert_eq!(failover.healthy_endpoints(), vec!["a", "b"]);
+        responses.sort();
+        assert_eq!(responses, vec!["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn stalled_health_checks_time_out() {
+        let nodes = [("a", true), ("b", false)].map(|(name, stalled)| {
+            let (name, client) = node(name, Arc::new(AtomicBool::new(false)));
+            (name, StallingNode { client, stalled })
+        });
+        let failover = FailoverRpcClient::builder()
+            .health_check_timeout(Duration::from_millis(50))
+            .build_from_clients(nodes)
+            .unwrap();
+        let client = RpcClient::new(Arc::new(failover.clone()));
+
+        // The request waits for the health check, which gives up on "a" rather than
+        // waiting for it forever, and so the request is sent to "b".
+        let res: String =
+            tokio::time::timeout(Duration::from_secs(5), client.request("foo", rpc_params![]))
+                .await
+                .expect("the health check should time out")
+                .unwrap();
+        assert_eq!(res, "b");
+        assert_eq!(failover.healthy_endpoints(), vec!["b"]);
+    }
+
+    #[tokio::test]
+    async fn transaction_submissions_are_not_retried() {
+        let a_down = Arc::new(AtomicBool::new(false));
+        let (failover, client) = failover_client(
+            Duration::from_secs(3600),
+            [
+                ("a", a_down.clone()),
+                ("b", Arc::new(AtomicBool::new(false))),
+            ],
+        );
+
+        // Both nodes pass the initial health check, and then "a" goes away. Requests are
+        // sent round-robin, so one of these goes to "a" first, and is not sent on to "b".
+        let _: String = client.request("foo", rpc_params![]).await.unwrap();
+        a_down.store(true, Ordering::SeqCst);
+        let mut responses = Vec::new();
+        let mut errors = Vec::new();
+        for _ in 0..2 {
+            match client
+                .request::<String>("author_submitExtrinsic", rpc_params![])
+                .await
+            {
+                Ok(res) => responses.push(res),
+                Err(e) => errors.push(e),
+            }
+        }
+        assert_eq!(responses, vec!["b"]);
+        assert!(
+            matches!(&errors[..], [crate::Error::Rpc(RpcError::ClientError(_))]),
+            "unexpected errors: {errors:?}"
+        );
+        assert_eq!(failover.healthy_endpoints(), vec!["b"]);
+    }
+
+    #[tokio::test]
+    async fn transaction_watch_subscriptions_are_not_moved() {
+        let nodes = ["a", "b"].map(|name| {
+            let (name, client) = node(name, Arc::new(AtomicBool::new(false)));
+            let client = client.on_subscribe(move |_sub| {
+                Ok(subscription([
+                    Ok(to_raw(name)),
+                    Err(RpcError::ClientError("connection reset".into())),
+                ]))
+            });
+            (name, client)
+        });
+        let failover = FailoverRpcClient::builder()
+            .health_check_interval(Duration::from_secs(3600))
+            .build_from_clients(nodes)
+            .unwrap();
+
+        // Other subscriptions are moved to another node after an error like this one,
+        // but this one ends, since resubscribing would submit the transaction again.
+        let mut sub = failover
+            .subscribe_raw(
+                "author_submitAndWatchExtrinsic",
+                None,
+                "author_unwatchExtrinsic",
+            )
+            .await
+            .unwrap()
+            .stream;
+        assert!(sub.next().await.unwrap().is_ok());
+        let err = sub.next().await.unwrap().unwrap_err();
+        assert!(
+            matches!(err, RpcError::ClientError(_)),
+            "unexpected error: {err}"
+        );
+        assert!(sub.next().await.is_none());
+        assert_eq!(failover.healthy_endpoints().len(), 1);
+    }
+}
@@ -0,0 +1,276 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! An [`RpcClientT`] implementation which talks to a node over HTTP.
+
+use super::{
+    jsonrpsee_impl::{batch_request, Params},
+    RpcClientT, RpcFuture, RpcSubscription,
+};
+use crate::error::RpcError;
+use futures::StreamExt;
+use jsonrpsee::{
+    core::client::ClientT,
+    http_client::{HttpClient, HttpClientBuilder},
+};
+use serde_json::value::RawValue;
+use std::{sync::Arc, time::Duration};
+
+/// An [`RpcClientT`] implementation, based on `jsonrpsee`, which makes requests
+/// to a node over HTTP.
+///
+/// HTTP has no notion of subscriptions, and so [`RpcClientT::subscribe_raw`] will
+/// return [`RpcError::SubscriptionsUnsupported`] for almost every method. The
+/// exceptions are `chain_subscribeFinalizedHeads` and `chain_subscribeNewHeads`,
+/// which are emulated by polling the node for the latest finalized or best block
+/// header. This means that APIs like [`crate::blocks::BlocksClient::subscribe_finalized()`]
+/// continue to work, while those like [`crate::tx::SubmittableExtrinsic::submit_and_watch()`]
+/// do not; use [`crate::tx::SubmittableExtrinsic::submit()`] instead.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use std::sync::Arc;
+/// use subxt::{rpc::HttpRpcClient, OnlineClient, PolkadotConfig};
+///
+/// let rpc_client = HttpRpcClient::new("http://127.0.0.1:9933").unwrap();
+/// let api = OnlineClient::<PolkadotConfig>::from_rpc_client(Arc::new(rpc_client))
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct HttpRpcClient {
+    client: Arc<HttpClient>,
+    poll_interval: Duration,
+}
+
+impl HttpRpcClient {
+    /// Create a new client which will make requests to the given URL.
+    pub fn new(url: impl AsRef<str>) -> Result<Self, RpcError> {
+        let client = HttpClientBuilder::default()
+            .build(url)
+            .map_err(|e| RpcError::ClientError(Box::new(e)))?;
+        Ok(Self::from_client(client))
+    }
+
+    /// Create a new client from an already configured [`HttpClient`].
+    pub fn from_client(client: HttpClient) -> Self {
+        Self {
+            client: Arc::new(client),
+            poll_interval: Duration::from_secs(6),
+        }
+    }
+
+    /// How often to poll the node for new block headers when emulating the
+    /// supported subscriptions. Defaults to 6 seconds.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+}
+
+impl RpcClientT for HttpRpcClient {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            let res = ClientT::request(&*self.client, method, Params(params)).await?;
+            Ok(res)
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        _params: Option<Box<RawValue>>,
+        _unsub: &'a str,
+    ) -> RpcFuture<'a, RpcSubscription> {
+        Box::pin(async move {
+            let head_method = match sub {
+                "chain_subscribeFinalizedHeads" => HeadMethod::Finalized,
+                "chain_subscribeNewHeads" => HeadMethod::Best,
+                _ => return Err(RpcError::SubscriptionsUnsupported(sub.to_owned())),
+            };
+
+            let state = PollState {
+                client: self.client.clone(),
+                poll_interval: self.poll_interval,
+                head_method,
+                last_hash: None,
+                first: true,
+            };
+            let stream = futures::stream::unfold(state, |mut state| async move {
+                let item = state.next_header().await;
+                Some((item, state))
+            })
+            .boxed();
+
+            Ok(RpcSubscription { stream, id: None })
+        })
+    }
+
+    fn batch_request_raw<'a>(
+        &'a self,
+        batch: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        Box::pin(batch_request(&*self.client, batch))
+    }
+}
+
+// Which head we are polling for.
+#[derive(Debug, Clone, Copy)]
+enum HeadMethod {
+    Finalized,
+    Best,
+}
+
+// The state needed to emulate a header subscription by polling.
+struct PollState {
+    client: Arc<HttpClient>,
+    poll_interval: Duration,
+    head_method: HeadMethod,
+    last_hash: Option<String>,
+    first: bool,
+}
+
+impl PollState {
+    // Wait until the head hash changes, and then hand back the corresponding header.
+    async fn next_header(&mut self) -> Result<Box<RawValue>, RpcError> {
+        loop {
+            if !std::mem::take(&mut self.first) {
+                tokio::time::sleep(self.poll_interval).await;
+            }
+
+            let hash: Box<RawValue> = match self.head_method {
+                HeadMethod::Finalized => self.request("chain_getFinalizedHead", None).await?,
+                HeadMethod::Best => self.request("chain_getBlockHash", None).await?,
+            };
+            if self.last_hash.as_deref() == Some(hash.get()) {
+                continue;
+            }
+
+            let params = RawValue::from_string(format!("[{}]", hash.get()))
+                .expect("a JSON value in an array is valid JSON; qed");
+            let header = self.request("chain_getHeader", Some(params)).await?;
+            self.last_hash = Some(hash.get().to_owned());
+            return Ok(header);
+        }
+    }
+
+    async fn request(
+        &self,
+        method: &str,
+        params: Option<Box<RawValue>>,
+    ) -> Result<Box<RawValue>, RpcError> {
+        ClientT::request(&*self.client, method, Params(params))
+            .await
+            .map_err(RpcError::from)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::JsonRpcError;
+    use serde_json::{json, Value};
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+    };
+
+    // Answer a single HTTP request with the given function, handing back the URL to send
+    // the request to.
+    fn serve_once(respond: impl FnOnce(Value) -> Value + Send + 'static) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        content_length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+
+            let response = respond(serde_json::from_slice(&body).unwrap()).to_string();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                response.len(),
+                response
+            )
+            .unwrap();
+        });
+
+        url
+    }
+
+    #[tokio::test]
+    async fn subscriptions_are_unsupported() {
+        // Nothing is listening here, but we should fail before trying to connect.
+        let client = HttpRpcClient::new("http://127.0.0.1:1").unwrap();
+        let res = client
+            .subscribe_raw(
+                "author_submitAndWatchExtrinsic",
+                None,
+                "author_unwatchExtrinsic",
+            )
+            .await;
+        assert!(matches!(
+            res,
+            Err(RpcError::SubscriptionsUnsupported(m)) if m == "author_submitAndWatchExtrinsic"
+        ));
+    }
+
+    #[tokio::test]
+    async fn batch_results_are_mapped_to_requests() {
+        // Respond to each request with its method name (or an error for "fail"), handing
+        // the responses back in the reverse order to the requests.
+        let url = serve_once(|batch| {
+            let responses = batch.as_array().unwrap().iter().rev().map(|req| {
+                let id = &req["id"];
+                match req["method"].as_str().unwrap() {
+                    "fail" => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": -32601, "message": "Method not found" },
+                    }),
+                    method => json!({ "jsonrpc": "2.0", "id": id, "result": method }),
+                }
+            });
+            Value::Array(responses.collect())
+        });
+
+        let client = HttpRpcClient::new(url).unwrap();
+        let res = client
+            .batch_request_raw(vec![("a", None), ("fail", None), ("b", None)])
+            .await
+            .unwrap();
+
+        assert_eq!(res.len(), 3);
+        assert_eq!(res[0].as_ref().unwrap().get(), r#""a""#);
+        assert!(
+            matches!(&res[1], Err(RpcError::Call(e)) if e.code == JsonRpcError::METHOD_NOT_FOUND)
+        );
+        assert_eq!(res[2].as_ref().unwrap().get(), r#""b""#);
+    }
+}
@@ -2,20 +2,39 @@
 // This file is dual-licensed as Apache-2.0 or GPL-3.0.
 // see LICENSE for license details.
 
-use super::{RpcClientT, RpcFuture, RpcSubscription};
-use crate::error::RpcError;
-use futures::stream::{StreamExt, TryStreamExt};
+use crate::error::{JsonRpcError, RpcError};
 use jsonrpsee::{
     core::{
-        client::{Client, ClientT, SubscriptionClientT, SubscriptionKind},
+        client::{BatchResponse, ClientT},
+        params::BatchRequestBuilder,
         traits::ToRpcParams,
         Error as JsonRpseeError,
     },
-    types::SubscriptionId,
+    types::{error::CallError, ErrorObject},
 };
 use serde_json::value::RawValue;
 
-struct Params(Option<Box<RawValue>>);
+impl From<JsonRpseeError> for RpcError {
+    fn from(e: JsonRpseeError) -> Self {
+        match e {
+            JsonRpseeError::Call(CallError::Custom(e)) => RpcError::Call(e.into()),
+            e => RpcError::ClientError(Box::new(e)),
+        }
+    }
+}
+
+impl<'a> From<ErrorObject<'a>> for JsonRpcError {
+    fn from(e: ErrorObject<'a>) -> Self {
+        JsonRpcError {
+            code: e.code(),
+            message: e.message().to_owned(),
+            data: e.data().and_then(|d| serde_json::from_str(d.get()).ok()),
+        }
+    }
+}
+
+/// Already-serialized params, handed as-is to `jsonrpsee`.
+pub(crate) struct Params(pub(crate) Option<Box<RawValue>>);
 
 impl ToRpcParams for Params {
     fn to_rpc_params(self) -> Result<Option<Box<RawValue>>, JsonRpseeError> {
@@ -23,47 +42,121 @@ impl ToRpcParams for Params {
     }
 }
 
-impl RpcClientT for Client {
-    fn request_raw<'a>(
-        &'a self,
-        method: &'a str,
-        params: Option<Box<RawValue>>,
-    ) -> RpcFuture<'a, Box<RawValue>> {
-        Box::pin(async move {
-            let res = ClientT::request(self, method, Params(params))
+/// Send a batch of requests using the native `jsonrpsee` batch support, handing back
+/// a result for each request in the order that they were given.
+pub(crate) async fn batch_request<C: ClientT + Sync>(
+    client: &C,
+    batch: Vec<(&str, Option<Box<RawValue>>)>,
+) -> Result<Vec<Result<Box<RawValue>, RpcError>>, RpcError> {
+    // jsonrpsee refuses to send empty batches.
+    if batch.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut builder = BatchRequestBuilder::new();
+    for (method, params) in batch {
+        builder
+            .insert(method, Params(params))
+            .map_err(RpcError::from)?;
+    }
+
+    let res = ClientT::batch_request::<Box<RawValue>>(client, builder).await?;
+    Ok(batch_results(res))
+}
+
+// jsonrpsee hands back the batch results in the order that the requests were made.
+fn batch_results(res: BatchResponse<'_, Box<RawValue>>) -> Vec<Result<Box<RawValue>, RpcError>> {
+    res.into_iter()
+        .map(|r| r.map_err(|e| RpcError::Call(e.into())))
+        .collect()
+}
+
+#[cfg(any(feature = "jsonrpsee-ws", feature = "jsonrpsee-web"))]
+mod async_client {
+    use super::{batch_request, Params};
+    use crate::{
+        error::RpcError,
+        rpc::{RpcClientT, RpcFuture, RpcSubscription},
+    };
+    use futures::stream::{StreamExt, TryStreamExt};
+    use jsonrpsee::{
+        core::client::{Client, ClientT, SubscriptionClientT, SubscriptionKind},
+        types::SubscriptionId,
+    };
+    use serde_json::value::RawValue;
+
+    impl RpcClientT for Client {
+        fn request_raw<'a>(
+            &'a self,
+            method: &'a str,
+            params: Option<Box<RawValue>>,
+        ) -> RpcFuture<'a, Box<RawValue>> {
+            Box::pin(async move {
+                let res = ClientT::request(self, method, Params(params)).await?;
+                Ok(res)
+            })
+        }
+
+        fn subscribe_raw<'a>(
+            &'a self,
+            sub: &'a str,
+            params: Option<Box<RawValue>>,
+            unsub: &'a str,
+        ) -> RpcFuture<'a, RpcSubscription> {
+            Box::pin(async move {
+                let stream = SubscriptionClientT::subscribe::<Box<RawValue>, _>(
+                    self,
+                    sub,
+                    Params(params),
+                    unsub,
+                )
                 .await
-                .map_err(|e| RpcError::ClientError(Box::new(e)))?;
-            Ok(res)
-        })
+                .map_err(RpcError::from)?;
+
+                let id = match stream.kind() {
+                    SubscriptionKind::Subscription(SubscriptionId::Str(id)) => {
+                        Some(id.clone().into_owned())
+                    }
+                    _ => None,
+                };
+
+                let stream = stream.map_err(RpcError::from).boxed();
+                Ok(RpcSubscription { stream, id })
+            })
+        }
+
+        fn batch_request_raw<'a>(
+            &'a self,
+            batch: Vec<(&'a str, Option<Box<RawValue>>)>,
+        ) -> RpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+            Box::pin(batch_request(self, batch))
+        }
     }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::mock_rpc_client::to_raw;
+
+    #[test]
+    fn batch_results_are_mapped_in_order() {
+        let res = BatchResponse::new(
+            2,
+            vec![
+                Ok(to_raw("a")),
+                Err(ErrorObject::owned(-32601, "Method not found", None::<()>)),
+                Ok(to_raw("b")),
+            ],
+            1,
+        );
+        let res = batch_results(res);
 
-    fn subscribe_raw<'a>(
-        &'a self,
-        sub: &'a str,
-        params: Option<Box<RawValue>>,
-        unsub: &'a str,
-    ) -> RpcFuture<'a, RpcSubscription> {
-        Box::pin(async move {
-            let stream = SubscriptionClientT::subscribe::<Box<RawValue>, _>(
-                self,
-                sub,
-                Params(params),
-                unsub,
-            )
-            .await
-            .map_err(|e| RpcError::ClientError(Box::new(e)))?;
-
-            let id = match stream.kind() {
-                SubscriptionKind::Subscription(SubscriptionId::Str(id)) => {
-                    Some(id.clone().into_owned())
-                }
-                _ => None,
-            };
-
-            let stream = stream
-                .map_err(|e| RpcError::ClientError(Box::new(e)))
-                .boxed();
-            Ok(RpcSubscription { stream, id })
-        })
+        assert_eq!(res.len(), 3);
+        assert_eq!(res[0].as_ref().unwrap().get(), r#""a""#);
+        assert!(
+            matches!(&res[1], Err(RpcError::Call(e)) if e.code == JsonRpcError::METHOD_NOT_FOUND)
+        );
+        assert_eq!(res[2].as_ref().unwrap().get(), r#""b""#);
     }
 }
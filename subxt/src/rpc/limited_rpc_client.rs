@@ -0,0 +1,428 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! An [`RpcClientT`] middleware which applies timeouts, a rate limit and a cap on the
+//! number of in-flight requests to the client that it wraps.
+
+use super::{RpcClientT, RpcFuture, RpcSubscription};
+use crate::error::RpcError;
+use serde_json::value::RawValue;
+use std::{
+    future::Future,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+use tokio::sync::Semaphore;
+
+/// A builder to configure and construct a [`LimitedRpcClient`]. By default, no limits
+/// are applied at all.
+#[derive(Debug, Clone, Default)]
+pub struct LimitedRpcClientBuilder {
+    request_timeout: Option<Duration>,
+    rate_limit: Option<(u32, Duration)>,
+    burst: Option<u32>,
+    max_concurrent_requests: Option<usize>,
+}
+
+impl LimitedRpcClientBuilder {
+    /// Create a new builder which applies no limits.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail any request (or attempt to subscribe) which has not received a response
+    /// within the given duration with [`RpcError::RequestTimeout`]. Time spent waiting
+    /// on the rate limit or the concurrency cap does not count towards this.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = Some(timeout);
+        self
+    }
+
+    /// Send no more than `requests` requests every `per` duration, on average. Requests
+    /// over this limit wait until they are allowed to be sent. Each request in a batch
+    /// counts separately towards this limit, as does each attempt to subscribe.
+    pub fn rate_limit(mut self, requests: u32, per: Duration) -> Self {
+        self.rate_limit = Some((requests, per));
+        self
+    }
+
+    /// The number of requests that can be sent in quick succession once the rate limit
+    /// has not been reached for a while. This defaults to the number of requests given
+    /// in [`LimitedRpcClientBuilder::rate_limit()`], and does nothing without it.
+    pub fn burst(mut self, requests: u32) -> Self {
+        self.burst = Some(requests);
+        self
+    }
+
+    /// Allow at most this many requests to be waiting on a response at any one time.
+    /// Further requests wait until one of these has completed. Each request in a batch
+    /// counts separately towards this limit (a batch of more requests than this waits
+    /// until no others are in flight). Open subscriptions do not count towards it.
+    pub fn max_concurrent_requests(mut self, max: usize) -> Self {
+        self.max_concurrent_requests = Some(max);
+        self
+    }
+
+    /// Wrap the given client, applying the configured limits to it.
+    pub fn build<C: RpcClientT>(self, inner: C) -> LimitedRpcClient<C> {
+        let rate_limit = self.rate_limit.and_then(|(requests, per)| {
+            TokenBucket::new(requests, per, self.burst.unwrap_or(requests))
+        });
+        LimitedRpcClient {
+            inner,
+            request_timeout: self.request_timeout,
+            rate_limit,
+            concurrency: self
+                .max_concurrent_requests
+                .map(|max| (Semaphore::new(max), max)),
+        }
+    }
+}
+
+/// An [`RpcClientT`] implementation which wraps some other [`RpcClientT`], applying a
+/// per-request timeout, a token bucket rate limit and a cap on the number of in-flight
+/// requests to everything sent through it.
+///
+/// Since this can be handed to [`crate::OnlineClient::from_rpc_client()`], every other
+/// API (storage, blocks, transactions and so on) respects the limits configured here.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use std::{sync::Arc, time::Duration};
+/// use subxt::{rpc::LimitedRpcClient, OnlineClient, PolkadotConfig};
+///
+/// let ws_client = subxt::client::default_rpc_client("ws://127.0.0.1:9944")
+///     .await
+///     .unwrap();
+/// let rpc_client = LimitedRpcClient::builder()
+///     .request_timeout(Duration::from_secs(30))
+///     .rate_limit(50, Duration::from_secs(1))
+///     .max_concurrent_requests(16)
+///     .build(ws_client);
+///
+/// let api = OnlineClient::<PolkadotConfig>::from_rpc_client(Arc::new(rpc_client))
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub struct LimitedRpcClient<C> {
+    inner: C,
+    request_timeout: Option<Duration>,
+    rate_limit: Option<TokenBucket>,
+    // The semaphore limiting concurrent requests, and the number of permits it has.
+    concurrency: Option<(Semaphore, usize)>,
+}
+
+impl<C> std::fmt::Debug for LimitedRpcClient<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LimitedRpcClient")
+            .field("request_timeout", &self.request_timeout)
+            .field("rate_limit", &self.rate_limit)
+            .field("concurrency", &self.concurrency)
+            .finish()
+    }
+}
+
+impl LimitedRpcClient<()> {
+    /// Configure and construct a new [`LimitedRpcClient`].
+    pub fn builder() -> LimitedRpcClientBuilder {
+        LimitedRpcClientBuilder::new()
+    }
+}
+
+impl<C: RpcClientT> LimitedRpcClient<C> {
+    // Wait until the rate limit and concurrency cap allow `requests` requests to be
+    // sent, and then make them using the given function, applying the request timeout.
+    // The inner client isn't called until then, so that nothing is sent early.
+    async fn limited<'a, T, F>(
+        &'a self,
+        requests: usize,
+        make_requests: impl FnOnce() -> F,
+    ) -> Result<T, RpcError>
+    where
+        F: Future<Output = Result<T, RpcError>> + 'a,
+    {
+        if let Some(rate_limit) = &self.rate_limit {
+            for _ in 0..requests {
+                rate_limit.acquire().await;
+            }
+        }
+        let _permit = match &self.concurrency {
+            Some((semaphore, max)) => {
+                // Take every permit for batches larger than the cap, else we'd wait forever.
+                let permits = requests.min(*max) as u32;
+                Some(
+                    semaphore
+                        .acquire_many(permits)
+                        .await
+                        .expect("semaphore is never closed; qed"),
+                )
+            }
+            None => None,
+        };
+        let fut = make_requests();
+        match self.request_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, fut)
+                .await
+                .map_err(|_| RpcError::RequestTimeout(timeout))?,
+            None => fut.await,
+        }
+    }
+}
+
+impl<C: RpcClientT> RpcClientT for LimitedRpcClient<C> {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RpcFuture<'a, Box<RawValue>> {
+        Box::pin(self.limited(1, move || self.inner.request_raw(method, params)))
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RpcFuture<'a, RpcSubscription> {
+        Box::pin(self.limited(1, move || self.inner.subscribe_raw(sub, params, unsub)))
+    }
+
+    fn batch_request_raw<'a>(
+        &'a self,
+        batch: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        let requests = batch.len();
+        Box::pin(self.limited(requests, move || self.inner.batch_request_raw(batch)))
+    }
+}
+
+// A token bucket, which refills at a constant rate up to some capacity. A token is
+// taken each time a request is made.
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    tokens_per_sec: f64,
+    state: Mutex<TokenBucketState>,
+}
+
+#[derive(Debug)]
+struct TokenBucketState {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    // A rate of zero is treated as no limit at all, and so returns `None`.
+    fn new(requests: u32, per: Duration, burst: u32) -> Option<Self> {
+        if requests == 0 || per.is_zero() {
+            return None;
+        }
+        let capacity = f64::from(burst.max(1));
+        Some(Self {
+            capacity,
+            tokens_per_sec: f64::from(requests) / per.as_secs_f64(),
+            state: Mutex::new(TokenBucketState {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        })
+    }
+
+    // Wait until a token is available, and take it.
+    async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().expect("shouldn't be poisoned");
+                let now = Instant::now();
+                let refilled =
+                    now.duration_since(state.last_refill).as_secs_f64() * self.tokens_per_sec;
+                state.tokens = (state.tokens + refilled).min(self.capacity);
+                state.last_refill = now;
+
+                if state.tokens >= 1.0 {
+                    state.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - state.tokens) / self.tokens_per_sec)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::{rpc_params, RpcClient};
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    // A client which takes the given time to respond to each request (or batch of them),
+    // keeping track of the most requests that were in flight at once, and of how many
+    // times it has been called.
+    #[derive(Default)]
+    struct SlowClient {
+        delay: Duration,
+        calls: AtomicUsize,
+        in_flight: AtomicUsize,
+        max_in_flight: AtomicUsize,
+    }
+
+    impl SlowClient {
+        async fn respond(&self, requests: usize) {
+            let n = self.in_flight.fetch_add(requests, Ordering::SeqCst) + requests;
+            self.max_in_flight.fetch_max(n, Ordering::SeqCst);
+            tokio::time::sleep(self.delay).await;
+            self.in_flight.fetch_sub(requests, Ordering::SeqCst);
+        }
+    }
+
+    impl RpcClientT for SlowClient {
+        fn request_raw<'a>(
+            &'a self,
+            method: &'a str,
+            _params: Option<Box<RawValue>>,
+        ) -> RpcFuture<'a, Box<RawValue>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                self.respond(1).await;
+                Ok(serde_json::value::to_raw_value(method).unwrap())
+            })
+        }
+
+        fn batch_request_raw<'a>(
+            &'a self,
+            batch: Vec<(&'a str, Option<Box<RawValue>>)>,
+        ) -> RpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                self.respond(batch.len()).await;
+                let res = batch
+                    .into_iter()
+                    .map(|(method, _)| Ok(serde_json::value::to_raw_value(method).unwrap()))
+                    .collect();
+                Ok(res)
+            })
+        }
+
+        fn subscribe_raw<'a>(
+            &'a self,
+            _sub: &'a str,
+            _params: Option<Box<RawValue>>,
+            _unsub: &'a str,
+        ) -> RpcFuture<'a, RpcSubscription> {
+            Box::pin(async move { Err(RpcError::SubscriptionsUnsupported("test".to_owned())) })
+        }
+    }
+
+    fn slow_client(delay: Duration) -> SlowClient {
+        SlowClient {
+            delay,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn times_out_slow_requests() {
+        let client = RpcClient::new(Arc::new(
+            LimitedRpcClient::builder()
+                .request_timeout(Duration::from_millis(10))
+                .build(slow_client(Duration::from_secs(10))),
+        ));
+
+        let err = client
+            .request::<String>("foo", rpc_params![])
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, crate::Error::Rpc(RpcError::RequestTimeout(_))),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[tokio::test]
+    async fn caps_concurrent_requests() {
+        let limited = Arc::new(
+            LimitedRpcClient::builder()
+                .max_concurrent_requests(2)
+                .build(slow_client(Duration::from_millis(20))),
+        );
+        let client = RpcClient::new(limited.clone());
+
+        let requests = (0..6).map(|_| client.request::<String>("foo", rpc_params![]));
+        for res in futures::future::join_all(requests).await {
+            assert_eq!(res.unwrap(), "foo");
+        }
+        assert_eq!(limited.inner.max_in_flight.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn rate_limits_requests() {
+        let client = RpcClient::new(Arc::new(
+            LimitedRpcClient::builder()
+                .rate_limit(100, Duration::from_secs(1))
+                .burst(1)
+                .build(slow_client(Duration::ZERO)),
+        ));
+
+        // One request can be made immediately, and the rest are spaced 10ms apart.
+        let started = Instant::now();
+        for _ in 0..5 {
+            let _: String = client.request("foo", rpc_params![]).await.unwrap();
+        }
+        assert!(started.elapsed() >= Duration::from_millis(35));
+    }
+
+    #[tokio::test]
+    async fn batches_count_each_request_towards_the_cap() {
+        let limited = Arc::new(
+            LimitedRpcClient::builder()
+                .max_concurrent_requests(3)
+                .build(slow_client(Duration::from_millis(20))),
+        );
+        let client = RpcClient::new(limited.clone());
+
+        // A batch of two and two single requests is four requests, so they can't all be
+        // in flight at once.
+        let batch = client.batch_request::<String>([("a", rpc_params![]), ("b", rpc_params![])]);
+        let singles = (0..2).map(|_| client.request::<String>("foo", rpc_params![]));
+        let (batch, singles) = futures::join!(batch, futures::future::join_all(singles));
+        assert!(batch.unwrap().into_iter().all(|res| res.is_ok()));
+        assert!(singles.into_iter().all(|res| res.is_ok()));
+        assert_eq!(limited.inner.max_in_flight.load(Ordering::SeqCst), 3);
+
+        // Batches larger than the cap wait for every permit, rather than forever.
+        let batch = (0..5).map(|_| ("foo", rpc_params![]));
+        let res = client.batch_request::<String>(batch).await.unwrap();
+        assert_eq!(res.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn requests_are_not_made_until_allowed() {
+        let limited = LimitedRpcClient::builder()
+            .max_concurrent_requests(1)
+            .build(slow_client(Duration::from_secs(10)));
+
+        // While the first request holds the only permit, the second is queued without
+        // calling the inner client.
+        let mut first = limited.request_raw("foo", None);
+        let mut second = limited.request_raw("foo", None);
+        let waiting = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            limited.inner.calls.load(Ordering::SeqCst)
+        };
+        let calls = tokio::select! {
+            _ = &mut first => panic!("the first request should still be in flight"),
+            _ = &mut second => panic!("the second request should be queued"),
+            calls = waiting => calls,
+        };
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,387 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! An [`RpcClientT`] middleware which records metrics about, and emits `tracing` spans
+//! for, every call made through it.
+
+use super::{RpcClientT, RpcFuture, RpcSubscription, RpcSubscriptionStream};
+use crate::error::RpcError;
+use futures::StreamExt;
+use serde_json::value::RawValue;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::Instrument;
+
+/// Metrics recorded for a single RPC method.
+///
+/// Subscriptions are recorded against the name of the method used to subscribe, so for
+/// instance `chain_subscribeNewHeads` would count the number of times we subscribed,
+/// how long it took to do so, and how many of these subscriptions are still open.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MethodMetrics {
+    /// The number of requests (or subscriptions) made using this method.
+    pub requests: u64,
+    /// The number of requests (or subscriptions) which failed.
+    pub errors: u64,
+    /// The total time spent waiting for responses to requests using this method.
+    pub total_latency: Duration,
+    /// The longest time spent waiting for a response to a request using this method.
+    pub max_latency: Duration,
+    /// The number of subscriptions using this method which are currently open.
+    pub open_subscriptions: u64,
+    /// The number of items handed back by subscriptions using this method, including
+    /// any errors.
+    pub subscription_items: u64,
+    /// The number of errors handed back by subscriptions using this method. These are
+    /// not counted in [`MethodMetrics::errors`].
+    pub subscription_errors: u64,
+}
+
+impl MethodMetrics {
+    /// The average time spent waiting for a response, or `None` if no requests
+    /// have been made.
+    pub fn average_latency(&self) -> Option<Duration> {
+        let requests = u32::try_from(self.requests).ok().filter(|&n| n > 0)?;
+        Some(self.total_latency / requests)
+    }
+
+    /// The proportion of requests which failed, between 0 and 1.
+    pub fn error_rate(&self) -> f64 {
+        if self.requests == 0 {
+            return 0.0;
+        }
+        self.errors as f64 / self.requests as f64
+    }
+
+    fn record_latency(&mut self, latency: Duration) {
+        self.total_latency += latency;
+        self.max_latency = self.max_latency.max(latency);
+    }
+}
+
+/// A handle to the metrics recorded by a [`MetricsRpcClient`]. This is cheap to clone,
+/// and every clone sees the same metrics.
+#[derive(Clone, Debug, Default)]
+pub struct RpcMetrics {
+    methods: Arc<Mutex<HashMap<String, MethodMetrics>>>,
+}
+
+impl RpcMetrics {
+    /// The metrics recorded for the given method so far, if any calls have been made
+    /// to it.
+    pub fn method(&self, method: &str) -> Option<MethodMetrics> {
+        self.methods
+            .lock()
+            .expect("shouldn't be poisoned")
+            .get(method)
+            .cloned()
+    }
+
+    /// A copy of the metrics recorded so far for every method that has been called.
+    pub fn snapshot(&self) -> HashMap<String, MethodMetrics> {
+        self.methods.lock().expect("shouldn't be poisoned").clone()
+    }
+
+    /// Forget about all of the metrics recorded so far. Subscriptions which are still
+    /// open will be counted again from zero once they close, so open subscription
+    /// counts may be briefly inaccurate after this.
+    pub fn reset(&self) {
+        self.methods.lock().expect("shouldn't be poisoned").clear();
+    }
+
+    fn with_method(&self, method: &str, f: impl FnOnce(&mut MethodMetrics)) {
+        let mut methods = self.methods.lock().expect("shouldn't be poisoned");
+        match methods.get_mut(method) {
+            Some(m) => f(m),
+            None => f(methods.entry(method.to_owned()).or_default()),
+        }
+    }
+}
+
+/// An [`RpcClientT`] implementation which wraps some other [`RpcClientT`] and records
+/// [`MethodMetrics`] for every method called through it. A `tracing` span is also
+/// entered for the duration of each request, containing the method name, the size of
+/// the params in bytes, and (once complete) the time taken.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use std::sync::Arc;
+/// use subxt::{rpc::MetricsRpcClient, OnlineClient, PolkadotConfig};
+///
+/// let ws_client = subxt::client::default_rpc_client("ws://127.0.0.1:9944")
+///     .await
+///     .unwrap();
+/// let rpc_client = MetricsRpcClient::new(ws_client);
+/// let metrics = rpc_client.metrics();
+///
+/// let api = OnlineClient::<PolkadotConfig>::from_rpc_client(Arc::new(rpc_client))
+///     .await
+///     .unwrap();
+///
+/// // ... use the client ...
+///
+/// for (method, m) in metrics.snapshot() {
+///     println!("{method}: {} requests, avg {:?}", m.requests, m.average_latency());
+/// }
+/// # }
+/// ```
+pub struct MetricsRpcClient<C> {
+    inner: C,
+    metrics: RpcMetrics,
+}
+
+impl<C> std::fmt::Debug for MetricsRpcClient<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetricsRpcClient")
+            .field("metrics", &self.metrics)
+            .finish()
+    }
+}
+
+impl<C: RpcClientT> MetricsRpcClient<C> {
+    /// Wrap the given client, recording metrics for every call made through it.
+    pub fn new(inner: C) -> Self {
+        Self::with_metrics(inner, RpcMetrics::default())
+    }
+
+    /// Wrap the given client, recording metrics into an existing [`RpcMetrics`]. This
+    /// allows the metrics for several clients to be aggregated together.
+    pub fn with_metrics(inner: C, metrics: RpcMetrics) -> Self {
+        Self { inner, metrics }
+    }
+
+    /// A handle to the metrics recorded by this client.
+    pub fn metrics(&self) -> RpcMetrics {
+        self.metrics.clone()
+    }
+}
+
+impl<C: RpcClientT> RpcClientT for MetricsRpcClient<C> {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RpcFuture<'a, Box<RawValue>> {
+        let span = tracing::debug_span!(
+            "rpc_request",
+            method,
+            params_len = params_len(&params),
+            latency_ms = tracing::field::Empty,
+        );
+        let fut = async move {
+            let started = Instant::now();
+            let res = self.inner.request_raw(method, params).await;
+            let latency = started.elapsed();
+
+            record_completion(&tracing::Span::current(), latency, &res);
+            self.metrics.with_method(method, |m| {
+                m.requests += 1;
+                m.errors += res.is_err() as u64;
+                m.record_latency(latency);
+            });
+            res
+        };
+        Box::pin(fut.instrument(span))
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RpcFuture<'a, RpcSubscription> {
+        let span = tracing::debug_span!(
+            "rpc_subscribe",
+            method = sub,
+            params_len = params_len(&params),
+            latency_ms = tracing::field::Empty,
+        );
+        let fut = async move {
+            let started = Instant::now();
+            let res = self.inner.subscribe_raw(sub, params, unsub).await;
+            let latency = started.elapsed();
+
+            record_completion(&tracing::Span::current(), latency, &res);
+            self.metrics.with_method(sub, |m| {
+                m.requests += 1;
+                m.errors += res.is_err() as u64;
+                m.open_subscriptions += res.is_ok() as u64;
+                m.record_latency(latency);
+            });
+            let subscription = res?;
+
+            let guard = OpenSubscription {
+                metrics: self.metrics.clone(),
+                method: sub.to_owned(),
+            };
+            let stream: RpcSubscriptionStream = subscription
+                .stream
+                .map(move |item| {
+                    guard.metrics.with_method(&guard.method, |m| {
+                        m.subscription_items += 1;
+                        m.subscription_errors += item.is_err() as u64;
+                    });
+                    item
+                })
+                .boxed();
+
+            Ok(RpcSubscription {
+                stream,
+                id: subscription.id,
+            })
+        };
+        Box::pin(fut.instrument(span))
+    }
+
+    fn batch_request_raw<'a>(
+        &'a self,
+        batch: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        let span = tracing::debug_span!(
+            "rpc_batch_request",
+            batch_len = batch.len(),
+            params_len = batch.iter().map(|(_, p)| params_len(p)).sum::<usize>(),
+            latency_ms = tracing::field::Empty,
+        );
+        let fut = async move {
+            let methods: Vec<&str> = batch.iter().map(|(method, _)| *method).collect();
+            let started = Instant::now();
+            let res = self.inner.batch_request_raw(batch).await;
+            let latency = started.elapsed();
+
+            record_completion(&tracing::Span::current(), latency, &res);
+            // Each request in the batch is recorded individually, and is considered to
+            // have taken as long as the whole batch did.
+            for (idx, method) in methods.into_iter().enumerate() {
+                let failed = match &res {
+                    Ok(results) => results.get(idx).map_or(true, |r| r.is_err()),
+                    Err(_) => true,
+                };
+                self.metrics.with_method(method, |m| {
+                    m.requests += 1;
+                    m.errors += failed as u64;
+                    m.record_latency(latency);
+                });
+            }
+            res
+        };
+        Box::pin(fut.instrument(span))
+    }
+}
+
+// Decrements the open subscription count for a method when dropped.
+struct OpenSubscription {
+    metrics: RpcMetrics,
+    method: String,
+}
+
+impl Drop for OpenSubscription {
+    fn drop(&mut self) {
+        self.metrics.with_method(&self.method, |m| {
+            m.open_subscriptions = m.open_subscriptions.saturating_sub(1);
+        });
+    }
+}
+
+fn params_len(params: &Option<Box<RawValue>>) -> usize {
+    params.as_ref().map_or(0, |p| p.get().len())
+}
+
+fn record_completion<T>(span: &tracing::Span, latency: Duration, res: &Result<T, RpcError>) {
+    span.record("latency_ms", latency.as_millis() as u64);
+    if let Err(e) = res {
+        tracing::debug!("RPC call failed after {latency:?}: {e}");
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::{
+        mock_rpc_client::{subscription, to_raw, MockRpcClient},
+        rpc_params, RpcClient,
+    };
+
+    #[tokio::test]
+    async fn records_method_metrics() {
+        let client = MetricsRpcClient::new(MockRpcClient::new());
+        let metrics = client.metrics();
+        let client = RpcClient::new(Arc::new(client));
+
+        for _ in 0..3 {
+            let _: String = client.request("foo", rpc_params![1]).await.unwrap();
+        }
+        assert!(client
+            .request::<String>("fail", rpc_params![])
+            .await
+            .is_err());
+
+        let foo = metrics.method("foo").unwrap();
+        assert_eq!(foo.requests, 3);
+        assert_eq!(foo.errors, 0);
+        assert!(foo.average_latency().is_some());
+
+        let fail = metrics.method("fail").unwrap();
+        assert_eq!(fail.requests, 1);
+        assert_eq!(fail.error_rate(), 1.0);
+
+        assert_eq!(metrics.method("bar"), None);
+    }
+
+    #[tokio::test]
+    async fn tracks_open_subscriptions() {
+        let client = MetricsRpcClient::new(MockRpcClient::new());
+        let metrics = client.metrics();
+        let client = RpcClient::new(Arc::new(client));
+
+        let mut sub = client
+            .subscribe::<u32>("sub", rpc_params![], "unsub")
+            .await
+            .unwrap();
+        assert_eq!(metrics.method("sub").unwrap().open_subscriptions, 1);
+
+        assert_eq!(sub.next().await.unwrap().unwrap(), 1);
+        assert_eq!(metrics.method("sub").unwrap().subscription_items, 1);
+
+        drop(sub);
+        let sub_metrics = metrics.method("sub").unwrap();
+        assert_eq!(sub_metrics.open_subscriptions, 0);
+        assert_eq!(sub_metrics.requests, 1);
+    }
+
+    #[tokio::test]
+    async fn subscription_errors_are_counted_separately() {
+        let client = MetricsRpcClient::new(MockRpcClient::new().on_subscribe(|_sub| {
+            Ok(subscription([
+                Err(RpcError::ClientError("bad item".into())),
+                Err(RpcError::ClientError("bad item".into())),
+                Ok(to_raw(1)),
+            ]))
+        }));
+        let metrics = client.metrics();
+        let client = RpcClient::new(Arc::new(client));
+
+        let sub = client
+            .subscribe::<u32>("sub", rpc_params![], "unsub")
+            .await
+            .unwrap();
+        let items: Vec<_> = sub.collect().await;
+        assert_eq!(items.len(), 3);
+
+        // More items failed than subscriptions were made, but none of the subscriptions
+        // themselves failed.
+        let sub_metrics = metrics.method("sub").unwrap();
+        assert_eq!(sub_metrics.subscription_items, 3);
+        assert_eq!(sub_metrics.subscription_errors, 2);
+        assert_eq!(sub_metrics.errors, 0);
+        assert_eq!(sub_metrics.error_rate(), 0.0);
+    }
+}
@@ -0,0 +1,98 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! A mock [`RpcClientT`] implementation which the tests of the other RPC clients share.
+
+use super::{RawValue, RpcClientT, RpcFuture, RpcSubscription};
+use crate::error::RpcError;
+use futures::StreamExt;
+
+type RequestFn =
+    dyn Fn(&str, Option<Box<RawValue>>) -> Result<Box<RawValue>, RpcError> + Send + Sync;
+type SubscribeFn = dyn Fn(&str) -> Result<RpcSubscription, RpcError> + Send + Sync;
+
+/// A client which, by default, responds to every request with the method name (or an
+/// error if the method is `"fail"`), and to every subscription with the numbers 1 and 2.
+/// Either of these can be replaced. Batches are made using the sequential default.
+pub(crate) struct MockRpcClient {
+    on_request: Box<RequestFn>,
+    on_subscribe: Box<SubscribeFn>,
+}
+
+impl Default for MockRpcClient {
+    fn default() -> Self {
+        MockRpcClient {
+            on_request: Box::new(|method: &str, _params| {
+                if method == "fail" {
+                    return Err(RpcError::ClientError("request failed".into()));
+                }
+                Ok(to_raw(method))
+            }),
+            on_subscribe: Box::new(|_sub: &str| Ok(subscription([1, 2].map(|n| Ok(to_raw(n)))))),
+        }
+    }
+}
+
+impl MockRpcClient {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle requests with the given function.
+    pub fn on_request(
+        mut self,
+        f: impl Fn(&str, Option<Box<RawValue>>) -> Result<Box<RawValue>, RpcError>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.on_request = Box::new(f);
+        self
+    }
+
+    /// Handle subscriptions with the given function.
+    pub fn on_subscribe(
+        mut self,
+        f: impl Fn(&str) -> Result<RpcSubscription, RpcError> + Send + Sync + 'static,
+    ) -> Self {
+        self.on_subscribe = Box::new(f);
+        self
+    }
+}
+
+impl RpcClientT for MockRpcClient {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move { (self.on_request)(method, params) })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        _params: Option<Box<RawValue>>,
+        _unsub: &'a str,
+    ) -> RpcFuture<'a, RpcSubscription> {
+        Box::pin(async move { (self.on_subscribe)(sub) })
+    }
+}
+
+/// Serialize a value to a raw JSON value.
+pub(crate) fn to_raw(value: impl serde::Serialize) -> Box<RawValue> {
+    serde_json::value::to_raw_value(&value).unwrap()
+}
+
+/// A subscription which hands back the given items and then ends.
+pub(crate) fn subscription<I>(items: I) -> RpcSubscription
+where
+    I: IntoIterator<Item = Result<Box<RawValue>, RpcError>>,
+    I::IntoIter: Send + 'static,
+{
+    RpcSubscription {
+        stream: futures::stream::iter(items).boxed(),
+        id: Some("sub".to_owned()),
+    }
+}
@@ -47,6 +47,28 @@
 #[cfg(feature = "jsonrpsee")]
 mod jsonrpsee_impl;
 
+#[cfg(feature = "failover-rpc-client")]
+mod failover_rpc_client;
+
+#[cfg(feature = "jsonrpsee-http")]
+mod http_rpc_client;
+
+#[cfg(feature = "limited-rpc-client")]
+mod limited_rpc_client;
+
+#[cfg(feature = "rpc-metrics")]
+mod metrics_rpc_client;
+
+#[cfg(feature = "reconnecting-rpc-client")]
+mod reconnecting_rpc_client;
+
+#[cfg(all(feature = "unix-socket-rpc-client", unix))]
+mod unix_socket_rpc_client;
+
+#[cfg(test)]
+pub(crate) mod mock_rpc_client;
+
+mod record_replay_rpc_client;
 mod rpc;
 mod rpc_client;
 mod rpc_client_t;
@@ -62,3 +84,23 @@ pub use rpc_client_t::{
 };
 
 pub use rpc_client::{rpc_params, RpcClient, RpcParams, Subscription};
+
+pub use record_replay_rpc_client::{RecordingRpcClient, ReplayError, ReplayRpcClient};
+
+#[cfg(feature = "failover-rpc-client")]
+pub use failover_rpc_client::{FailoverRpcClient, FailoverRpcClientBuilder, LoadBalancing};
+
+#[cfg(feature = "jsonrpsee-http")]
+pub use http_rpc_client::HttpRpcClient;
+
+#[cfg(feature = "limited-rpc-client")]
+pub use limited_rpc_client::{LimitedRpcClient, LimitedRpcClientBuilder};
+
+#[cfg(feature = "rpc-metrics")]
+pub use metrics_rpc_client::{MethodMetrics, MetricsRpcClient, RpcMetrics};
+
+#[cfg(feature = "reconnecting-rpc-client")]
+pub use reconnecting_rpc_client::{ReconnectingRpcClient, ReconnectingRpcClientBuilder};
+
+#[cfg(all(feature = "unix-socket-rpc-client", unix))]
+pub use unix_socket_rpc_client::UnixSocketRpcClient;
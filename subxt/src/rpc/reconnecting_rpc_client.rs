@@ -0,0 +1,662 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! An [`RpcClientT`] implementation which transparently reconnects to a node
+//! when the underlying websocket connection is lost.
+
+use super::{RpcClientT, RpcFuture, RpcSubscription, RpcSubscriptionStream};
+use crate::{client::jsonrpsee_helpers, error::RpcError};
+use futures::{future::BoxFuture, lock::Mutex as AsyncMutex, FutureExt, StreamExt};
+use jsonrpsee::core::{client::Client, Error as JsonRpseeError};
+use serde_json::value::RawValue;
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+/// A builder to configure and construct a [`ReconnectingRpcClient`].
+#[derive(Debug, Clone)]
+pub struct ReconnectingRpcClientBuilder {
+    initial_delay: Duration,
+    max_delay: Duration,
+    max_attempts: Option<usize>,
+}
+
+impl Default for ReconnectingRpcClientBuilder {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(10),
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectingRpcClientBuilder {
+    /// Create a new builder with the default retry settings; an exponential backoff
+    /// starting at 100ms and capped at 10s between attempts, which retries forever.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The delay to wait before the first reconnection attempt. This doubles on each
+    /// subsequent failed attempt, up to [`ReconnectingRpcClientBuilder::max_delay()`].
+    pub fn initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    /// The maximum delay to wait between reconnection attempts.
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// The maximum number of reconnection attempts to make before giving up and
+    /// returning an error. By default, we will try to reconnect forever.
+    pub fn max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = Some(attempts);
+        self
+    }
+
+    /// Connect to the node at the given URL. The initial connection is attempted
+    /// only once, and an error is returned if it cannot be established.
+    pub async fn build(self, url: impl Into<String>) -> Result<ReconnectingRpcClient, RpcError> {
+        let url = url.into();
+        let connect_url = url.clone();
+        let connect = move || {
+            let url = connect_url.clone();
+            async move {
+                let client = jsonrpsee_helpers::client(&url)
+                    .await
+                    .map_err(|e| RpcError::ClientError(Box::new(e)))?;
+                Ok(Arc::new(client) as Arc<dyn Connection>)
+            }
+            .boxed()
+        };
+        self.build_with(url, Box::new(connect)).await
+    }
+
+    // Build a client which uses the given function to (re)connect to the node.
+    async fn build_with(
+        self,
+        url: String,
+        connect: Box<Connect>,
+    ) -> Result<ReconnectingRpcClient, RpcError> {
+        let client = connect().await?;
+        Ok(ReconnectingRpcClient {
+            inner: Arc::new(Inner {
+                url,
+                retry: self,
+                connect,
+                client: RwLock::new(client),
+                reconnect_lock: AsyncMutex::new(()),
+            }),
+        })
+    }
+
+    // An iterator over the delays to wait between reconnection attempts.
+    fn delays(&self) -> impl Iterator<Item = Duration> {
+        let max_delay = self.max_delay;
+        let max_attempts = self.max_attempts.unwrap_or(usize::MAX);
+        std::iter::successors(Some(self.initial_delay), move |d| {
+            Some(d.saturating_mul(2).min(max_delay))
+        })
+        .take(max_attempts)
+    }
+}
+
+/// An [`RpcClientT`] implementation, based on `jsonrpsee`, which redials the node
+/// (with an exponential backoff) whenever the connection to it is lost.
+///
+/// - Requests which fail because the connection was lost are retried once a new
+///   connection has been established.
+/// - Subscriptions are re-established on the new connection using the same method
+///   and parameters. In order to signal that some items may have been missed while
+///   disconnected, the subscription stream will first emit an
+///   [`RpcError::DisconnectedWillReconnect`] error, and then carry on producing items
+///   from the new subscription.
+/// - Transactions are never submitted again on the new connection, since the node may
+///   have received them before the connection was lost. An `author_submitExtrinsic`
+///   request hands back the error instead, and an `author_submitAndWatchExtrinsic`
+///   subscription emits [`RpcError::DisconnectedWillReconnect`] followed by
+///   [`RpcError::SubscriptionDropped`], and then ends.
+///
+/// Higher level APIs like [`crate::blocks::BlocksClient::subscribe_finalized()`] and
+/// [`crate::client::RuntimeUpdaterStream`] skip over this marker, since they are able
+/// to fill in any gap themselves.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use std::{sync::Arc, time::Duration};
+/// use subxt::{rpc::ReconnectingRpcClient, OnlineClient, PolkadotConfig};
+///
+/// let rpc_client = ReconnectingRpcClient::builder()
+///     .max_delay(Duration::from_secs(30))
+///     .build("ws://127.0.0.1:9944")
+///     .await
+///     .unwrap();
+///
+/// let api = OnlineClient::<PolkadotConfig>::from_rpc_client(Arc::new(rpc_client))
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ReconnectingRpcClient {
+    inner: Arc<Inner>,
+}
+
+impl std::fmt::Debug for ReconnectingRpcClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReconnectingRpcClient")
+            .field("url", &self.inner.url)
+            .field("retry", &self.inner.retry)
+            .finish()
+    }
+}
+
+impl ReconnectingRpcClient {
+    /// Configure and build a new [`ReconnectingRpcClient`].
+    pub fn builder() -> ReconnectingRpcClientBuilder {
+        ReconnectingRpcClientBuilder::new()
+    }
+
+    /// Connect to the node at the given URL using the default retry settings.
+    pub async fn new(url: impl Into<String>) -> Result<Self, RpcError> {
+        Self::builder().build(url).await
+    }
+}
+
+// A connection to the node. This is a jsonrpsee `Client`, other than in tests.
+trait Connection: RpcClientT {
+    fn is_connected(&self) -> bool;
+}
+
+impl Connection for Client {
+    fn is_connected(&self) -> bool {
+        Client::is_connected(self)
+    }
+}
+
+type Connect = dyn Fn() -> BoxFuture<'static, Result<Arc<dyn Connection>, RpcError>> + Send + Sync;
+
+struct Inner {
+    url: String,
+    retry: ReconnectingRpcClientBuilder,
+    connect: Box<Connect>,
+    client: RwLock<Arc<dyn Connection>>,
+    // Held while reconnecting so that only one reconnection happens at a time.
+    reconnect_lock: AsyncMutex<()>,
+}
+
+impl Inner {
+    fn current(&self) -> Arc<dyn Connection> {
+        self.client.read().expect("shouldn't be poisoned").clone()
+    }
+
+    /// Replace the given (disconnected) client with a new one. If some other task
+    /// has already replaced it, then the current client is returned instead.
+    async fn reconnect(
+        &self,
+        stale: &Arc<dyn Connection>,
+    ) -> Result<Arc<dyn Connection>, RpcError> {
+        let _guard = self.reconnect_lock.lock().await;
+
+        // Compare the data pointers only; vtable pointers aren't guaranteed to be unique.
+        let current = self.current();
+        if Arc::as_ptr(&current) as *const () != Arc::as_ptr(stale) as *const () {
+            return Ok(current);
+        }
+
+        let mut delays = self.retry.delays();
+        loop {
+            match (self.connect)().await {
+                Ok(client) => {
+                    tracing::debug!("Reconnected to {}", self.url);
+                    *self.client.write().expect("shouldn't be poisoned") = client.clone();
+                    return Ok(client);
+                }
+                Err(e) => match delays.next() {
+                    Some(delay) => {
+                        tracing::debug!(
+                            "Failed to reconnect to {} ({e}); retrying in {delay:?}",
+                            self.url
+                        );
+                        tokio::time::sleep(delay).await;
+                    }
+                    None => return Err(e),
+                },
+            }
+        }
+    }
+
+    async fn request(
+        &self,
+        method: &str,
+        params: Option<Box<RawValue>>,
+    ) -> Result<Box<RawValue>, RpcError> {
+        loop {
+            let client = self.current();
+            match client.request_raw(method, params.clone()).await {
+                Err(e) if is_disconnect(&client, &e) && is_idempotent(method) => {
+                    tracing::debug!("Connection lost during `{method}` request; reconnecting");
+                    self.reconnect(&client).await?;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    async fn batch_request(
+        &self,
+        batch: Vec<(&str, Option<Box<RawValue>>)>,
+    ) -> Result<Vec<Result<Box<RawValue>, RpcError>>, RpcError> {
+        loop {
+            let client = self.current();
+            match client.batch_request_raw(batch.clone()).await {
+                Err(e) if is_disconnect(&client, &e) => {
+                    tracing::debug!("Connection lost during batch request; reconnecting");
+                    self.reconnect(&client).await?;
+                }
+                res => return res,
+            }
+        }
+    }
+
+    async fn subscribe(
+        &self,
+        sub: &str,
+        params: Option<Box<RawValue>>,
+        unsub: &str,
+    ) -> Result<(Arc<dyn Connection>, RpcSubscription), RpcError> {
+        loop {
+            let client = self.current();
+            match client.subscribe_raw(sub, params.clone(), unsub).await {
+                Ok(subscription) => return Ok((client, subscription)),
+                Err(e) if is_disconnect(&client, &e) => {
+                    tracing::debug!("Connection lost during `{sub}` subscription; reconnecting");
+                    self.reconnect(&client).await?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+impl RpcClientT for ReconnectingRpcClient {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RpcFuture<'a, Box<RawValue>> {
+        Box::pin(self.inner.request(method, params))
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RpcFuture<'a, RpcSubscription> {
+        Box::pin(async move {
+            let (client, subscription) = self.inner.subscribe(sub, params.clone(), unsub).await?;
+
+            let state = ResubscribeState {
+                inner: self.inner.clone(),
+                client,
+                stream: Some(subscription.stream),
+                sub: sub.to_owned(),
+                params,
+                unsub: unsub.to_owned(),
+                done: false,
+            };
+
+            let stream: RpcSubscriptionStream =
+                futures::stream::unfold(state, |mut state| async move {
+                    let item = state.next().await?;
+                    Some((item, state))
+                })
+                .boxed();
+
+            Ok(RpcSubscription {
+                stream,
+                id: subscription.id,
+            })
+        })
+    }
+
+    fn batch_request_raw<'a>(
+        &'a self,
+        batch: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        Box::pin(self.inner.batch_request(batch))
+    }
+}
+
+// The state needed to re-establish a subscription on a new connection.
+struct ResubscribeState {
+    inner: Arc<Inner>,
+    client: Arc<dyn Connection>,
+    // This is `None` if we need to resubscribe before we can hand back more items.
+    stream: Option<RpcSubscriptionStream>,
+    sub: String,
+    params: Option<Box<RawValue>>,
+    unsub: String,
+    // Set once we hit an error that we can't recover from.
+    done: bool,
+}
+
+impl ResubscribeState {
+    async fn next(&mut self) -> Option<Result<Box<RawValue>, RpcError>> {
+        if self.done {
+            return None;
+        }
+
+        if self.stream.is_none() {
+            if !is_idempotent(&self.sub) {
+                self.done = true;
+                return Some(Err(RpcError::SubscriptionDropped));
+            }
+            let res = self
+                .inner
+                .subscribe(&self.sub, self.params.clone(), &self.unsub)
+                .await;
+            match res {
+                Ok((client, subscription)) => {
+                    self.client = client;
+                    self.stream = Some(subscription.stream);
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            }
+        }
+        let stream = self.stream.as_mut().expect("stream was set above; qed");
+
+        match stream.next().await {
+            Some(Err(e)) if is_disconnect(&self.client, &e) => {
+                self.stream = None;
+                Some(Err(self.disconnected(e.to_string())))
+            }
+            // The subscription ended while the connection is still up, so the
+            // server has closed it and there's nothing more to receive.
+            None if self.client.is_connected() => None,
+            None => {
+                self.stream = None;
+                Some(Err(self.disconnected("connection closed".to_owned())))
+            }
+            item => item,
+        }
+    }
+
+    fn disconnected(&self, reason: String) -> RpcError {
+        let outcome = if is_idempotent(&self.sub) {
+            "it will be re-established"
+        } else {
+            "it won't be re-established, since that would submit the transaction again"
+        };
+        tracing::debug!(
+            "Subscription `{}` interrupted ({reason}); {outcome}",
+            self.sub
+        );
+        RpcError::DisconnectedWillReconnect(format!(
+            "subscription `{}` interrupted ({reason}); {outcome}",
+            self.sub
+        ))
+    }
+}
+
+// Was this error caused by the connection to the node being lost?
+fn is_disconnect(client: &dyn Connection, err: &RpcError) -> bool {
+    if !client.is_connected() {
+        return true;
+    }
+    match err {
+        RpcError::SubscriptionDropped => true,
+        RpcError::ClientError(e) => matches!(
+            e.downcast_ref::<JsonRpseeError>(),
+            Some(JsonRpseeError::RestartNeeded(_) | JsonRpseeError::Transport(_))
+        ),
+        _ => false,
+    }
+}
+
+// Can this request or subscription be made again on a new connection? Transactions
+// aren't, since the node may have received them before the connection was lost.
+fn is_idempotent(method: &str) -> bool {
+    !matches!(
+        method,
+        "author_submitExtrinsic" | "author_submitAndWatchExtrinsic"
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::mock_rpc_client::{to_raw, MockRpcClient};
+    use futures::channel::mpsc;
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex,
+    };
+
+    struct MockConnection {
+        client: MockRpcClient,
+        connected: Arc<AtomicBool>,
+    }
+
+    impl Connection for MockConnection {
+        fn is_connected(&self) -> bool {
+            self.connected.load(Ordering::SeqCst)
+        }
+    }
+
+    impl RpcClientT for MockConnection {
+        fn request_raw<'a>(
+            &'a self,
+            method: &'a str,
+            params: Option<Box<RawValue>>,
+        ) -> RpcFuture<'a, Box<RawValue>> {
+            self.client.request_raw(method, params)
+        }
+
+        fn subscribe_raw<'a>(
+            &'a self,
+            sub: &'a str,
+            params: Option<Box<RawValue>>,
+            unsub: &'a str,
+        ) -> RpcFuture<'a, RpcSubscription> {
+            self.client.subscribe_raw(sub, params, unsub)
+        }
+    }
+
+    // A node which we can drop the connection to. Requests are answered with the number of
+    // the connection that they were made on, and subscriptions start by handing back that
+    // number too, so that we can tell which connection was used.
+    #[derive(Clone, Default)]
+    struct MockNode(Arc<Mutex<MockNodeState>>);
+
+    #[derive(Default)]
+    struct MockNodeState {
+        connections: usize,
+        connected: Arc<AtomicBool>,
+        subscriptions: Vec<mpsc::UnboundedSender<Result<Box<RawValue>, RpcError>>>,
+        refuse_connections: bool,
+    }
+
+    impl MockNode {
+        fn connect(&self) -> Result<Arc<dyn Connection>, RpcError> {
+            let mut state = self.0.lock().unwrap();
+            if state.refuse_connections {
+                return Err(RpcError::ClientError("connection refused".into()));
+            }
+            state.connections += 1;
+            state.connected = Arc::new(AtomicBool::new(true));
+
+            let n = state.connections;
+            let connected = state.connected.clone();
+            let request_connected = connected.clone();
+            let subscribe_connected = connected.clone();
+            let node = self.clone();
+            let client = MockRpcClient::new()
+                .on_request(move |_method, _params| {
+                    if !request_connected.load(Ordering::SeqCst) {
+                        return Err(RpcError::ClientError("connection closed".into()));
+                    }
+                    Ok(to_raw(n))
+                })
+                .on_subscribe(move |_sub| {
+                    if !subscribe_connected.load(Ordering::SeqCst) {
+                        return Err(RpcError::ClientError("connection closed".into()));
+                    }
+                    let (tx, rx) = mpsc::unbounded();
+                    tx.unbounded_send(Ok(to_raw(n))).unwrap();
+                    node.0.lock().unwrap().subscriptions.push(tx);
+                    Ok(RpcSubscription {
+                        stream: rx.boxed(),
+                        id: Some(format!("sub{n}")),
+                    })
+                });
+            Ok(Arc::new(MockConnection { client, connected }))
+        }
+
+        // Drop the current connection, ending any subscriptions made on it.
+        fn drop_connection(&self) {
+            let mut state = self.0.lock().unwrap();
+            state.connected.store(false, Ordering::SeqCst);
+            state.subscriptions.clear();
+        }
+
+        fn refuse_connections(&self) {
+            self.0.lock().unwrap().refuse_connections = true;
+        }
+
+        fn connections(&self) -> usize {
+            self.0.lock().unwrap().connections
+        }
+    }
+
+    async fn client(node: &MockNode, retry: ReconnectingRpcClientBuilder) -> ReconnectingRpcClient {
+        let node = node.clone();
+        let connect = move || {
+            let res = node.connect();
+            async move { res }.boxed()
+        };
+        retry
+            .initial_delay(Duration::from_millis(1))
+            .build_with("mock".to_owned(), Box::new(connect))
+            .await
+            .unwrap()
+    }
+
+    fn number(value: Box<RawValue>) -> usize {
+        serde_json::from_str(value.get()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn requests_are_retried_on_a_new_connection() {
+        let node = MockNode::default();
+        let client = client(&node, ReconnectingRpcClientBuilder::new()).await;
+
+        assert_eq!(number(client.request_raw("foo", None).await.unwrap()), 1);
+        node.drop_connection();
+        assert_eq!(number(client.request_raw("foo", None).await.unwrap()), 2);
+        assert_eq!(node.connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn subscriptions_are_reestablished_after_a_disconnect() {
+        let node = MockNode::default();
+        let client = client(&node, ReconnectingRpcClientBuilder::new()).await;
+        let mut sub = client
+            .subscribe_raw("sub", None, "unsub")
+            .await
+            .unwrap()
+            .stream;
+
+        assert_eq!(number(sub.next().await.unwrap().unwrap()), 1);
+
+        // Dropping the connection ends the subscription. We're told that items may
+        // have been missed, and then get items from a subscription on a new connection.
+        node.drop_connection();
+        assert!(matches!(
+            sub.next().await,
+            Some(Err(RpcError::DisconnectedWillReconnect(_)))
+        ));
+        assert_eq!(number(sub.next().await.unwrap().unwrap()), 2);
+        assert_eq!(node.connections(), 2);
+    }
+
+    #[tokio::test]
+    async fn subscriptions_end_if_reconnecting_fails() {
+        let node = MockNode::default();
+        let client = client(&node, ReconnectingRpcClientBuilder::new().max_attempts(2)).await;
+        let mut sub = client
+            .subscribe_raw("sub", None, "unsub")
+            .await
+            .unwrap()
+            .stream;
+        assert_eq!(number(sub.next().await.unwrap().unwrap()), 1);
+
+        node.refuse_connections();
+        node.drop_connection();
+        assert!(matches!(
+            sub.next().await,
+            Some(Err(RpcError::DisconnectedWillReconnect(_)))
+        ));
+        assert!(matches!(
+            sub.next().await,
+            Some(Err(RpcError::ClientError(_)))
+        ));
+        assert!(sub.next().await.is_none());
+        assert!(client.request_raw("foo", None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn transactions_are_not_submitted_again() {
+        let node = MockNode::default();
+        let client = client(&node, ReconnectingRpcClientBuilder::new()).await;
+        let mut sub = client
+            .subscribe_raw(
+                "author_submitAndWatchExtrinsic",
+                None,
+                "author_unwatchExtrinsic",
+            )
+            .await
+            .unwrap()
+            .stream;
+        assert_eq!(number(sub.next().await.unwrap().unwrap()), 1);
+
+        // The watch subscription ends rather than being re-established, which would
+        // submit the transaction again.
+        node.drop_connection();
+        assert!(matches!(
+            sub.next().await,
+            Some(Err(RpcError::DisconnectedWillReconnect(_)))
+        ));
+        assert!(matches!(
+            sub.next().await,
+            Some(Err(RpcError::SubscriptionDropped))
+        ));
+        assert!(sub.next().await.is_none());
+        assert_eq!(node.connections(), 1);
+
+        // Nor are submissions retried, though other requests still reconnect.
+        let err = client
+            .request_raw("author_submitExtrinsic", None)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, RpcError::ClientError(_)),
+            "unexpected error: {err}"
+        );
+        assert_eq!(node.connections(), 1);
+        assert_eq!(number(client.request_raw("foo", None).await.unwrap()), 2);
+    }
+}
@@ -0,0 +1,618 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! [`RpcClientT`] implementations which record the traffic between subxt and a node,
+//! and replay such a recording later on without a node at all.
+//!
+//! Recordings are stored as newline delimited JSON, with one entry per line. Each
+//! request, subscription, subscription item and the end of each subscription are
+//! recorded as separate entries as they happen.
+
+use super::{RpcClientT, RpcFuture, RpcSubscription, RpcSubscriptionId, RpcSubscriptionStream};
+use crate::error::{JsonRpcError, RpcError};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::value::RawValue;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// An error handed back from a [`ReplayRpcClient`]. This will be wrapped in
+/// [`RpcError::ClientError`], and can be downcast to if necessary.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum ReplayError {
+    /// The recorded request or subscription failed with this error message. Errors
+    /// handed back by the node are replayed as [`RpcError::Call`] instead.
+    #[error("{0}")]
+    Recorded(String),
+    /// Nothing was recorded for a request with the given method and params.
+    #[error("No recorded response for `{method}` with params {params:?}")]
+    NoRecordedRequest {
+        /// The method that was requested.
+        method: String,
+        /// The params that were given, if any.
+        params: Option<String>,
+    },
+    /// Nothing was recorded for a subscription with the given method and params.
+    #[error("No recorded subscription for `{method}` with params {params:?}")]
+    NoRecordedSubscription {
+        /// The subscription method.
+        method: String,
+        /// The params that were given, if any.
+        params: Option<String>,
+    },
+}
+
+// A single line in a recording.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum Entry {
+    Request {
+        method: String,
+        params: Option<Box<RawValue>>,
+        result: Result<Box<RawValue>, RecordedError>,
+    },
+    Subscribe {
+        sub: u64,
+        method: String,
+        params: Option<Box<RawValue>>,
+        unsub: String,
+        result: Result<Option<RpcSubscriptionId>, RecordedError>,
+    },
+    SubscriptionItem {
+        sub: u64,
+        result: Result<Box<RawValue>, RecordedError>,
+    },
+    SubscriptionEnd {
+        sub: u64,
+    },
+}
+
+// A recorded error. Errors handed back by the node are kept whole, so that they can be
+// told apart in the same way when replayed, and anything else is kept as its message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum RecordedError {
+    Call {
+        code: i32,
+        message: String,
+        data: Option<serde_json::Value>,
+    },
+    Other(String),
+}
+
+impl From<&RpcError> for RecordedError {
+    fn from(e: &RpcError) -> Self {
+        match e {
+            RpcError::Call(e) => RecordedError::Call {
+                code: e.code,
+                message: e.message.clone(),
+                data: e.data.clone(),
+            },
+            e => RecordedError::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<RecordedError> for RpcError {
+    fn from(e: RecordedError) -> Self {
+        match e {
+            RecordedError::Call {
+                code,
+                message,
+                data,
+            } => RpcError::Call(JsonRpcError {
+                code,
+                message,
+                data,
+            }),
+            RecordedError::Other(e) => replay_error(ReplayError::Recorded(e)),
+        }
+    }
+}
+
+/// An [`RpcClientT`] implementation which wraps some other [`RpcClientT`], and records
+/// every request and subscription made through it (along with the responses and
+/// subscription items handed back) so that they can be replayed later on using a
+/// [`ReplayRpcClient`].
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use std::sync::Arc;
+/// use subxt::{rpc::RecordingRpcClient, OnlineClient, PolkadotConfig};
+///
+/// let ws_client = subxt::client::default_rpc_client("ws://127.0.0.1:9944")
+///     .await
+///     .unwrap();
+/// let rpc_client = RecordingRpcClient::new(ws_client, "recording.jsonl").unwrap();
+///
+/// // Everything that this client does over RPC will be recorded:
+/// let api = OnlineClient::<PolkadotConfig>::from_rpc_client(Arc::new(rpc_client))
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub struct RecordingRpcClient<C> {
+    inner: C,
+    recorder: Arc<Recorder>,
+}
+
+impl<C> std::fmt::Debug for RecordingRpcClient<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordingRpcClient").finish()
+    }
+}
+
+impl<C: RpcClientT> RecordingRpcClient<C> {
+    /// Wrap the given client, recording all traffic to a file at the given path. The
+    /// file is created if it does not exist, and truncated if it does.
+    pub fn new(inner: C, path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        let file = File::create(path)?;
+        Ok(Self::from_writer(inner, BufWriter::new(file)))
+    }
+
+    /// Wrap the given client, recording all traffic to the given writer.
+    pub fn from_writer(inner: C, writer: impl Write + Send + 'static) -> Self {
+        Self {
+            inner,
+            recorder: Arc::new(Recorder {
+                writer: Mutex::new(Box::new(writer)),
+                next_sub: AtomicU64::new(0),
+            }),
+        }
+    }
+}
+
+impl<C: RpcClientT> RpcClientT for RecordingRpcClient<C> {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RpcFuture<'a, Box<RawValue>> {
+        Box::pin(async move {
+            let res = self.inner.request_raw(method, params.clone()).await;
+            self.recorder.record(&Entry::Request {
+                method: method.to_owned(),
+                params,
+                result: to_recorded(&res),
+            });
+            res
+        })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RpcFuture<'a, RpcSubscription> {
+        Box::pin(async move {
+            let res = self.inner.subscribe_raw(sub, params.clone(), unsub).await;
+            let sub_idx = self.recorder.next_sub.fetch_add(1, Ordering::Relaxed);
+            self.recorder.record(&Entry::Subscribe {
+                sub: sub_idx,
+                method: sub.to_owned(),
+                params,
+                unsub: unsub.to_owned(),
+                result: match &res {
+                    Ok(s) => Ok(s.id.clone()),
+                    Err(e) => Err(e.into()),
+                },
+            });
+            let subscription = res?;
+
+            let recorder = self.recorder.clone();
+            let stream: RpcSubscriptionStream =
+                futures::stream::unfold(Some(subscription.stream), move |stream| {
+                    let recorder = recorder.clone();
+                    async move {
+                        let mut stream = stream?;
+                        match stream.next().await {
+                            Some(item) => {
+                                recorder.record(&Entry::SubscriptionItem {
+                                    sub: sub_idx,
+                                    result: to_recorded(&item),
+                                });
+                                Some((item, Some(stream)))
+                            }
+                            None => {
+                                recorder.record(&Entry::SubscriptionEnd { sub: sub_idx });
+                                None
+                            }
+                        }
+                    }
+                })
+                .boxed();
+
+            Ok(RpcSubscription {
+                stream,
+                id: subscription.id,
+            })
+        })
+    }
+
+    fn batch_request_raw<'a>(
+        &'a self,
+        batch: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        Box::pin(async move {
+            let results = self.inner.batch_request_raw(batch.clone()).await?;
+            // Each request in the batch is recorded individually, so that they can
+            // be replayed regardless of how they are requested.
+            for ((method, params), res) in batch.into_iter().zip(&results) {
+                self.recorder.record(&Entry::Request {
+                    method: method.to_owned(),
+                    params,
+                    result: to_recorded(res),
+                });
+            }
+            Ok(results)
+        })
+    }
+}
+
+struct Recorder {
+    writer: Mutex<Box<dyn Write + Send>>,
+    next_sub: AtomicU64,
+}
+
+impl Recorder {
+    fn record(&self, entry: &Entry) {
+        let mut writer = self.writer.lock().expect("shouldn't be poisoned");
+        // We flush after every entry so that a recording is usable even if the
+        // process doesn't exit cleanly. A failure to record isn't worth failing the
+        // request over, so we just log it.
+        let res = serde_json::to_writer(&mut *writer, entry)
+            .map_err(std::io::Error::from)
+            .and_then(|_| writer.write_all(b"\n"))
+            .and_then(|_| writer.flush());
+        if let Err(e) = res {
+            tracing::warn!("Failed to record RPC traffic: {e}");
+        }
+    }
+}
+
+fn to_recorded(res: &Result<Box<RawValue>, RpcError>) -> Result<Box<RawValue>, RecordedError> {
+    match res {
+        Ok(val) => Ok(val.clone()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// An [`RpcClientT`] implementation which replays a recording made by a
+/// [`RecordingRpcClient`], without needing a node to talk to.
+///
+/// Requests are matched against the recording by their method and params. If the same
+/// request was recorded several times, the recorded responses are handed back in the
+/// order that they were recorded, and the last one is repeated once they run out.
+/// Subscriptions are matched in the same way, and end once every recorded item has been
+/// handed back.
+///
+/// Errors that the node handed back are replayed as the same [`RpcError::Call`]s. Any
+/// other errors, as well as requests and subscriptions that weren't recorded, are handed
+/// back as [`RpcError::ClientError`]s containing a [`ReplayError`].
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use std::sync::Arc;
+/// use subxt::{rpc::ReplayRpcClient, OnlineClient, PolkadotConfig};
+///
+/// let rpc_client = ReplayRpcClient::from_file("recording.jsonl").unwrap();
+/// let api = OnlineClient::<PolkadotConfig>::from_rpc_client(Arc::new(rpc_client))
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct ReplayRpcClient {
+    requests: Mutex<HashMap<Key, Recorded<Result<Box<RawValue>, RecordedError>>>>,
+    subscriptions: Mutex<HashMap<Key, Recorded<RecordedSubscription>>>,
+}
+
+// Recordings are looked up by their method and params.
+type Key = (String, Option<String>);
+
+// Some recorded values, and the index of the next one to hand back.
+#[derive(Debug)]
+struct Recorded<T> {
+    values: Vec<T>,
+    next: usize,
+}
+
+impl<T: Clone> Recorded<T> {
+    fn next(&mut self) -> Option<T> {
+        let value = self.values.get(self.next).or_else(|| self.values.last())?;
+        self.next += 1;
+        Some(value.clone())
+    }
+}
+
+impl<T> Default for Recorded<T> {
+    fn default() -> Self {
+        Self {
+            values: Vec::new(),
+            next: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RecordedSubscription {
+    result: Result<Option<RpcSubscriptionId>, RecordedError>,
+    items: Vec<Result<Box<RawValue>, RecordedError>>,
+}
+
+impl ReplayRpcClient {
+    /// Load a recording from the file at the given path.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, std::io::Error> {
+        let file = File::open(path)?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    /// Load a recording from the given reader.
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, std::io::Error> {
+        let mut requests: HashMap<Key, Recorded<_>> = HashMap::new();
+        let mut subscriptions: HashMap<Key, Recorded<RecordedSubscription>> = HashMap::new();
+        // Where each subscription lives in `subscriptions`, so that we can add items to it.
+        let mut sub_locations: HashMap<u64, (Key, usize)> = HashMap::new();
+
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str(&line)? {
+                Entry::Request {
+                    method,
+                    params,
+                    result,
+                } => {
+                    let key = (method, params.map(|p| p.get().to_owned()));
+                    requests.entry(key).or_default().values.push(result);
+                }
+                Entry::Subscribe {
+                    sub,
+                    method,
+                    params,
+                    result,
+                    ..
+                } => {
+                    let key = (method, params.map(|p| p.get().to_owned()));
+                    let recorded = subscriptions.entry(key.clone()).or_default();
+                    recorded.values.push(RecordedSubscription {
+                        result,
+                        items: Vec::new(),
+                    });
+                    sub_locations.insert(sub, (key, recorded.values.len() - 1));
+                }
+                Entry::SubscriptionItem { sub, result } => {
+                    let recorded_sub = sub_locations
+                        .get(&sub)
+                        .and_then(|(key, idx)| subscriptions.get_mut(key)?.values.get_mut(*idx));
+                    if let Some(recorded_sub) = recorded_sub {
+                        recorded_sub.items.push(result);
+                    }
+                }
+                Entry::SubscriptionEnd { .. } => {}
+            }
+        }
+
+        Ok(Self {
+            requests: Mutex::new(requests),
+            subscriptions: Mutex::new(subscriptions),
+        })
+    }
+}
+
+impl RpcClientT for ReplayRpcClient {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RpcFuture<'a, Box<RawValue>> {
+        let key = (method.to_owned(), params.map(|p| p.get().to_owned()));
+        let res = self
+            .requests
+            .lock()
+            .expect("shouldn't be poisoned")
+            .get_mut(&key)
+            .and_then(|r| r.next());
+
+        let res = match res {
+            Some(Ok(val)) => Ok(val),
+            Some(Err(e)) => Err(e.into()),
+            None => Err(replay_error(ReplayError::NoRecordedRequest {
+                method: key.0,
+                params: key.1,
+            })),
+        };
+        Box::pin(async move { res })
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        _unsub: &'a str,
+    ) -> RpcFuture<'a, RpcSubscription> {
+        let key = (sub.to_owned(), params.map(|p| p.get().to_owned()));
+        let recorded = self
+            .subscriptions
+            .lock()
+            .expect("shouldn't be poisoned")
+            .get_mut(&key)
+            .and_then(|r| r.next());
+
+        let res = match recorded {
+            Some(RecordedSubscription {
+                result: Ok(id),
+                items,
+            }) => {
+                let items = items.into_iter().map(|item| item.map_err(RpcError::from));
+                let stream = futures::stream::iter(items).boxed();
+                Ok(RpcSubscription { stream, id })
+            }
+            Some(RecordedSubscription { result: Err(e), .. }) => Err(e.into()),
+            None => Err(replay_error(ReplayError::NoRecordedSubscription {
+                method: key.0,
+                params: key.1,
+            })),
+        };
+        Box::pin(async move { res })
+    }
+}
+
+fn replay_error(e: ReplayError) -> RpcError {
+    RpcError::ClientError(Box::new(e))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        error::Error,
+        rpc::{
+            mock_rpc_client::{subscription, to_raw, MockRpcClient},
+            rpc_params, RpcClient,
+        },
+    };
+
+    // A writer which we can read the recording back from.
+    #[derive(Clone, Default)]
+    struct SharedBuf(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuf {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn replays_what_was_recorded() {
+        let buf = SharedBuf::default();
+        let recording = RpcClient::new(Arc::new(RecordingRpcClient::from_writer(
+            MockRpcClient::new(),
+            buf.clone(),
+        )));
+
+        let res: String = recording.request("foo", rpc_params![1]).await.unwrap();
+        assert_eq!(res, "foo");
+        assert!(recording
+            .request::<String>("fail", rpc_params![])
+            .await
+            .is_err());
+        let sub = recording
+            .subscribe::<u32>("sub", rpc_params![], "unsub")
+            .await
+            .unwrap();
+        let items: Vec<u32> = sub.map(|i| i.unwrap()).collect().await;
+        assert_eq!(items, vec![1, 2]);
+
+        let recorded = buf.0.lock().unwrap().clone();
+        let replay = RpcClient::new(Arc::new(
+            ReplayRpcClient::from_reader(&recorded[..]).unwrap(),
+        ));
+
+        // Requests are matched on their method and params:
+        let res: String = replay.request("foo", rpc_params![1]).await.unwrap();
+        assert_eq!(res, "foo");
+        assert!(replay
+            .request::<String>("foo", rpc_params![2])
+            .await
+            .is_err());
+        // The last response is repeated once they run out:
+        let res: String = replay.request("foo", rpc_params![1]).await.unwrap();
+        assert_eq!(res, "foo");
+        // Errors are replayed too:
+        let err = replay
+            .request::<String>("fail", rpc_params![])
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("request failed"), "unexpected error: {err}");
+
+        let sub = replay
+            .subscribe::<u32>("sub", rpc_params![], "unsub")
+            .await
+            .unwrap();
+        assert_eq!(sub.subscription_id().map(|s| s.as_str()), Some("sub"));
+        let items: Vec<u32> = sub.map(|i| i.unwrap()).collect().await;
+        assert_eq!(items, vec![1, 2]);
+    }
+
+    #[tokio::test]
+    async fn replays_node_errors_as_they_were() {
+        let outdated = JsonRpcError {
+            code: 1010,
+            message: "Invalid Transaction".to_owned(),
+            data: Some("Transaction is outdated".into()),
+        };
+        let not_found = JsonRpcError {
+            code: JsonRpcError::METHOD_NOT_FOUND,
+            message: "Method not found".to_owned(),
+            data: None,
+        };
+        let (e1, e2, e3) = (outdated.clone(), not_found.clone(), outdated.clone());
+        let mock = MockRpcClient::new()
+            .on_request(move |method, _params| match method {
+                "submit" => Err(RpcError::Call(e1.clone())),
+                _ => Err(RpcError::Call(e2.clone())),
+            })
+            .on_subscribe(move |_sub| {
+                Ok(subscription([
+                    Ok(to_raw(1)),
+                    Err(RpcError::Call(e3.clone())),
+                ]))
+            });
+
+        let buf = SharedBuf::default();
+        let recording = RecordingRpcClient::from_writer(mock, buf.clone());
+        recording.request_raw("submit", None).await.unwrap_err();
+        recording.request_raw("missing", None).await.unwrap_err();
+        let sub = recording.subscribe_raw("sub", None, "unsub").await.unwrap();
+        let _: Vec<_> = sub.stream.collect().await;
+
+        let recorded = buf.0.lock().unwrap().clone();
+        let replay = ReplayRpcClient::from_reader(&recorded[..]).unwrap();
+
+        assert!(matches!(
+            replay.request_raw("submit", None).await,
+            Err(RpcError::Call(e)) if e == outdated
+        ));
+        let items: Vec<_> = replay
+            .subscribe_raw("sub", None, "unsub")
+            .await
+            .unwrap()
+            .stream
+            .collect()
+            .await;
+        assert_eq!(items[0].as_ref().unwrap().get(), "1");
+        assert!(matches!(&items[1], Err(RpcError::Call(e)) if *e == outdated));
+
+        // And so they're interpreted in the same way as the originals were.
+        let replay = RpcClient::new(Arc::new(replay));
+        assert!(matches!(
+            replay.request::<String>("missing", rpc_params![]).await,
+            Err(Error::Rpc(RpcError::MethodNotSupported(m))) if m == "missing"
+        ));
+    }
+}
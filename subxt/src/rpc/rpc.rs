@@ -31,11 +31,17 @@
 //! # }
 //! ```
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use codec::{Decode, Encode};
+use serde::Serialize;
 
-use crate::{error::Error, utils::PhantomDataSendSync, Config, Metadata};
+use crate::{
+    error::{Error, RpcError, TransactionError},
+    metadata::DecodeWithMetadata,
+    utils::PhantomDataSendSync,
+    Config, Metadata,
+};
 
 use super::{
     rpc_params,
@@ -46,13 +52,25 @@ use super::{
 /// Client for substrate rpc interfaces
 pub struct Rpc<T: Config> {
     client: RpcClient,
+    methods: Arc<Mutex<NodeMethods>>,
     _marker: PhantomDataSendSync<T>,
 }
 
+// What we've found out about the RPC methods that the node exposes.
+#[derive(Clone, Default)]
+enum NodeMethods {
+    #[default]
+    NotFetched,
+    Known(Arc<types::RpcMethods>),
+    // The node refused to tell us.
+    Unknown,
+}
+
 impl<T: Config> Clone for Rpc<T> {
     fn clone(&self) -> Self {
         Self {
             client: self.client.clone(),
+            methods: self.methods.clone(),
             _marker: PhantomDataSendSync::new(),
         }
     }
@@ -72,6 +90,7 @@ impl<T: Config> Rpc<T> {
     pub fn new<R: RpcClientT>(client: Arc<R>) -> Self {
         Self {
             client: RpcClient::new(client),
+            methods: Default::default(),
             _marker: PhantomDataSendSync::new(),
         }
     }
@@ -132,6 +151,92 @@ impl<T: Config> Rpc<T> {
             .map_err(Into::into)
     }
 
+    /// Fetch the raw bytes for a given key in a child trie. The child trie is identified
+    /// by its prefixed storage key (see [`crate::storage::ChildInfo::prefixed_storage_key`]).
+    pub async fn child_storage(
+        &self,
+        child_storage_key: &[u8],
+        key: &[u8],
+        hash: Option<T::Hash>,
+    ) -> Result<Option<types::StorageData>, Error> {
+        let params = rpc_params![to_hex(child_storage_key), to_hex(key), hash];
+        let data = self.client.request("childstate_getStorage", params).await?;
+        Ok(data)
+    }
+
+    /// Fetch the raw bytes for each of the given keys in a child trie.
+    pub async fn child_storage_entries(
+        &self,
+        child_storage_key: &[u8],
+        keys: impl IntoIterator<Item = &[u8]>,
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<Option<types::StorageData>>, Error> {
+        let keys: Vec<String> = keys.into_iter().map(to_hex).collect();
+        let params = rpc_params![to_hex(child_storage_key), keys, hash];
+        let data = self
+            .client
+            .request("childstate_getStorageEntries", params)
+            .await?;
+        Ok(data)
+    }
+
+    /// Returns the keys with prefix in a child trie with pagination support.
+    /// Up to `count` keys will be returned.
+    /// If `start_key` is passed, return next keys in storage in lexicographic order.
+    pub async fn child_storage_keys_paged(
+        &self,
+        child_storage_key: &[u8],
+        prefix: &[u8],
+        count: u32,
+        start_key: Option<&[u8]>,
+        hash: Option<T::Hash>,
+    ) -> Result<Vec<types::StorageKey>, Error> {
+        let start_key = start_key.map(to_hex);
+        let params = rpc_params![
+            to_hex(child_storage_key),
+            to_hex(prefix),
+            count,
+            start_key,
+            hash
+        ];
+        let data = self
+            .client
+            .request("childstate_getKeysPaged", params)
+            .await?;
+        Ok(data)
+    }
+
+    /// Fetch the hash of the value at a given key in a child trie.
+    pub async fn child_storage_hash(
+        &self,
+        child_storage_key: &[u8],
+        key: &[u8],
+        hash: Option<T::Hash>,
+    ) -> Result<Option<T::Hash>, Error> {
+        let params = rpc_params![to_hex(child_storage_key), to_hex(key), hash];
+        let data = self
+            .client
+            .request("childstate_getStorageHash", params)
+            .await?;
+        Ok(data)
+    }
+
+    /// Get proof of child trie storage entries at a specific block's state.
+    pub async fn child_read_proof(
+        &self,
+        child_storage_key: &[u8],
+        keys: impl IntoIterator<Item = &[u8]>,
+        hash: Option<T::Hash>,
+    ) -> Result<types::ReadProof<T::Hash>, Error> {
+        let keys: Vec<String> = keys.into_iter().map(to_hex).collect();
+        let params = rpc_params![to_hex(child_storage_key), keys, hash];
+        let proof = self
+            .client
+            .request("childstate_getReadProof", params)
+            .await?;
+        Ok(proof)
+    }
+
     /// Fetch the genesis hash
     pub async fn genesis_hash(&self) -> Result<T::Hash, Error> {
         let block_zero = 0u32;
@@ -163,6 +268,17 @@ impl<T: Config> Rpc<T> {
         self.client.request("system_health", rpc_params![]).await
     }
 
+    /// Fetch the next nonce of the given account, taking into account any transactions
+    /// from it which are in the transaction pool.
+    pub async fn system_account_next_index(&self, account_id: &T::AccountId) -> Result<u64, Error>
+    where
+        T::AccountId: Serialize,
+    {
+        self.client
+            .request("system_accountNextIndex", rpc_params![account_id])
+            .await
+    }
+
     /// Fetch system chain
     pub async fn system_chain(&self) -> Result<String, Error> {
         self.client.request("system_chain", rpc_params![]).await
@@ -178,6 +294,61 @@ impl<T: Config> Rpc<T> {
         self.client.request("system_version", rpc_params![]).await
     }
 
+    /// Fetch the RPC methods that the node exposes.
+    pub async fn methods(&self) -> Result<types::RpcMethods, Error> {
+        self.client.request("rpc_methods", rpc_params![]).await
+    }
+
+    /// The RPC methods that the node exposes. These are fetched using [`Rpc::methods()`]
+    /// the first time that this is called and cached from then on, with the cache being
+    /// shared between clones of this [`Rpc`].
+    ///
+    /// If the node refuses to say which methods it exposes, then that is cached too, and
+    /// from then on this fails with [`RpcError::MethodNotSupported`] without asking again.
+    /// Other failures (for instance, losing the connection) are not cached.
+    pub async fn supported_methods(&self) -> Result<Arc<types::RpcMethods>, Error> {
+        let cached = self.methods.lock().expect("shouldn't be poisoned").clone();
+        match cached {
+            NodeMethods::Known(methods) => return Ok(methods),
+            NodeMethods::Unknown => {
+                return Err(RpcError::MethodNotSupported("rpc_methods".to_owned()).into())
+            }
+            NodeMethods::NotFetched => {}
+        }
+
+        match self.methods().await {
+            Ok(methods) => {
+                let methods = Arc::new(methods);
+                *self.methods.lock().expect("shouldn't be poisoned") =
+                    NodeMethods::Known(methods.clone());
+                Ok(methods)
+            }
+            // The node answered, but won't tell us which methods it exposes.
+            Err(e @ Error::Rpc(RpcError::Call(_) | RpcError::MethodNotSupported(_))) => {
+                *self.methods.lock().expect("shouldn't be poisoned") = NodeMethods::Unknown;
+                Err(e)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Pick the first of the given methods, in order of preference, that the node exposes.
+    ///
+    /// If it exposes none of them, this fails with [`RpcError::MethodNotSupported`] naming
+    /// the preferred method. If the node won't tell us which methods it exposes, then we
+    /// assume that the preferred method is available and let calling it decide.
+    pub(crate) async fn best_method<'a>(&self, methods: &[&'a str]) -> Result<&'a str, Error> {
+        let preferred = methods[0];
+        let Ok(supported) = self.supported_methods().await else {
+            return Ok(preferred);
+        };
+        methods
+            .iter()
+            .copied()
+            .find(|method| supported.contains(method))
+            .ok_or_else(|| RpcError::MethodNotSupported(preferred.to_owned()).into())
+    }
+
     /// Get a header
     pub async fn header(&self, hash: Option<T::Hash>) -> Result<Option<T::Header>, Error> {
         let params = rpc_params![hash];
@@ -195,6 +366,32 @@ impl<T: Config> Rpc<T> {
         Ok(block_hash)
     }
 
+    /// Get the headers for each of the given block hashes, sending the requests
+    /// in a single batch.
+    pub async fn headers(
+        &self,
+        hashes: impl IntoIterator<Item = T::Hash>,
+    ) -> Result<Vec<Option<T::Header>>, Error> {
+        let requests = hashes
+            .into_iter()
+            .map(|hash| ("chain_getHeader", rpc_params![hash]));
+        let headers = self.client.batch_request(requests).await?;
+        headers.into_iter().collect()
+    }
+
+    /// Get the block hashes for each of the given block numbers, sending the requests
+    /// in a single batch.
+    pub async fn block_hashes(
+        &self,
+        block_numbers: impl IntoIterator<Item = types::BlockNumber>,
+    ) -> Result<Vec<Option<T::Hash>>, Error> {
+        let requests = block_numbers
+            .into_iter()
+            .map(|n| ("chain_getBlockHash", rpc_params![n]));
+        let block_hashes = self.client.batch_request(requests).await?;
+        block_hashes.into_iter().collect()
+    }
+
     /// Get a block hash of the latest finalized block
     pub async fn finalized_head(&self) -> Result<T::Hash, Error> {
         let hash = self
@@ -323,14 +520,18 @@ impl<T: Config> Rpc<T> {
         Ok(subscription)
     }
 
-    /// Create and submit an extrinsic and return corresponding Hash if successful
+    /// Create and submit an extrinsic and return corresponding Hash if successful.
+    ///
+    /// If the node refuses the extrinsic because it is invalid, the reason is decoded
+    /// into [`TransactionError::Validity`] where possible.
     pub async fn submit_extrinsic<X: Encode>(&self, extrinsic: X) -> Result<T::Hash, Error> {
         let bytes: types::Bytes = extrinsic.encode().into();
         let params = rpc_params![bytes];
         let xt_hash = self
             .client
             .request("author_submitExtrinsic", params)
-            .await?;
+            .await
+            .map_err(decode_validity_error)?;
         Ok(xt_hash)
     }
 
@@ -394,6 +595,9 @@ impl<T: Config> Rpc<T> {
     }
 
     /// Create and submit an extrinsic and return a subscription to the events triggered.
+    ///
+    /// If the node refuses the extrinsic because it is invalid, the reason is decoded
+    /// into [`TransactionError::Validity`] where possible.
     pub async fn watch_extrinsic<X: Encode>(
         &self,
         extrinsic: X,
@@ -407,7 +611,26 @@ impl<T: Config> Rpc<T> {
                 params,
                 "author_unwatchExtrinsic",
             )
+            .await
+            .map_err(decode_validity_error)?;
+        Ok(subscription)
+    }
+
+    /// Submit an extrinsic using the new `transaction_unstable_submitAndWatch` method,
+    /// subscribing to [`types::TransactionEvent`]s describing its progress.
+    pub async fn transaction_unstable_submit_and_watch(
+        &self,
+        tx: &[u8],
+    ) -> Result<Subscription<types::TransactionEvent<T::Hash>>, Error> {
+        let subscription = self
+            .client
+            .subscribe(
+                "transaction_unstable_submitAndWatch",
+                rpc_params![to_hex(tx)],
+                "transaction_unstable_unwatch",
+            )
             .await?;
+
         Ok(subscription)
     }
 
@@ -423,6 +646,58 @@ impl<T: Config> Rpc<T> {
         Ok(())
     }
 
+    /// Fetch the raw bytes stored at a key in the node's offchain local storage.
+    pub async fn offchain_local_storage_get(
+        &self,
+        kind: types::StorageKind,
+        key: &[u8],
+    ) -> Result<Option<types::Bytes>, Error> {
+        let value = self
+            .client
+            .request("offchain_localStorageGet", rpc_params![kind, to_hex(key)])
+            .await?;
+        Ok(value)
+    }
+
+    /// Fetch the value stored at a key in the node's offchain local storage, decoding it
+    /// into the type with the given ID in the metadata. Use
+    /// [`crate::dynamic::DecodedValueThunk`] or [`crate::dynamic::DecodedValue`] to decode into
+    /// a dynamic value.
+    pub async fn offchain_local_storage_get_decoded<V: DecodeWithMetadata>(
+        &self,
+        kind: types::StorageKind,
+        key: &[u8],
+        type_id: u32,
+        metadata: &Metadata,
+    ) -> Result<Option<V>, Error> {
+        let Some(bytes) = self.offchain_local_storage_get(kind, key).await? else {
+            return Ok(None);
+        };
+        let value = V::decode_with_metadata(&mut &bytes[..], type_id, metadata)?;
+        Ok(Some(value))
+    }
+
+    /// Write raw bytes to a key in the node's offchain local storage.
+    ///
+    /// # Note
+    ///
+    /// This is an unsafe RPC method, and so the node must be started with
+    /// `--rpc-methods unsafe` (or be accessed locally) to allow it.
+    pub async fn offchain_local_storage_set(
+        &self,
+        kind: types::StorageKind,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), Error> {
+        self.client
+            .request(
+                "offchain_localStorageSet",
+                rpc_params![kind, to_hex(key), to_hex(value)],
+            )
+            .await?;
+        Ok(())
+    }
+
     /// Generate new session keys and returns the corresponding public keys.
     pub async fn rotate_keys(&self) -> Result<types::Bytes, Error> {
         self.client
@@ -461,6 +736,18 @@ impl<T: Config> Rpc<T> {
         Ok(types::DryRunResultBytes(result_bytes.0))
     }
 
+    /// Fetch the weight, class and expected fee of an extrinsic via the `payment_queryInfo`
+    /// RPC method. Prefer calling the `TransactionPaymentApi_query_info` runtime API where
+    /// it's available.
+    pub async fn payment_query_info(
+        &self,
+        encoded_signed: &[u8],
+        at: Option<T::Hash>,
+    ) -> Result<types::RuntimeDispatchInfo, Error> {
+        let params = rpc_params![to_hex(encoded_signed), at];
+        self.client.request("payment_queryInfo", params).await
+    }
+
     /// Subscribe to `chainHead_unstable_follow` to obtain all reported blocks by the chain.
     ///
     /// The subscription ID can be used to make queries for the
@@ -617,8 +904,306 @@ impl<T: Config> Rpc<T> {
 
         Ok(hash)
     }
+
+    /// Fetch the body (the list of extrinsics) of a block using the
+    /// `archive_unstable_body` method. Returns `None` if the block is not known.
+    pub async fn archive_unstable_body(
+        &self,
+        hash: T::Hash,
+    ) -> Result<Option<Vec<types::ChainBlockExtrinsic>>, Error> {
+        let body = self
+            .client
+            .request("archive_unstable_body", rpc_params![hash])
+            .await?;
+
+        Ok(body)
+    }
+
+    /// Fetch the header of a block using the `archive_unstable_header` method.
+    /// Returns `None` if the block is not known.
+    pub async fn archive_unstable_header(&self, hash: T::Hash) -> Result<Option<T::Header>, Error>
+    where
+        T::Header: Decode,
+    {
+        let header: Option<types::Bytes> = self
+            .client
+            .request("archive_unstable_header", rpc_params![hash])
+            .await?;
+
+        let header = header
+            .map(|bytes| T::Header::decode(&mut &bytes[..]))
+            .transpose()?;
+        Ok(header)
+    }
+
+    /// Query the storage of a block using the `archive_unstable_storage` method. If
+    /// `child_trie` is given, the queries apply to that child trie rather than to the
+    /// main trie.
+    ///
+    /// # Note
+    ///
+    /// The node may not process every query given. Check
+    /// [`types::ArchiveStorageResult::discarded_items`], and send any queries that
+    /// were discarded again.
+    pub async fn archive_unstable_storage(
+        &self,
+        hash: T::Hash,
+        items: Vec<types::ArchiveStorageQuery>,
+        child_trie: Option<&[u8]>,
+    ) -> Result<types::ArchiveStorageResult, Error> {
+        let result = self
+            .client
+            .request(
+                "archive_unstable_storage",
+                rpc_params![hash, items, child_trie.map(to_hex)],
+            )
+            .await?;
+
+        Ok(result)
+    }
+
+    /// Execute a runtime API call at a block using the `archive_unstable_call` method.
+    pub async fn archive_unstable_call(
+        &self,
+        hash: T::Hash,
+        function: &str,
+        call_parameters: &[u8],
+    ) -> Result<types::ArchiveCallResult, Error> {
+        let result = self
+            .client
+            .request(
+                "archive_unstable_call",
+                rpc_params![hash, function, to_hex(call_parameters)],
+            )
+            .await?;
+
+        Ok(result)
+    }
+
+    /// Fetch the hashes of every known block at the given height using the
+    /// `archive_unstable_hashByHeight` method. Below the finalized height, this will be
+    /// at most one block.
+    pub async fn archive_unstable_hash_by_height(
+        &self,
+        height: u64,
+    ) -> Result<Vec<T::Hash>, Error> {
+        let hashes = self
+            .client
+            .request("archive_unstable_hashByHeight", rpc_params![height])
+            .await?;
+
+        Ok(hashes)
+    }
+
+    /// Fetch the height of the latest finalized block using the
+    /// `archive_unstable_finalizedHeight` method.
+    pub async fn archive_unstable_finalized_height(&self) -> Result<u64, Error> {
+        let height = self
+            .client
+            .request("archive_unstable_finalizedHeight", rpc_params![])
+            .await?;
+
+        Ok(height)
+    }
+
+    /// Fetch the genesis hash using the `archive_unstable_genesisHash` method.
+    pub async fn archive_unstable_genesis_hash(&self) -> Result<T::Hash, Error> {
+        let hash = self
+            .client
+            .request("archive_unstable_genesisHash", rpc_params![])
+            .await?;
+
+        Ok(hash)
+    }
+}
+
+// Transaction pool errors carry the reason that a transaction was refused; surface
+// this as a structured error rather than an opaque RPC error where we can.
+fn decode_validity_error(err: Error) -> Error {
+    if let Error::Rpc(RpcError::Call(e)) = &err {
+        if let Some(validity) = e.transaction_validity_error() {
+            return TransactionError::Validity(validity).into();
+        }
+    }
+    err
 }
 
 fn to_hex(bytes: impl AsRef<[u8]>) -> String {
     format!("0x{}", hex::encode(bytes.as_ref()))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::substrate::{Digest, SubstrateHeader, H256},
+        error::JsonRpcError,
+        rpc::{
+            mock_rpc_client::{to_raw, MockRpcClient},
+            RawValue, RpcFuture, RpcSubscription,
+        },
+        SubstrateConfig,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    type Header = <SubstrateConfig as Config>::Header;
+
+    fn header(number: u32) -> Header {
+        SubstrateHeader {
+            parent_hash: H256::zero(),
+            number,
+            state_root: H256::zero(),
+            extrinsics_root: H256::zero(),
+            digest: Digest::default(),
+        }
+    }
+
+    // A chain whose block hashes are their block numbers. Blocks from 10 onwards don't
+    // exist yet, and asking about blocks from 100 onwards is an error.
+    fn mock_chain() -> MockRpcClient {
+        MockRpcClient::new().on_request(|method, params| {
+            let params = params.expect("params are given");
+            let number = match method {
+                "chain_getBlockHash" => serde_json::from_str::<(u64,)>(params.get()).unwrap().0,
+                "chain_getHeader" => {
+                    let (hash,): (H256,) = serde_json::from_str(params.get()).unwrap();
+                    hash.to_low_u64_be()
+                }
+                _ => panic!("unexpected method {method}"),
+            };
+            if number >= 100 {
+                return Err(RpcError::Call(JsonRpcError {
+                    code: -32602,
+                    message: "Invalid params".to_owned(),
+                    data: None,
+                }));
+            }
+            if number >= 10 {
+                return Ok(to_raw(serde_json::Value::Null));
+            }
+            match method {
+                "chain_getBlockHash" => Ok(to_raw(H256::from_low_u64_be(number))),
+                _ => Ok(to_raw(header(number as u32))),
+            }
+        })
+    }
+
+    // Sends each batch in one go, as clients with native batch support do, rather than
+    // making each request in turn.
+    struct BatchingClient {
+        inner: MockRpcClient,
+        batches: AtomicUsize,
+    }
+
+    impl RpcClientT for BatchingClient {
+        fn request_raw<'a>(
+            &'a self,
+            _method: &'a str,
+            _params: Option<Box<RawValue>>,
+        ) -> RpcFuture<'a, Box<RawValue>> {
+            panic!("requests should be batched")
+        }
+
+        fn subscribe_raw<'a>(
+            &'a self,
+            sub: &'a str,
+            params: Option<Box<RawValue>>,
+            unsub: &'a str,
+        ) -> RpcFuture<'a, RpcSubscription> {
+            self.inner.subscribe_raw(sub, params, unsub)
+        }
+
+        fn batch_request_raw<'a>(
+            &'a self,
+            batch: Vec<(&'a str, Option<Box<RawValue>>)>,
+        ) -> RpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+            self.batches.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                let requests = batch
+                    .into_iter()
+                    .map(|(method, params)| self.inner.request_raw(method, params));
+                Ok(futures::future::join_all(requests).await)
+            })
+        }
+    }
+
+    async fn check_batches(rpc: Rpc<SubstrateConfig>) {
+        let hashes = rpc
+            .block_hashes([3u32, 1, 10, 2].map(types::BlockNumber::from))
+            .await
+            .unwrap();
+        let expected = [Some(3), Some(1), None, Some(2)].map(|n| n.map(H256::from_low_u64_be));
+        assert_eq!(hashes, expected);
+
+        let hashes = [2, 11, 1].map(H256::from_low_u64_be);
+        let headers = rpc.headers(hashes).await.unwrap();
+        assert_eq!(headers, vec![Some(header(2)), None, Some(header(1))]);
+
+        // Any request in the batch failing fails the whole thing:
+        let err = rpc
+            .block_hashes([1u32, 100, 2].map(types::BlockNumber::from))
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, Error::Rpc(RpcError::Call(e)) if e.code == -32602),
+            "unexpected error: {err}"
+        );
+        let err = rpc.headers([H256::from_low_u64_be(100)]).await.unwrap_err();
+        assert!(matches!(err, Error::Rpc(RpcError::Call(_))));
+
+        assert_eq!(rpc.block_hashes([]).await.unwrap(), vec![]);
+    }
+
+    #[tokio::test]
+    async fn batches_made_in_turn() {
+        check_batches(Rpc::new(Arc::new(mock_chain()))).await;
+    }
+
+    #[tokio::test]
+    async fn batches_made_in_one_go() {
+        let client = Arc::new(BatchingClient {
+            inner: mock_chain(),
+            batches: AtomicUsize::new(0),
+        });
+        check_batches(Rpc::new(client.clone())).await;
+        assert_eq!(client.batches.load(Ordering::SeqCst), 5);
+    }
+
+    #[tokio::test]
+    async fn unknown_methods_are_cached() {
+        // A node which doesn't expose `rpc_methods`, and whose connection drops the
+        // first time that it's asked.
+        let requests = Arc::new(AtomicUsize::new(0));
+        let client = MockRpcClient::new().on_request({
+            let requests = requests.clone();
+            move |method, _params| {
+                assert_eq!(method, "rpc_methods");
+                if requests.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return Err(RpcError::ClientError("connection closed".into()));
+                }
+                Err(RpcError::Call(JsonRpcError {
+                    code: JsonRpcError::METHOD_NOT_FOUND,
+                    message: "Method not found".to_owned(),
+                    data: None,
+                }))
+            }
+        });
+        let rpc = Rpc::<SubstrateConfig>::new(Arc::new(client));
+
+        // Without knowing which methods there are, the preferred one is picked. Losing
+        // the connection isn't cached, but the node refusing to tell us is.
+        for _ in 0..3 {
+            let method = rpc
+                .best_method(&["new_method", "old_method"])
+                .await
+                .unwrap();
+            assert_eq!(method, "new_method");
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        assert!(matches!(
+            rpc.supported_methods().await,
+            Err(Error::Rpc(RpcError::MethodNotSupported(_)))
+        ));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+}
@@ -3,7 +3,7 @@
 // see LICENSE for license details.
 
 use super::{RpcClientT, RpcSubscription, RpcSubscriptionId};
-use crate::error::Error;
+use crate::error::{Error, JsonRpcError, RpcError};
 use futures::{Stream, StreamExt};
 use serde::{de::DeserializeOwned, Serialize};
 use serde_json::value::RawValue;
@@ -31,11 +31,46 @@ impl RpcClient {
         method: &str,
         params: RpcParams,
     ) -> Result<Res, Error> {
-        let res = self.0.request_raw(method, params.build()).await?;
+        let res = self
+            .0
+            .request_raw(method, params.build())
+            .await
+            .map_err(|e| name_missing_method(e, method))?;
         let val = serde_json::from_str(res.get())?;
         Ok(val)
     }
 
+    /// Make a batch of RPC requests, each given as a method name and some parameters.
+    /// Every response is expected to decode to the same type. A result is handed back
+    /// for each request, in the same order that the requests were given.
+    ///
+    /// The outer `Result` fails if the batch as a whole could not be sent; the inner
+    /// results correspond to each individual request.
+    ///
+    /// See [`RpcParams`] and the [`rpc_params!`] macro for an example of how to
+    /// construct the parameters.
+    pub async fn batch_request<'a, Res: DeserializeOwned>(
+        &self,
+        requests: impl IntoIterator<Item = (&'a str, RpcParams)>,
+    ) -> Result<Vec<Result<Res, Error>>, Error> {
+        let batch: Vec<_> = requests
+            .into_iter()
+            .map(|(method, params)| (method, params.build()))
+            .collect();
+        let methods: Vec<&str> = batch.iter().map(|(method, _)| *method).collect();
+        let res = self.0.batch_request_raw(batch).await?;
+        let vals = res
+            .into_iter()
+            .zip(methods)
+            .map(|(r, method)| {
+                let raw_val = r.map_err(|e| name_missing_method(e, method))?;
+                let val = serde_json::from_str(raw_val.get())?;
+                Ok(val)
+            })
+            .collect();
+        Ok(vals)
+    }
+
     /// Subscribe to an RPC endpoint, providing the parameters and the method to call to
     /// unsubscribe from it again.
     ///
@@ -47,11 +82,26 @@ impl RpcClient {
         params: RpcParams,
         unsub: &str,
     ) -> Result<Subscription<Res>, Error> {
-        let sub = self.0.subscribe_raw(sub, params.build(), unsub).await?;
+        let sub = self
+            .0
+            .subscribe_raw(sub, params.build(), unsub)
+            .await
+            .map_err(|e| name_missing_method(e, sub))?;
         Ok(Subscription::new(sub))
     }
 }
 
+// Nodes hand back a generic "method not found" error when asked to call something
+// that they don't expose; say which method that was instead.
+fn name_missing_method(err: RpcError, method: &str) -> RpcError {
+    match err {
+        RpcError::Call(e) if e.code == JsonRpcError::METHOD_NOT_FOUND => {
+            RpcError::MethodNotSupported(method.to_owned())
+        }
+        err => err,
+    }
+}
+
 impl std::fmt::Debug for RpcClient {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_tuple("RpcClient").finish()
@@ -208,3 +258,30 @@ impl<Res: DeserializeOwned> Stream for Subscription<Res> {
         Poll::Ready(res)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::mock_rpc_client::MockRpcClient;
+
+    #[tokio::test]
+    async fn missing_methods_are_named() {
+        // A node which doesn't know about any methods at all.
+        let rpc = MockRpcClient::new().on_request(|_method, _params| {
+            Err(RpcError::Call(JsonRpcError {
+                code: JsonRpcError::METHOD_NOT_FOUND,
+                message: "Method not found".to_owned(),
+                data: None,
+            }))
+        });
+        let client = RpcClient::new(Arc::new(rpc));
+        let err = client
+            .request::<String>("system_dryRun", rpc_params![])
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(&err, Error::Rpc(RpcError::MethodNotSupported(m)) if m == "system_dryRun"),
+            "unexpected error: {err}"
+        );
+    }
+}
@@ -50,6 +50,30 @@ pub trait RpcClientT: Send + Sync + 'static {
         params: Option<Box<RawValue>>,
         unsub: &'a str,
     ) -> RpcFuture<'a, RpcSubscription>;
+
+    /// Make a batch of raw requests, handing back a result for each request in the same
+    /// order that they were given. Params are expected to be in the same format as those
+    /// handed to [`RpcClientT::request_raw`].
+    ///
+    /// The outer `Result` fails if the batch as a whole could not be sent or its response
+    /// understood; the inner results correspond to each individual request.
+    ///
+    /// By default, this makes each request in turn via [`RpcClientT::request_raw`]. Clients
+    /// which are able to send a JSON-RPC batch in a single round trip should override this.
+    ///
+    /// Prefer to use the interface provided on [`super::RpcClient`] where possible.
+    fn batch_request_raw<'a>(
+        &'a self,
+        batch: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        Box::pin(async move {
+            let mut results = Vec::with_capacity(batch.len());
+            for (method, params) in batch {
+                results.push(self.request_raw(method, params).await);
+            }
+            Ok(results)
+        })
+    }
 }
 
 /// A boxed future that is returned from the [`RpcClientT`] methods.
@@ -69,3 +93,38 @@ pub type RpcSubscriptionStream =
 
 /// The ID associated with the [`RpcClientT`]'s `subscription`.
 pub type RpcSubscriptionId = String;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::{mock_rpc_client::MockRpcClient, rpc_params, RpcClient};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn default_batches_are_made_in_order() {
+        let client = MockRpcClient::new();
+        let res = client
+            .batch_request_raw(vec![("a", None), ("fail", None), ("b", None)])
+            .await
+            .unwrap();
+
+        assert_eq!(res.len(), 3);
+        assert_eq!(res[0].as_ref().unwrap().get(), r#""a""#);
+        assert!(matches!(res[1], Err(RpcError::ClientError(_))));
+        assert_eq!(res[2].as_ref().unwrap().get(), r#""b""#);
+
+        // Each result is decoded separately:
+        let client = RpcClient::new(Arc::new(client));
+        let res = client
+            .batch_request::<String>([
+                ("a", rpc_params![]),
+                ("fail", rpc_params![]),
+                ("b", rpc_params![]),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(res[0].as_ref().unwrap(), "a");
+        assert!(res[1].is_err());
+        assert_eq!(res[2].as_ref().unwrap(), "b");
+    }
+}
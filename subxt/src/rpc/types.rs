@@ -374,6 +374,50 @@ pub struct Health {
     pub should_have_peers: bool,
 }
 
+/// The RPC methods that a node exposes, as returned by `rpc_methods`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RpcMethods {
+    /// The version of the response format.
+    pub version: u32,
+    /// The names of the methods (and subscriptions) that the node exposes.
+    pub methods: Vec<String>,
+}
+
+impl RpcMethods {
+    /// Does the node expose a method with the given name?
+    pub fn contains(&self, method: &str) -> bool {
+        self.methods.iter().any(|m| m == method)
+    }
+}
+
+/// Information about the dispatch of an extrinsic, as returned by `payment_queryInfo`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeDispatchInfo {
+    /// The weight of the extrinsic. The shape of this depends on the weight type used
+    /// by the runtime, and so it's left undecoded.
+    pub weight: serde_json::Value,
+    /// The class of the extrinsic, for instance `"normal"` or `"operational"`.
+    pub class: String,
+    /// The fee that the extrinsic is expected to pay, less any tip.
+    #[serde(deserialize_with = "deserialize_balance")]
+    pub partial_fee: u128,
+}
+
+// Balances are given as decimal strings, to avoid overflowing JavaScript numbers.
+fn deserialize_balance<'de, D: serde::Deserializer<'de>>(d: D) -> Result<u128, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Balance {
+        Number(u64),
+        String(String),
+    }
+    match Balance::deserialize(d)? {
+        Balance::Number(n) => Ok(n.into()),
+        Balance::String(s) => s.parse().map_err(serde::de::Error::custom),
+    }
+}
+
 /// The operation could not be processed due to an error.
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -728,6 +772,128 @@ impl<Hash> From<TransactionEventIR<Hash>> for TransactionEvent<Hash> {
     }
 }
 
+/// The kind of offchain local storage to access. This mirrors `StorageKind` from
+/// `sp_core::offchain`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum StorageKind {
+    /// Persistent storage is non-revertible and not fork-aware. Values written here are
+    /// visible to every offchain worker, regardless of the block they are run for.
+    Persistent,
+    /// Local storage is revertible and fork-aware. Values written here are only visible
+    /// to offchain workers run for blocks on the same fork.
+    Local,
+}
+
+/// The type of an item to query using `archive_unstable_storage`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ArchiveStorageQueryType {
+    /// Fetch the value stored at the key.
+    Value,
+    /// Fetch the hash of the value stored at the key.
+    Hash,
+    /// Fetch the merkle value of the closest descendant of the key.
+    ClosestDescendantMerkleValue,
+    /// Fetch the values of every key which has the given key as a prefix.
+    DescendantsValues,
+    /// Fetch the hashes of the values of every key which has the given key as a prefix.
+    DescendantsHashes,
+}
+
+/// An item to query using `archive_unstable_storage`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveStorageQuery {
+    /// The storage key to query.
+    pub key: Bytes,
+    /// What to fetch about the key.
+    #[serde(rename = "type")]
+    pub query_type: ArchiveStorageQueryType,
+    /// For descendant queries, only keys which come after this one in lexicographic
+    /// order are returned. This allows large queries to be paged through.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pagination_start_key: Option<Bytes>,
+}
+
+impl ArchiveStorageQuery {
+    /// Construct a query of the given type for some storage key.
+    pub fn new(key: impl Into<Vec<u8>>, query_type: ArchiveStorageQueryType) -> Self {
+        Self {
+            key: Bytes(key.into()),
+            query_type,
+            pagination_start_key: None,
+        }
+    }
+}
+
+/// The response to an `archive_unstable_storage` request.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveStorageResult {
+    /// The items found. Queries for keys which have no value produce no items.
+    #[serde(rename = "result")]
+    pub items: Vec<ArchiveStorageItem>,
+    /// The number of queries, counting from the end of those given, which the node
+    /// did not process. These should be sent again in another request.
+    pub discarded_items: usize,
+}
+
+/// A single storage item handed back from `archive_unstable_storage`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveStorageItem {
+    /// The storage key that this item is for.
+    pub key: Bytes,
+    /// The value at the key, if it was asked for.
+    #[serde(default)]
+    pub value: Option<Bytes>,
+    /// The hash of the value at the key, if it was asked for.
+    #[serde(default)]
+    pub hash: Option<Bytes>,
+    /// The closest descendant merkle value of the key, if it was asked for.
+    #[serde(default)]
+    pub closest_descendant_merkle_value: Option<Bytes>,
+    /// The child trie that the key belongs to, if any.
+    #[serde(default)]
+    pub child_trie_key: Option<Bytes>,
+}
+
+/// The response to an `archive_unstable_call` request.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "ArchiveCallResultIR")]
+pub enum ArchiveCallResult {
+    /// The runtime API call succeeded, returning these SCALE encoded bytes.
+    Success(Bytes),
+    /// The runtime API call failed for the given reason.
+    Error(String),
+}
+
+/// Intermediate representation (IR) for [`ArchiveCallResult`], which is handed back in a
+/// JSON compatible format similar to:
+///
+/// ```json
+/// { success: true, value: "0xFF" }
+/// ```
+#[derive(Deserialize)]
+struct ArchiveCallResultIR {
+    success: bool,
+    #[serde(default)]
+    value: Option<Bytes>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl From<ArchiveCallResultIR> for ArchiveCallResult {
+    fn from(value: ArchiveCallResultIR) -> Self {
+        match (value.success, value.value) {
+            (true, Some(bytes)) => ArchiveCallResult::Success(bytes),
+            (true, None) => ArchiveCallResult::Error("no value was returned".to_owned()),
+            (false, _) => ArchiveCallResult::Error(value.error.unwrap_or_default()),
+        }
+    }
+}
+
 /// Serialize and deserialize helper as string.
 mod as_string {
     use super::*;
@@ -857,4 +1023,37 @@ mod test {
             },
         );
     }
+
+    #[test]
+    fn archive_call_result_deserializes() {
+        let res: ArchiveCallResult =
+            serde_json::from_value(serde_json::json!({ "success": true, "value": "0x0102" }))
+                .unwrap();
+        assert_eq!(res, ArchiveCallResult::Success(Bytes(vec![1, 2])));
+
+        let res: ArchiveCallResult =
+            serde_json::from_value(serde_json::json!({ "success": false, "error": "boom" }))
+                .unwrap();
+        assert_eq!(res, ArchiveCallResult::Error("boom".to_owned()));
+    }
+
+    #[test]
+    fn storage_kind_is_substrate_compatible() {
+        use sp_core::offchain::StorageKind as SpStorageKind;
+
+        assert_ser_deser(&SpStorageKind::PERSISTENT, &StorageKind::Persistent);
+        assert_ser_deser(&SpStorageKind::LOCAL, &StorageKind::Local);
+    }
+
+    #[test]
+    fn runtime_dispatch_info_deserializes() {
+        let info: RuntimeDispatchInfo = serde_json::from_value(serde_json::json!({
+            "weight": { "refTime": 1000, "proofSize": 0 },
+            "class": "normal",
+            "partialFee": "340282366920938463463374607431768211455"
+        }))
+        .unwrap();
+        assert_eq!(info.class, "normal");
+        assert_eq!(info.partial_fee, u128::MAX);
+    }
 }
@@ -0,0 +1,267 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! An [`RpcClientT`] implementation which talks to a node over a Unix domain socket.
+//!
+//! Messages are exchanged as JSON-RPC. Each message that we send is terminated by a
+//! newline, but messages from the node are framed by where each JSON value ends, and
+//! so they may or may not be separated by whitespace.
+//!
+//! This is a `jsonrpsee` client with a Unix socket transport plugged in, and so it
+//! supports requests, batches and subscriptions in exactly the same way as the
+//! default websocket client does.
+
+use super::{RpcClientT, RpcFuture, RpcSubscription};
+use crate::error::RpcError;
+use jsonrpsee::core::client::{
+    Client, ClientBuilder, ReceivedMessage, TransportReceiverT, TransportSenderT,
+};
+use serde_json::value::RawValue;
+use std::{future::Future, io, path::Path, pin::Pin};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{
+        unix::{OwnedReadHalf, OwnedWriteHalf},
+        UnixStream,
+    },
+};
+
+/// An [`RpcClientT`] implementation which talks to a node over a Unix domain socket,
+/// for tools running on the same host as the node which would rather not have it
+/// listen on a websocket port.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[tokio::main]
+/// # async fn main() {
+/// use std::sync::Arc;
+/// use subxt::{rpc::UnixSocketRpcClient, OnlineClient, PolkadotConfig};
+///
+/// let rpc_client = UnixSocketRpcClient::connect("/tmp/node.ipc").await.unwrap();
+///
+/// let api = OnlineClient::<PolkadotConfig>::from_rpc_client(Arc::new(rpc_client))
+///     .await
+///     .unwrap();
+/// # }
+/// ```
+pub struct UnixSocketRpcClient {
+    client: Client,
+}
+
+impl std::fmt::Debug for UnixSocketRpcClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UnixSocketRpcClient").finish()
+    }
+}
+
+impl UnixSocketRpcClient {
+    /// Connect to a node listening on the Unix socket at the given path.
+    pub async fn connect(path: impl AsRef<Path>) -> Result<Self, RpcError> {
+        let stream = UnixStream::connect(path)
+            .await
+            .map_err(|e| RpcError::ClientError(Box::new(e)))?;
+        let (read, write) = stream.into_split();
+
+        let client = ClientBuilder::default()
+            .max_notifs_per_subscription(4096)
+            .build_with_tokio(
+                Sender(write),
+                Receiver {
+                    read: BufReader::new(read),
+                    buf: Vec::new(),
+                },
+            );
+        Ok(Self { client })
+    }
+}
+
+impl RpcClientT for UnixSocketRpcClient {
+    fn request_raw<'a>(
+        &'a self,
+        method: &'a str,
+        params: Option<Box<RawValue>>,
+    ) -> RpcFuture<'a, Box<RawValue>> {
+        self.client.request_raw(method, params)
+    }
+
+    fn subscribe_raw<'a>(
+        &'a self,
+        sub: &'a str,
+        params: Option<Box<RawValue>>,
+        unsub: &'a str,
+    ) -> RpcFuture<'a, RpcSubscription> {
+        self.client.subscribe_raw(sub, params, unsub)
+    }
+
+    fn batch_request_raw<'a>(
+        &'a self,
+        batch: Vec<(&'a str, Option<Box<RawValue>>)>,
+    ) -> RpcFuture<'a, Vec<Result<Box<RawValue>, RpcError>>> {
+        self.client.batch_request_raw(batch)
+    }
+}
+
+// The transport traits are `async_trait`s. We don't depend on `async_trait`
+// ourselves, so we write out the boxed futures that it would produce by hand.
+type TransportFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, io::Error>> + Send + 'a>>;
+
+struct Sender(OwnedWriteHalf);
+
+impl TransportSenderT for Sender {
+    type Error = io::Error;
+
+    fn send<'a, 'async_trait>(&'a mut self, msg: String) -> TransportFuture<'async_trait, ()>
+    where
+        'a: 'async_trait,
+        Self: 'async_trait,
+    {
+        Box::pin(async move {
+            self.0.write_all(msg.as_bytes()).await?;
+            self.0.write_all(b"\n").await?;
+            self.0.flush().await
+        })
+    }
+
+    fn close<'a, 'async_trait>(&'a mut self) -> TransportFuture<'async_trait, ()>
+    where
+        'a: 'async_trait,
+        Self: 'async_trait,
+    {
+        Box::pin(self.0.shutdown())
+    }
+}
+
+struct Receiver {
+    read: BufReader<OwnedReadHalf>,
+    // Bytes that we've read but not yet handed back as a message.
+    buf: Vec<u8>,
+}
+
+impl TransportReceiverT for Receiver {
+    type Error = io::Error;
+
+    fn receive<'a, 'async_trait>(&'a mut self) -> TransportFuture<'async_trait, ReceivedMessage>
+    where
+        'a: 'async_trait,
+        Self: 'async_trait,
+    {
+        Box::pin(async move {
+            loop {
+                // Hand back the first complete JSON value in the buffer, if there is one.
+                let mut values =
+                    serde_json::Deserializer::from_slice(&self.buf).into_iter::<&RawValue>();
+                match values.next() {
+                    Some(Ok(value)) => {
+                        let msg = value.get().to_owned();
+                        let end = values.byte_offset();
+                        self.buf.drain(..end);
+                        return Ok(ReceivedMessage::Text(msg));
+                    }
+                    // We only have part of the next message so far.
+                    Some(Err(e)) if e.is_eof() => {}
+                    Some(Err(e)) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+                    // There's nothing but whitespace in the buffer.
+                    None => self.buf.clear(),
+                }
+
+                let read = self.read.fill_buf().await?;
+                if read.is_empty() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "the node closed the connection",
+                    ));
+                }
+                let len = read.len();
+                self.buf.extend_from_slice(read);
+                self.read.consume(len);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::rpc::{rpc_params, RpcClient};
+    use futures::StreamExt;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+    use tokio::net::UnixListener;
+
+    // A stand-in for a node, which answers `system_name` requests and hands back a
+    // couple of items to anything which subscribes. Unless `delimited` is set, the
+    // responses to each request are written back to back, and split across two writes.
+    async fn serve(listener: UnixListener, delimited: bool) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+
+        while let Some(line) = lines.next_line().await.unwrap() {
+            let req: Value = serde_json::from_str(&line).unwrap();
+            let id = req["id"].clone();
+            let responses = match req["method"].as_str().unwrap() {
+                "system_name" => vec![json!({ "jsonrpc": "2.0", "id": id, "result": "stand-in" })],
+                "test_subscribe" => {
+                    let mut responses =
+                        vec![json!({ "jsonrpc": "2.0", "id": id, "result": "sub" })];
+                    for n in [1, 2] {
+                        responses.push(json!({
+                            "jsonrpc": "2.0",
+                            "method": "test_subscription",
+                            "params": { "subscription": "sub", "result": n }
+                        }));
+                    }
+                    responses
+                }
+                _ => vec![json!({ "jsonrpc": "2.0", "id": id, "result": true })],
+            };
+            if delimited {
+                for res in responses {
+                    let msg = format!("{res}\n");
+                    write.write_all(msg.as_bytes()).await.unwrap();
+                }
+            } else {
+                let msgs: String = responses.iter().map(|res| res.to_string()).collect();
+                let (first, second) = msgs.split_at(msgs.len() / 2);
+                write.write_all(first.as_bytes()).await.unwrap();
+                write.flush().await.unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+                write.write_all(second.as_bytes()).await.unwrap();
+            }
+        }
+    }
+
+    async fn check_requests_and_subscriptions(label: &str, delimited: bool) {
+        let path =
+            std::env::temp_dir().join(format!("subxt-test-{}-{label}.ipc", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        tokio::spawn(serve(listener, delimited));
+
+        let client = RpcClient::new(Arc::new(UnixSocketRpcClient::connect(&path).await.unwrap()));
+
+        let name: String = client.request("system_name", rpc_params![]).await.unwrap();
+        assert_eq!(name, "stand-in");
+
+        let sub = client
+            .subscribe::<u32>("test_subscribe", rpc_params![], "test_unsubscribe")
+            .await
+            .unwrap();
+        let items: Vec<u32> = sub.take(2).map(|i| i.unwrap()).collect().await;
+        assert_eq!(items, vec![1, 2]);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn requests_and_subscriptions_work() {
+        check_requests_and_subscriptions("delimited", true).await;
+    }
+
+    #[tokio::test]
+    async fn messages_need_not_be_newline_delimited() {
+        check_requests_and_subscriptions("undelimited", false).await;
+    }
+}
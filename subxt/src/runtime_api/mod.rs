@@ -11,3 +11,5 @@ mod runtime_types;
 pub use runtime_client::RuntimeApiClient;
 pub use runtime_payload::{dynamic, DynamicRuntimeApiPayload, Payload, RuntimeApiPayload};
 pub use runtime_types::RuntimeApi;
+
+pub(crate) use runtime_types::encode_runtime_api_call;
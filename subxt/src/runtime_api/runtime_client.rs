@@ -13,6 +13,7 @@ use std::{future::Future, marker::PhantomData};
 #[derivative(Clone(bound = "Client: Clone"))]
 pub struct RuntimeApiClient<T, Client> {
     client: Client,
+    use_archive_rpc: bool,
     _marker: PhantomData<T>,
 }
 
@@ -21,9 +22,18 @@ impl<T, Client> RuntimeApiClient<T, Client> {
     pub fn new(client: Client) -> Self {
         Self {
             client,
+            use_archive_rpc: false,
             _marker: PhantomData,
         }
     }
+
+    /// Route the calls made by any [`RuntimeApi`] obtained from this client through the
+    /// `archive_unstable_call` RPC method rather than the legacy `state_call` one. Use
+    /// this to make calls at blocks which are too old for the node to answer otherwise.
+    pub fn use_archive_rpc(mut self, use_archive_rpc: bool) -> Self {
+        self.use_archive_rpc = use_archive_rpc;
+        self
+    }
 }
 
 impl<T, Client> RuntimeApiClient<T, Client>
@@ -33,7 +43,7 @@ where
 {
     /// Obtain a runtime API interface at some block hash.
     pub fn at(&self, block_hash: T::Hash) -> RuntimeApi<T, Client> {
-        RuntimeApi::new(self.client.clone(), block_hash)
+        RuntimeApi::new(self.client.clone(), block_hash).use_archive_rpc(self.use_archive_rpc)
     }
 
     /// Obtain a runtime API interface at the latest block hash.
@@ -43,15 +53,20 @@ where
         // Clone and pass the client in like this so that we can explicitly
         // return a Future that's Send + 'static, rather than tied to &self.
         let client = self.client.clone();
+        let use_archive_rpc = self.use_archive_rpc;
         async move {
-            // get the hash for the latest block and use that.
-            let block_hash = client
-                .rpc()
-                .block_hash(None)
-                .await?
-                .expect("didn't pass a block number; qed");
-
-            Ok(RuntimeApi::new(client, block_hash))
+            // use the block that the client is pinned to, or failing
+            // that, get the hash for the latest block and use that.
+            let block_hash = match client.pinned_block_hash() {
+                Some(hash) => hash,
+                None => client
+                    .rpc()
+                    .block_hash(None)
+                    .await?
+                    .expect("didn't pass a block number; qed"),
+            };
+
+            Ok(RuntimeApi::new(client, block_hash).use_archive_rpc(use_archive_rpc))
         }
     }
 }
@@ -4,8 +4,9 @@
 
 use crate::{
     client::OnlineClientT,
-    error::{Error, MetadataError},
-    metadata::DecodeWithMetadata,
+    error::{ArchiveError, Error, MetadataError},
+    metadata::{DecodeWithMetadata, Metadata},
+    rpc::types::ArchiveCallResult,
     Config,
 };
 use codec::Decode;
@@ -20,6 +21,7 @@ use super::RuntimeApiPayload;
 pub struct RuntimeApi<T: Config, Client> {
     client: Client,
     block_hash: T::Hash,
+    use_archive_rpc: bool,
     _marker: PhantomData<T>,
 }
 
@@ -29,9 +31,18 @@ impl<T: Config, Client> RuntimeApi<T, Client> {
         Self {
             client,
             block_hash,
+            use_archive_rpc: false,
             _marker: PhantomData,
         }
     }
+
+    /// Make runtime API calls using the `archive_unstable_call` RPC method rather than
+    /// the legacy `state_call` one. Archive nodes can answer these for any block,
+    /// however old.
+    pub fn use_archive_rpc(mut self, use_archive_rpc: bool) -> Self {
+        self.use_archive_rpc = use_archive_rpc;
+        self
+    }
 }
 
 impl<T, Client> RuntimeApi<T, Client>
@@ -47,9 +58,14 @@ where
     ) -> impl Future<Output = Result<Res, Error>> + 'a {
         let client = self.client.clone();
         let block_hash = self.block_hash;
+        let use_archive_rpc = self.use_archive_rpc;
         // Ensure that the returned future doesn't have a lifetime tied to api.runtime_api(),
         // which is a temporary thing we'll be throwing away quickly:
         async move {
+            if use_archive_rpc {
+                let bytes = archive_call(&client, block_hash, function, call_parameters).await?;
+                return Ok(Res::decode(&mut &bytes[..])?);
+            }
             let data: Res = client
                 .rpc()
                 .state_call(function, call_parameters, Some(block_hash))
@@ -65,45 +81,178 @@ where
     ) -> impl Future<Output = Result<Call::ReturnType, Error>> {
         let client = self.client.clone();
         let block_hash = self.block_hash;
+        let use_archive_rpc = self.use_archive_rpc;
         // Ensure that the returned future doesn't have a lifetime tied to api.runtime_api(),
         // which is a temporary thing we'll be throwing away quickly:
         async move {
             let metadata = client.metadata();
+            let (call_name, params, output_ty) = encode_runtime_api_call(&payload, &metadata)?;
 
-            let api_trait = metadata.runtime_api_trait_by_name_err(payload.trait_name())?;
-            let api_method = api_trait
-                .method_by_name(payload.method_name())
-                .ok_or_else(|| {
-                    MetadataError::RuntimeMethodNotFound(payload.method_name().to_owned())
-                })?;
-
-            // Validate the runtime API payload hash against the compile hash from codegen.
-            if let Some(static_hash) = payload.validation_hash() {
-                let Some(runtime_hash) = api_trait.method_hash(payload.method_name()) else {
-                    return Err(MetadataError::IncompatibleCodegen.into());
-                };
-                if static_hash != runtime_hash {
-                    return Err(MetadataError::IncompatibleCodegen.into());
-                }
-            }
-
-            // Encode the arguments of the runtime call.
-            // For static payloads (codegen) this is pass-through, bytes are not altered.
-            // For dynamic payloads this relies on `scale_value::encode_as_fields_to`.
-            let params = payload.encode_args(&metadata)?;
-            let call_name = format!("{}_{}", payload.trait_name(), payload.method_name());
-
-            let bytes = client
-                .rpc()
-                .state_call_raw(&call_name, Some(params.as_slice()), Some(block_hash))
-                .await?;
+            let bytes = if use_archive_rpc {
+                archive_call(&client, block_hash, &call_name, Some(params.as_slice())).await?
+            } else {
+                client
+                    .rpc()
+                    .state_call_raw(&call_name, Some(params.as_slice()), Some(block_hash))
+                    .await?
+                    .0
+            };
 
             let value = <Call::ReturnType as DecodeWithMetadata>::decode_with_metadata(
                 &mut &bytes[..],
-                api_method.output_ty(),
+                output_ty,
                 &metadata,
             )?;
             Ok(value)
         }
     }
 }
+
+// Make a runtime API call using the `archive_unstable_call` method, handing back the
+// bytes returned.
+async fn archive_call<T: Config, Client: OnlineClientT<T>>(
+    client: &Client,
+    block_hash: T::Hash,
+    function: &str,
+    call_parameters: Option<&[u8]>,
+) -> Result<Vec<u8>, Error> {
+    let res = client
+        .rpc()
+        .archive_unstable_call(block_hash, function, call_parameters.unwrap_or_default())
+        .await?;
+    match res {
+        ArchiveCallResult::Success(bytes) => Ok(bytes.0),
+        ArchiveCallResult::Error(reason) => Err(ArchiveError::CallFailed {
+            function: function.to_owned(),
+            reason,
+        }
+        .into()),
+    }
+}
+
+/// Validate a runtime API payload against the metadata, and encode it. This hands back
+/// the name of the runtime API function to call, the encoded arguments to call it with,
+/// and the type ID of the value that it returns.
+pub(crate) fn encode_runtime_api_call<Call: RuntimeApiPayload>(
+    payload: &Call,
+    metadata: &Metadata,
+) -> Result<(String, Vec<u8>, u32), Error> {
+    let api_trait = metadata.runtime_api_trait_by_name_err(payload.trait_name())?;
+    let api_method = api_trait
+        .method_by_name(payload.method_name())
+        .ok_or_else(|| MetadataError::RuntimeMethodNotFound(payload.method_name().to_owned()))?;
+
+    // Validate the runtime API payload hash against the compile hash from codegen.
+    if let Some(static_hash) = payload.validation_hash() {
+        let Some(runtime_hash) = api_trait.method_hash(payload.method_name()) else {
+            return Err(MetadataError::IncompatibleCodegen.into());
+        };
+        if static_hash != runtime_hash {
+            return Err(MetadataError::IncompatibleCodegen.into());
+        }
+    }
+
+    // Encode the arguments of the runtime call.
+    // For static payloads (codegen) this is pass-through, bytes are not altered.
+    // For dynamic payloads this relies on `scale_value::encode_as_fields_to`.
+    let params = payload.encode_args(metadata)?;
+    let call_name = format!("{}_{}", payload.trait_name(), payload.method_name());
+
+    Ok((call_name, params, api_method.output_ty()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::substrate::H256,
+        rpc::{
+            mock_rpc_client::{to_raw, MockRpcClient},
+            types::{Bytes, RuntimeVersion},
+        },
+        OnlineClient, SubstrateConfig,
+    };
+    use codec::Encode;
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+
+    type Requests = Arc<Mutex<Vec<(String, Value)>>>;
+
+    // A client which records the requests made to it. Legacy runtime calls hand back the
+    // number 1 and archive ones the number 2, unless the function called is `Fail_fail`.
+    fn client(requests: Requests) -> OnlineClient<SubstrateConfig> {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_tiny.scale").unwrap();
+        let metadata = Metadata::decode(&mut &*bytes).unwrap();
+
+        let rpc = MockRpcClient::new().on_request(move |method, params| {
+            let params: Value = serde_json::from_str(params.unwrap().get()).unwrap();
+            requests
+                .lock()
+                .unwrap()
+                .push((method.to_owned(), params.clone()));
+            match (method, params[1].as_str()) {
+                ("state_call", _) => Ok(to_raw(Bytes(1u32.encode()))),
+                ("archive_unstable_call", Some("Fail_fail")) => {
+                    Ok(to_raw(json!({ "success": false, "error": "boom" })))
+                }
+                ("archive_unstable_call", _) => Ok(to_raw(json!({
+                    "success": true,
+                    "value": Bytes(2u32.encode()),
+                }))),
+                _ => panic!("unexpected method {method}"),
+            }
+        });
+
+        let runtime_version = RuntimeVersion {
+            spec_version: 1,
+            transaction_version: 1,
+            other: Default::default(),
+        };
+        OnlineClient::from_rpc_client_with(H256::zero(), runtime_version, metadata, Arc::new(rpc))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn calls_are_routed_by_use_archive_rpc() {
+        let requests = Requests::default();
+        let block_hash = H256::repeat_byte(1);
+        let api = RuntimeApi::new(client(requests.clone()), block_hash);
+
+        let res: u32 = api.call_raw("Core_version", None).await.unwrap();
+        assert_eq!(res, 1);
+
+        let res: u32 = api
+            .use_archive_rpc(true)
+            .call_raw("Core_version", Some(&[1, 2]))
+            .await
+            .unwrap();
+        assert_eq!(res, 2);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(
+            *requests,
+            vec![
+                (
+                    "state_call".to_owned(),
+                    json!(["Core_version", "0x", block_hash])
+                ),
+                (
+                    "archive_unstable_call".to_owned(),
+                    json!([block_hash, "Core_version", "0x0102"])
+                ),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn failed_archive_calls_are_archive_errors() {
+        let api = RuntimeApi::new(client(Requests::default()), H256::zero()).use_archive_rpc(true);
+
+        let err = api.call_raw::<u32>("Fail_fail", None).await.unwrap_err();
+        let Error::Archive(ArchiveError::CallFailed { function, reason }) = err else {
+            panic!("expected a failed archive call, got {err:?}");
+        };
+        assert_eq!(function, "Fail_fail");
+        assert_eq!(reason, "boom");
+    }
+}
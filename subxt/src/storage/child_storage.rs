@@ -0,0 +1,419 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use crate::{
+    client::OnlineClientT,
+    error::Error,
+    rpc::types::{ReadProof, StorageData, StorageKey},
+    Config,
+};
+use codec::Decode;
+use derivative::Derivative;
+use std::{future::Future, marker::PhantomData};
+
+/// The prefix that the storage key of every default child trie is given in the main trie.
+const DEFAULT_CHILD_STORAGE_KEY_PREFIX: &[u8] = b":child_storage:default:";
+
+/// Identifies a child trie. This mirrors `ChildInfo` from `sp_storage`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ChildInfo {
+    /// A default child trie, identified by its unprefixed storage key.
+    Default(Vec<u8>),
+}
+
+impl ChildInfo {
+    /// A default child trie with the given (unprefixed) storage key. For instance, the
+    /// trie ID of a contract in `pallet_contracts`.
+    pub fn new_default(storage_key: impl Into<Vec<u8>>) -> Self {
+        ChildInfo::Default(storage_key.into())
+    }
+
+    /// Parse the prefixed storage key of a child trie, as it appears in the main trie.
+    /// Returns `None` if the key is not one that a child trie would be stored under.
+    pub fn from_prefixed_storage_key(key: &[u8]) -> Option<Self> {
+        key.strip_prefix(DEFAULT_CHILD_STORAGE_KEY_PREFIX)
+            .map(Self::new_default)
+    }
+
+    /// The unprefixed storage key of this child trie.
+    pub fn storage_key(&self) -> &[u8] {
+        match self {
+            ChildInfo::Default(key) => key,
+        }
+    }
+
+    /// The storage key that this child trie is found under in the main trie. This is
+    /// what the `childstate_*` RPC methods expect to be given.
+    pub fn prefixed_storage_key(&self) -> Vec<u8> {
+        match self {
+            ChildInfo::Default(key) => [DEFAULT_CHILD_STORAGE_KEY_PREFIX, key].concat(),
+        }
+    }
+}
+
+/// Query the storage of a child trie at some block. Child tries are not described by
+/// the metadata, and so values are handed back as raw bytes or decoded using
+/// [`codec::Decode`].
+#[derive(Derivative)]
+#[derivative(Clone(bound = "Client: Clone"))]
+pub struct ChildStorage<T: Config, Client> {
+    client: Client,
+    block_hash: T::Hash,
+    child_storage_key: Vec<u8>,
+    _marker: PhantomData<T>,
+}
+
+impl<T: Config, Client> ChildStorage<T, Client> {
+    /// Create a new [`ChildStorage`]
+    pub(crate) fn new(client: Client, block_hash: T::Hash, child_info: &ChildInfo) -> Self {
+        Self {
+            client,
+            block_hash,
+            child_storage_key: child_info.prefixed_storage_key(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T, Client> ChildStorage<T, Client>
+where
+    T: Config,
+    Client: OnlineClientT<T>,
+{
+    /// Fetch the raw encoded value at the key given.
+    pub fn fetch_raw<'key>(
+        &self,
+        key: &'key [u8],
+    ) -> impl Future<Output = Result<Option<Vec<u8>>, Error>> + 'key {
+        let client = self.client.clone();
+        let block_hash = self.block_hash;
+        let child_storage_key = self.child_storage_key.clone();
+        // Ensure that the returned future doesn't have a lifetime tied to self:
+        async move {
+            let data = client
+                .rpc()
+                .child_storage(&child_storage_key, key, Some(block_hash))
+                .await?;
+            Ok(data.map(|d| d.0))
+        }
+    }
+
+    /// Fetch the value at the key given, decoding it into the type provided.
+    pub fn fetch<'key, V: Decode + 'key>(
+        &self,
+        key: &'key [u8],
+    ) -> impl Future<Output = Result<Option<V>, Error>> + 'key {
+        let fetch = self.fetch_raw(key);
+        async move {
+            let Some(bytes) = fetch.await? else {
+                return Ok(None);
+            };
+            Ok(Some(V::decode(&mut &*bytes)?))
+        }
+    }
+
+    /// Fetch the hash of the value at the key given.
+    pub fn fetch_hash<'key>(
+        &self,
+        key: &'key [u8],
+    ) -> impl Future<Output = Result<Option<T::Hash>, Error>> + 'key {
+        let client = self.client.clone();
+        let block_hash = self.block_hash;
+        let child_storage_key = self.child_storage_key.clone();
+        async move {
+            let hash = client
+                .rpc()
+                .child_storage_hash(&child_storage_key, key, Some(block_hash))
+                .await?;
+            Ok(hash)
+        }
+    }
+
+    /// Fetch up to `count` keys starting with the given prefix, in lexicographic order.
+    ///
+    /// Supports pagination by passing a value to `start_key`.
+    pub fn fetch_keys<'key>(
+        &self,
+        prefix: &'key [u8],
+        count: u32,
+        start_key: Option<&'key [u8]>,
+    ) -> impl Future<Output = Result<Vec<StorageKey>, Error>> + 'key {
+        let client = self.client.clone();
+        let block_hash = self.block_hash;
+        let child_storage_key = self.child_storage_key.clone();
+        async move {
+            let keys = client
+                .rpc()
+                .child_storage_keys_paged(
+                    &child_storage_key,
+                    prefix,
+                    count,
+                    start_key,
+                    Some(block_hash),
+                )
+                .await?;
+            Ok(keys)
+        }
+    }
+
+    /// Fetch a proof that the given keys have the values that they do in this child trie.
+    pub fn read_proof<'key>(
+        &self,
+        keys: impl IntoIterator<Item = &'key [u8]> + 'key,
+    ) -> impl Future<Output = Result<ReadProof<T::Hash>, Error>> + 'key {
+        let client = self.client.clone();
+        let block_hash = self.block_hash;
+        let child_storage_key = self.child_storage_key.clone();
+        async move {
+            let proof = client
+                .rpc()
+                .child_read_proof(&child_storage_key, keys, Some(block_hash))
+                .await?;
+            Ok(proof)
+        }
+    }
+
+    /// Returns an iterator over the keys and raw values in this child trie which start
+    /// with the given prefix (which may be empty, to iterate over everything). Keys are
+    /// fetched `page_size` at a time.
+    pub fn iter(&self, prefix: impl Into<Vec<u8>>, page_size: u32) -> ChildKeyIter<T, Client> {
+        ChildKeyIter {
+            client: self.clone(),
+            prefix: prefix.into(),
+            count: page_size,
+            start_key: None,
+            buffer: Default::default(),
+        }
+    }
+}
+
+/// Iterates over the key value pairs in a child trie.
+pub struct ChildKeyIter<T: Config, Client> {
+    client: ChildStorage<T, Client>,
+    prefix: Vec<u8>,
+    count: u32,
+    start_key: Option<StorageKey>,
+    buffer: Vec<(StorageKey, StorageData)>,
+}
+
+impl<T, Client> ChildKeyIter<T, Client>
+where
+    T: Config,
+    Client: OnlineClientT<T>,
+{
+    /// Returns the next key and raw value from the child trie.
+    pub async fn next(&mut self) -> Result<Option<(StorageKey, Vec<u8>)>, Error> {
+        loop {
+            if let Some((k, v)) = self.buffer.pop() {
+                return Ok(Some((k, v.0)));
+            }
+
+            let start_key = self.start_key.take();
+            let keys = self
+                .client
+                .fetch_keys(&self.prefix, self.count, start_key.as_ref().map(|k| &*k.0))
+                .await?;
+
+            if keys.is_empty() {
+                return Ok(None);
+            }
+
+            self.start_key = keys.last().cloned();
+
+            let values = self
+                .client
+                .client
+                .rpc()
+                .child_storage_entries(
+                    &self.client.child_storage_key,
+                    keys.iter().map(|k| &*k.0),
+                    Some(self.client.block_hash),
+                )
+                .await?;
+
+            // Values are popped off the end of the buffer, so push them in reverse to
+            // hand them back in the order that the keys were given.
+            for (k, v) in keys.into_iter().zip(values).rev() {
+                if let Some(v) = v {
+                    self.buffer.push((k, v));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::substrate::H256,
+        rpc::{
+            mock_rpc_client::{to_raw, MockRpcClient},
+            types::{Bytes, RuntimeVersion},
+        },
+        Metadata, OnlineClient, SubstrateConfig,
+    };
+    use codec::Encode;
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+
+    type Requests = Arc<Mutex<Vec<(String, Value)>>>;
+
+    // The child trie holds the value `n` (as a u32) at each of the keys `[n]` for n from
+    // 0 to 4.
+    fn child_trie_value(key: &[u8]) -> Option<Bytes> {
+        match key {
+            [n] if *n < 5 => Some(Bytes((*n as u32).encode())),
+            _ => None,
+        }
+    }
+
+    // A client which records the requests made to it, and answers `childstate_*` requests
+    // about the child trie above.
+    fn client(requests: Requests) -> OnlineClient<SubstrateConfig> {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_tiny.scale").unwrap();
+        let metadata = Metadata::decode(&mut &*bytes).unwrap();
+
+        let rpc = MockRpcClient::new().on_request(move |method, params| {
+            let params: Value = serde_json::from_str(params.unwrap().get()).unwrap();
+            requests
+                .lock()
+                .unwrap()
+                .push((method.to_owned(), params.clone()));
+            let bytes = |value: &Value| -> Vec<u8> {
+                serde_json::from_value::<Bytes>(value.clone()).unwrap().0
+            };
+            match method {
+                "childstate_getStorage" => Ok(to_raw(child_trie_value(&bytes(&params[1])))),
+                "childstate_getKeysPaged" => {
+                    let prefix = bytes(&params[1]);
+                    let count = params[2].as_u64().unwrap() as usize;
+                    let start_key = (!params[3].is_null()).then(|| bytes(&params[3]));
+                    let keys: Vec<Bytes> = (0u8..5)
+                        .map(|n| vec![n])
+                        .filter(|k| k.starts_with(&prefix))
+                        .filter(|k| start_key.as_ref().map_or(true, |start| k > start))
+                        .take(count)
+                        .map(Bytes)
+                        .collect();
+                    Ok(to_raw(keys))
+                }
+                "childstate_getStorageEntries" => {
+                    let values: Vec<Option<Bytes>> = params[1]
+                        .as_array()
+                        .unwrap()
+                        .iter()
+                        .map(|key| child_trie_value(&bytes(key)))
+                        .collect();
+                    Ok(to_raw(values))
+                }
+                _ => panic!("unexpected method {method}"),
+            }
+        });
+
+        let runtime_version = RuntimeVersion {
+            spec_version: 1,
+            transaction_version: 1,
+            other: Default::default(),
+        };
+        OnlineClient::from_rpc_client_with(H256::zero(), runtime_version, metadata, Arc::new(rpc))
+            .unwrap()
+    }
+
+    fn child_storage(
+        requests: Requests,
+    ) -> ChildStorage<SubstrateConfig, OnlineClient<SubstrateConfig>> {
+        let child_info = ChildInfo::new_default(b"trie_id".to_vec());
+        ChildStorage::new(client(requests), H256::repeat_byte(1), &child_info)
+    }
+
+    // The prefixed storage key of the child trie, as it's handed to the node.
+    fn child_storage_key() -> Value {
+        json!(format!(
+            "0x{}",
+            hex::encode(b":child_storage:default:trie_id")
+        ))
+    }
+
+    #[tokio::test]
+    async fn values_are_fetched_from_the_child_trie() {
+        let requests = Requests::default();
+        let storage = child_storage(requests.clone());
+
+        assert_eq!(storage.fetch_raw(&[3]).await.unwrap(), Some(3u32.encode()));
+        assert_eq!(storage.fetch::<u32>(&[4]).await.unwrap(), Some(4));
+        assert_eq!(storage.fetch::<u32>(&[9]).await.unwrap(), None);
+
+        let requests = requests.lock().unwrap();
+        let block_hash = json!(H256::repeat_byte(1));
+        assert_eq!(
+            requests[0],
+            (
+                "childstate_getStorage".to_owned(),
+                json!([child_storage_key(), "0x03", block_hash])
+            )
+        );
+        assert_eq!(requests.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn iteration_pages_through_the_child_trie() {
+        let requests = Requests::default();
+        let storage = child_storage(requests.clone());
+
+        // Every value is handed back in order, across pages of two keys.
+        let mut iter = storage.iter(Vec::new(), 2);
+        let mut items = Vec::new();
+        while let Some((key, value)) = iter.next().await.unwrap() {
+            items.push((key.0, u32::decode(&mut &*value).unwrap()));
+        }
+        let expected: Vec<_> = (0u8..5).map(|n| (vec![n], n as u32)).collect();
+        assert_eq!(items, expected);
+
+        // Each page starts after the last key of the one before, until a page is empty.
+        let requests = requests.lock().unwrap();
+        let block_hash = json!(H256::repeat_byte(1));
+        let key_pages: Vec<Value> = requests
+            .iter()
+            .filter(|(method, _)| method == "childstate_getKeysPaged")
+            .map(|(_, params)| params.clone())
+            .collect();
+        let expected: Vec<Value> = [Value::Null, json!("0x01"), json!("0x03"), json!("0x04")]
+            .into_iter()
+            .map(|start_key| json!([child_storage_key(), "0x", 2, start_key, block_hash]))
+            .collect();
+        assert_eq!(key_pages, expected);
+
+        // The values of each page of keys are fetched together.
+        let (method, params) = &requests[1];
+        assert_eq!(method, "childstate_getStorageEntries");
+        assert_eq!(
+            *params,
+            json!([child_storage_key(), ["0x00", "0x01"], block_hash])
+        );
+    }
+
+    #[tokio::test]
+    async fn iteration_is_limited_to_the_prefix() {
+        let storage = child_storage(Requests::default());
+
+        let mut iter = storage.iter(vec![2], 2);
+        let (key, _) = iter.next().await.unwrap().unwrap();
+        assert_eq!(key.0, vec![2]);
+        assert!(iter.next().await.unwrap().is_none());
+    }
+
+    #[test]
+    fn child_info_prefixed_storage_key_roundtrips() {
+        let child_info = ChildInfo::new_default(b"trie_id".to_vec());
+        let prefixed = child_info.prefixed_storage_key();
+        assert_eq!(prefixed, b":child_storage:default:trie_id".to_vec());
+        assert_eq!(
+            ChildInfo::from_prefixed_storage_key(&prefixed),
+            Some(child_info)
+        );
+        assert_eq!(ChildInfo::from_prefixed_storage_key(b"trie_id"), None);
+    }
+}
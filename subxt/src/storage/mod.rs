@@ -4,16 +4,23 @@
 
 //! Types associated with accessing and working with storage items.
 
+mod child_storage;
 mod storage_address;
 mod storage_client;
 mod storage_type;
 
 pub mod utils;
 
+pub use child_storage::{ChildInfo, ChildKeyIter, ChildStorage};
+
 pub use storage_client::StorageClient;
 
 pub use storage_type::{KeyIter, Storage};
 
+pub(crate) use storage_type::{
+    decode_storage_with_metadata, lookup_entry_details, validate_storage_address,
+};
+
 // Re-export as this is used in the public API in this module:
 pub use crate::rpc::types::StorageKey;
 
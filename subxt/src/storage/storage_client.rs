@@ -20,6 +20,7 @@ use std::{future::Future, marker::PhantomData};
 #[derivative(Clone(bound = "Client: Clone"))]
 pub struct StorageClient<T, Client> {
     client: Client,
+    use_archive_rpc: bool,
     _marker: PhantomData<T>,
 }
 
@@ -28,9 +29,18 @@ impl<T, Client> StorageClient<T, Client> {
     pub fn new(client: Client) -> Self {
         Self {
             client,
+            use_archive_rpc: false,
             _marker: PhantomData,
         }
     }
+
+    /// Route the requests made by any [`Storage`] obtained from this client through the
+    /// `archive_unstable_*` RPC methods rather than the legacy `state_*` ones. Use this
+    /// to query blocks which are too old for the node to answer otherwise.
+    pub fn use_archive_rpc(mut self, use_archive_rpc: bool) -> Self {
+        self.use_archive_rpc = use_archive_rpc;
+        self
+    }
 }
 
 impl<T, Client> StorageClient<T, Client>
@@ -74,7 +84,7 @@ where
 {
     /// Obtain storage at some block hash.
     pub fn at(&self, block_hash: T::Hash) -> Storage<T, Client> {
-        Storage::new(self.client.clone(), block_hash)
+        Storage::new(self.client.clone(), block_hash).use_archive_rpc(self.use_archive_rpc)
     }
 
     /// Obtain storage at the latest block hash.
@@ -84,15 +94,20 @@ where
         // Clone and pass the client in like this so that we can explicitly
         // return a Future that's Send + 'static, rather than tied to &self.
         let client = self.client.clone();
+        let use_archive_rpc = self.use_archive_rpc;
         async move {
-            // get the hash for the latest block and use that.
-            let block_hash = client
-                .rpc()
-                .block_hash(None)
-                .await?
-                .expect("didn't pass a block number; qed");
+            // use the block that the client is pinned to, or failing
+            // that, get the hash for the latest block and use that.
+            let block_hash = match client.pinned_block_hash() {
+                Some(hash) => hash,
+                None => client
+                    .rpc()
+                    .block_hash(None)
+                    .await?
+                    .expect("didn't pass a block number; qed"),
+            };
 
-            Ok(Storage::new(client, block_hash))
+            Ok(Storage::new(client, block_hash).use_archive_rpc(use_archive_rpc))
         }
     }
 }
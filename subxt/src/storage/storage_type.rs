@@ -2,12 +2,17 @@
 // This file is dual-licensed as Apache-2.0 or GPL-3.0.
 // see LICENSE for license details.
 
-use super::storage_address::{StorageAddress, Yes};
+use super::{
+    child_storage::{ChildInfo, ChildStorage},
+    storage_address::{StorageAddress, Yes},
+};
 use crate::{
     client::OnlineClientT,
-    error::{Error, MetadataError},
+    error::{ArchiveError, Error, MetadataError},
     metadata::{DecodeWithMetadata, Metadata},
-    rpc::types::{StorageData, StorageKey},
+    rpc::types::{
+        ArchiveStorageItem, ArchiveStorageQuery, ArchiveStorageQueryType, StorageData, StorageKey,
+    },
     Config,
 };
 use derivative::Derivative;
@@ -20,6 +25,8 @@ use subxt_metadata::{PalletMetadata, StorageEntryMetadata, StorageEntryType};
 pub struct Storage<T: Config, Client> {
     client: Client,
     block_hash: T::Hash,
+    use_archive_rpc: bool,
+    metadata: Option<Metadata>,
     _marker: PhantomData<T>,
 }
 
@@ -29,9 +36,26 @@ impl<T: Config, Client> Storage<T, Client> {
         Self {
             client,
             block_hash,
+            use_archive_rpc: false,
+            metadata: None,
             _marker: PhantomData,
         }
     }
+
+    /// Make storage requests using the `archive_unstable_*` RPC methods rather than the
+    /// legacy `state_*` ones. Archive nodes can answer these for any block, however old.
+    pub fn use_archive_rpc(mut self, use_archive_rpc: bool) -> Self {
+        self.use_archive_rpc = use_archive_rpc;
+        self
+    }
+
+    /// Decode storage values using the given metadata rather than the client's current
+    /// metadata. Use this to work with the storage of blocks that were produced under an
+    /// older runtime.
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
 }
 
 impl<T, Client> Storage<T, Client>
@@ -39,6 +63,24 @@ where
     T: Config,
     Client: OnlineClientT<T>,
 {
+    // The metadata to decode storage values with.
+    fn metadata(&self) -> Metadata {
+        match &self.metadata {
+            Some(metadata) => metadata.clone(),
+            None => self.client.metadata(),
+        }
+    }
+
+    /// Query the storage of the given child trie at this block.
+    ///
+    /// Child tries are not described by the metadata, so any metadata given to
+    /// [`Storage::with_metadata()`] plays no part here. Child storage is always queried
+    /// using the legacy `childstate_*` RPC methods, whether or not
+    /// [`Storage::use_archive_rpc()`] was set.
+    pub fn child(&self, child_info: &ChildInfo) -> ChildStorage<T, Client> {
+        ChildStorage::new(self.client.clone(), self.block_hash, child_info)
+    }
+
     /// Fetch the raw encoded value at the address/key given.
     pub fn fetch_raw<'address>(
         &self,
@@ -46,9 +88,15 @@ where
     ) -> impl Future<Output = Result<Option<Vec<u8>>, Error>> + 'address {
         let client = self.client.clone();
         let block_hash = self.block_hash;
+        let use_archive_rpc = self.use_archive_rpc;
         // Ensure that the returned future doesn't have a lifetime tied to api.storage(),
         // which is a temporary thing we'll be throwing away quickly:
         async move {
+            if use_archive_rpc {
+                let query = ArchiveStorageQuery::new(key, ArchiveStorageQueryType::Value);
+                let items = archive_storage(&client, block_hash, query).await?;
+                return Ok(items.into_iter().find_map(|item| item.value).map(|v| v.0));
+            }
             let data = client.rpc().storage(key, Some(block_hash)).await?;
             Ok(data.map(|d| d.0))
         }
@@ -93,7 +141,7 @@ where
     {
         let client = self.clone();
         async move {
-            let metadata = client.client.metadata();
+            let metadata = client.metadata();
             let (pallet, entry) =
                 lookup_entry_details(address.pallet_name(), address.entry_name(), &metadata)?;
 
@@ -131,7 +179,7 @@ where
             if let Some(data) = client.fetch(address).await? {
                 Ok(data)
             } else {
-                let metadata = client.client.metadata();
+                let metadata = client.metadata();
                 let (_pallet_metadata, storage_entry) =
                     lookup_entry_details(pallet_name, entry_name, &metadata)?;
 
@@ -155,7 +203,35 @@ where
     ) -> impl Future<Output = Result<Vec<StorageKey>, Error>> + 'address {
         let client = self.client.clone();
         let block_hash = self.block_hash;
+        let use_archive_rpc = self.use_archive_rpc;
         async move {
+            if use_archive_rpc {
+                // There's no query for keys alone, so ask for hashes (which are the smallest
+                // thing we can ask for) and page through them until we have `count` keys,
+                // rather than asking the node for every descendant at once.
+                let mut keys = Vec::new();
+                let mut start_key = start_key.map(|k| k.to_vec());
+                while keys.len() < count as usize {
+                    let mut query =
+                        ArchiveStorageQuery::new(key, ArchiveStorageQueryType::DescendantsHashes);
+                    query.pagination_start_key = start_key.take().map(Into::into);
+                    let items = archive_storage(&client, block_hash, query).await?;
+
+                    let Some(last) = items.last() else {
+                        break;
+                    };
+                    start_key = Some(last.key.0.clone());
+
+                    let remaining = count as usize - keys.len();
+                    keys.extend(
+                        items
+                            .into_iter()
+                            .take(remaining)
+                            .map(|item| StorageKey(item.key.0)),
+                    );
+                }
+                return Ok(keys);
+            }
             let keys = client
                 .rpc()
                 .storage_keys_paged(key, count, start_key, Some(block_hash))
@@ -206,7 +282,7 @@ where
         let client = self.clone();
         let block_hash = self.block_hash;
         async move {
-            let metadata = client.client.metadata();
+            let metadata = client.metadata();
             let (pallet, entry) =
                 lookup_entry_details(address.pallet_name(), address.entry_name(), &metadata)?;
 
@@ -268,6 +344,27 @@ where
                     &self.metadata,
                 )?;
                 return Ok(Some((k, val)));
+            } else if self.client.use_archive_rpc {
+                // The archive RPC hands back keys and values together, so we don't
+                // need to fetch the keys first.
+                let mut query = ArchiveStorageQuery::new(
+                    &*self.address_root_bytes,
+                    ArchiveStorageQueryType::DescendantsValues,
+                );
+                query.pagination_start_key = self.start_key.take().map(|k| k.0.into());
+                let items = archive_storage(&self.client.client, self.block_hash, query).await?;
+
+                let Some(last) = items.last() else {
+                    return Ok(None);
+                };
+                self.start_key = Some(StorageKey(last.key.0.clone()));
+
+                for item in items {
+                    if let Some(value) = item.value {
+                        self.buffer
+                            .push((StorageKey(item.key.0), StorageData(value.0)));
+                    }
+                }
             } else {
                 let start_key = self.start_key.take();
                 let keys = self
@@ -304,6 +401,23 @@ where
     }
 }
 
+// Make a single `archive_unstable_storage` query, handing back the items found.
+async fn archive_storage<T: Config, Client: OnlineClientT<T>>(
+    client: &Client,
+    block_hash: T::Hash,
+    query: ArchiveStorageQuery,
+) -> Result<Vec<ArchiveStorageItem>, Error> {
+    let res = client
+        .rpc()
+        .archive_unstable_storage(block_hash, vec![query], None)
+        .await?;
+    // With only one query, the node either processes it or discards it.
+    if res.discarded_items > 0 {
+        return Err(ArchiveError::QueryDiscarded.into());
+    }
+    Ok(res.items)
+}
+
 /// Validate a storage address against the metadata.
 pub(crate) fn validate_storage_address<Address: StorageAddress>(
     address: &Address,
@@ -316,7 +430,7 @@ pub(crate) fn validate_storage_address<Address: StorageAddress>(
 }
 
 /// Return details about the given storage entry.
-fn lookup_entry_details<'a>(
+pub(crate) fn lookup_entry_details<'a>(
     pallet_name: &str,
     entry_name: &str,
     metadata: &'a Metadata,
@@ -355,7 +469,7 @@ fn return_type_from_storage_entry_type(entry: &StorageEntryType) -> u32 {
 }
 
 /// Given some bytes, a pallet and storage name, decode the response.
-fn decode_storage_with_metadata<T: DecodeWithMetadata>(
+pub(crate) fn decode_storage_with_metadata<T: DecodeWithMetadata>(
     bytes: &mut &[u8],
     metadata: &Metadata,
     storage_metadata: &StorageEntryMetadata,
@@ -365,3 +479,157 @@ fn decode_storage_with_metadata<T: DecodeWithMetadata>(
     let val = T::decode_with_metadata(bytes, return_ty, metadata)?;
     Ok(val)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::substrate::H256,
+        rpc::{
+            mock_rpc_client::{to_raw, MockRpcClient},
+            types::{Bytes, RuntimeVersion},
+        },
+        OnlineClient, SubstrateConfig,
+    };
+    use codec::Decode;
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+
+    type Requests = Arc<Mutex<Vec<(String, Value)>>>;
+
+    // A client which records the requests made to it. Archive storage queries are answered
+    // by `archive_storage_result()`, and legacy ones with the value `[1]`.
+    fn client(requests: Requests, discarded_items: usize) -> OnlineClient<SubstrateConfig> {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_tiny.scale").unwrap();
+        let metadata = Metadata::decode(&mut &*bytes).unwrap();
+
+        let rpc = MockRpcClient::new().on_request(move |method, params| {
+            let params: Value = serde_json::from_str(params.unwrap().get()).unwrap();
+            requests
+                .lock()
+                .unwrap()
+                .push((method.to_owned(), params.clone()));
+            match method {
+                "state_getStorage" => Ok(to_raw(Bytes(vec![1]))),
+                "archive_unstable_storage" => Ok(to_raw(archive_storage_result(
+                    &params[1][0],
+                    discarded_items,
+                ))),
+                _ => panic!("unexpected method {method}"),
+            }
+        });
+
+        let runtime_version = RuntimeVersion {
+            spec_version: 1,
+            transaction_version: 1,
+            other: Default::default(),
+        };
+        OnlineClient::from_rpc_client_with(H256::zero(), runtime_version, metadata, Arc::new(rpc))
+            .unwrap()
+    }
+
+    // The node stores the value `[n]` under each of the keys `[1, n]` for n from 0 to 4,
+    // and hands back at most two items in response to a query.
+    fn archive_storage_result(query: &Value, discarded_items: usize) -> Value {
+        if discarded_items > 0 {
+            return json!({ "result": [], "discardedItems": discarded_items });
+        }
+
+        let key: Bytes = serde_json::from_value(query["key"].clone()).unwrap();
+        let start_key: Option<Bytes> =
+            serde_json::from_value(query["paginationStartKey"].clone()).unwrap();
+        let items: Vec<Value> = (0u8..5)
+            .map(|n| vec![1, n])
+            .filter(|k| k.starts_with(&key.0))
+            .filter(|k| start_key.as_ref().map_or(true, |start| *k > start.0))
+            .take(2)
+            .map(|k| match query["type"].as_str().unwrap() {
+                "value" => json!({ "key": Bytes(k.clone()), "value": Bytes(vec![k[1]]) }),
+                "descendantsHashes" => json!({ "key": Bytes(k), "hash": Bytes(vec![0; 32]) }),
+                ty => panic!("unexpected query type {ty}"),
+            })
+            .collect();
+        json!({ "result": items, "discardedItems": 0 })
+    }
+
+    fn methods(requests: &Requests) -> Vec<String> {
+        let requests = requests.lock().unwrap();
+        requests.iter().map(|(method, _)| method.clone()).collect()
+    }
+
+    #[tokio::test]
+    async fn fetches_are_routed_by_use_archive_rpc() {
+        let requests = Requests::default();
+        let storage = Storage::new(client(requests.clone(), 0), H256::zero());
+
+        let value = storage.fetch_raw(&[1, 3]).await.unwrap();
+        assert_eq!(value, Some(vec![1]));
+        assert_eq!(methods(&requests), vec!["state_getStorage"]);
+
+        requests.lock().unwrap().clear();
+        let value = storage
+            .use_archive_rpc(true)
+            .fetch_raw(&[1, 3])
+            .await
+            .unwrap();
+        assert_eq!(value, Some(vec![3]));
+        assert_eq!(methods(&requests), vec!["archive_unstable_storage"]);
+
+        let params = &requests.lock().unwrap()[0].1;
+        assert_eq!(params[0], json!(H256::zero()));
+        assert_eq!(params[1], json!([{ "key": "0x0103", "type": "value" }]));
+        assert_eq!(params[2], Value::Null);
+    }
+
+    #[tokio::test]
+    async fn archive_key_fetches_page_through_descendants_hashes() {
+        let requests = Requests::default();
+        let storage = Storage::new(client(requests.clone(), 0), H256::zero()).use_archive_rpc(true);
+
+        // Three keys take two pages, the second of which starts after the first.
+        let keys = storage.fetch_keys(&[1], 3, None).await.unwrap();
+        assert_eq!(
+            keys,
+            vec![
+                StorageKey(vec![1, 0]),
+                StorageKey(vec![1, 1]),
+                StorageKey(vec![1, 2])
+            ]
+        );
+        let queries: Vec<Value> = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(_, params)| params[1][0].clone())
+            .collect();
+        assert_eq!(
+            queries,
+            vec![
+                json!({ "key": "0x01", "type": "descendantsHashes" }),
+                json!({
+                    "key": "0x01",
+                    "type": "descendantsHashes",
+                    "paginationStartKey": "0x0101",
+                }),
+            ]
+        );
+
+        // Paging stops once the node runs out of keys.
+        requests.lock().unwrap().clear();
+        let keys = storage.fetch_keys(&[1], 10, Some(&[1, 2])).await.unwrap();
+        assert_eq!(keys, vec![StorageKey(vec![1, 3]), StorageKey(vec![1, 4])]);
+        assert_eq!(methods(&requests).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn discarded_archive_queries_are_errors() {
+        let storage =
+            Storage::new(client(Requests::default(), 1), H256::zero()).use_archive_rpc(true);
+
+        let err = storage.fetch_raw(&[1, 3]).await.unwrap_err();
+        assert!(
+            matches!(err, Error::Archive(ArchiveError::QueryDiscarded)),
+            "{err:?}"
+        );
+    }
+}
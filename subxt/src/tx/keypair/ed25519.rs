@@ -0,0 +1,150 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! An ed25519 keypair.
+
+use super::{seed_from_phrase, DeriveJunction, KeypairError, SecretUri};
+use crate::{
+    tx::Signer,
+    utils::{AccountId32, MultiSignature},
+    Config,
+};
+use codec::Encode;
+use ed25519_zebra::{Signature, SigningKey, VerificationKey};
+
+/// An ed25519 keypair. This implements [`Signer`] for any [`Config`] whose accounts are
+/// [`AccountId32`]s and whose signatures can be created from a [`MultiSignature`].
+#[derive(Clone)]
+pub struct Keypair {
+    seed: [u8; 32],
+    signing_key: SigningKey,
+    account_id: AccountId32,
+}
+
+impl Keypair {
+    /// Create a keypair from a [`SecretUri`], deriving it along any junctions that it gives.
+    /// This fails if any of the junctions are soft, since ed25519 keypairs only support
+    /// hard derivation.
+    pub fn from_uri(uri: &SecretUri) -> Result<Self, KeypairError> {
+        let seed = seed_from_phrase(uri.phrase(), uri.password())?;
+        Self::from_seed(seed).derive(uri.junctions().iter().copied())
+    }
+
+    /// Create a keypair from a BIP-39 mnemonic phrase and an optional password.
+    pub fn from_phrase(phrase: &str, password: Option<&str>) -> Result<Self, KeypairError> {
+        let seed = seed_from_phrase(phrase, password)?;
+        Ok(Self::from_seed(seed))
+    }
+
+    /// Create a keypair from a 32 byte seed.
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        let signing_key = SigningKey::from(seed);
+        let account_id = AccountId32(VerificationKey::from(&signing_key).into());
+        Keypair {
+            seed,
+            signing_key,
+            account_id,
+        }
+    }
+
+    /// Derive a new keypair from this one along the given junctions. This fails if any of
+    /// the junctions are soft, since ed25519 keypairs only support hard derivation.
+    pub fn derive(
+        &self,
+        junctions: impl IntoIterator<Item = DeriveJunction>,
+    ) -> Result<Self, KeypairError> {
+        let mut seed = self.seed;
+        for junction in junctions {
+            let DeriveJunction::Hard(chain_code) = junction else {
+                return Err(KeypairError::SoftDerivationNotSupported);
+            };
+            seed = ("Ed25519HDKD", seed, chain_code).using_encoded(sp_core_hashing::blake2_256);
+        }
+        Ok(Self::from_seed(seed))
+    }
+
+    /// The public key of this keypair.
+    pub fn public_key(&self) -> [u8; 32] {
+        self.account_id.0
+    }
+
+    /// The account ID of this keypair, which is its public key.
+    pub fn account_id(&self) -> &AccountId32 {
+        &self.account_id
+    }
+
+    /// Sign a message, returning the 64 byte signature.
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        self.signing_key.sign(message).into()
+    }
+
+    /// Does the given signature of the message belong to the keypair with the given
+    /// public key?
+    pub fn verify(signature: &[u8; 64], message: &[u8], public_key: &[u8; 32]) -> bool {
+        let Ok(public_key) = VerificationKey::try_from(*public_key) else {
+            return false;
+        };
+        public_key
+            .verify(&Signature::from(*signature), message)
+            .is_ok()
+    }
+}
+
+// Only the public half of the keypair is shown, so that secrets don't end up in logs.
+impl std::fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Keypair")
+            .field("account_id", &self.account_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Signer<T> for Keypair
+where
+    T: Config<AccountId = AccountId32>,
+    T::Signature: From<MultiSignature>,
+{
+    fn account_id(&self) -> &T::AccountId {
+        &self.account_id
+    }
+
+    fn address(&self) -> T::Address {
+        self.account_id.clone().into()
+    }
+
+    fn sign(&self, signer_payload: &[u8]) -> T::Signature {
+        MultiSignature::Ed25519(Keypair::sign(self, signer_payload)).into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn keypair(uri: &str) -> Result<Keypair, KeypairError> {
+        Keypair::from_uri(&uri.parse().unwrap())
+    }
+
+    #[test]
+    fn dev_accounts_match_substrate() {
+        // The well known ed25519 public key of the development account "Alice".
+        assert_eq!(
+            hex::encode(keypair("//Alice").unwrap().public_key()),
+            "88dc3417d5058ec4b4503e0c12ea1a0a89be200fe98922423d4334014fa6b0ee"
+        );
+    }
+
+    #[test]
+    fn derivation_and_signing() {
+        assert_eq!(
+            keypair("//Alice/soft").unwrap_err(),
+            KeypairError::SoftDerivationNotSupported
+        );
+
+        let alice = keypair("//Alice").unwrap();
+        let signature = alice.sign(b"hello");
+        assert!(Keypair::verify(&signature, b"hello", &alice.public_key()));
+        assert!(!Keypair::verify(&signature, b"bye", &alice.public_key()));
+    }
+}
@@ -0,0 +1,94 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! Pure Rust sr25519 and ed25519 keypairs which implement [`super::Signer`], and so can sign
+//! transactions without needing `sp_core` or `sp_runtime` (see the `substrate-compat` feature).
+//!
+//! Keypairs are derived from a [`SecretUri`] in the same way that Substrate derives them, and
+//! so development accounts and accounts created with Substrate tooling have the same keys here.
+//! The sr25519 keypairs are enabled by the `sr25519` feature, and the ed25519 keypairs by the
+//! `ed25519` feature.
+
+mod secret_uri;
+
+#[cfg(feature = "ed25519")]
+pub mod ed25519;
+#[cfg(feature = "sr25519")]
+pub mod sr25519;
+
+pub use secret_uri::{DeriveJunction, SecretUri, SecretUriError, DEV_PHRASE};
+
+use hmac::Hmac;
+use sha2::Sha512;
+
+/// An error creating a keypair.
+#[derive(Clone, Debug, Eq, thiserror::Error, PartialEq)]
+#[non_exhaustive]
+pub enum KeypairError {
+    /// The hex seed is not 32 bytes long, or isn't valid hex.
+    #[error("Invalid seed (should be 32 bytes of hex, prefixed with 0x)")]
+    InvalidSeed,
+    /// The phrase is not a valid BIP-39 mnemonic phrase.
+    #[error("Invalid mnemonic phrase: {0}")]
+    InvalidPhrase(String),
+    /// Soft derivation was asked for, but this kind of keypair only supports hard derivation.
+    #[error("Soft derivation is not supported for this kind of keypair")]
+    SoftDerivationNotSupported,
+}
+
+/// The 32 byte seed given by the phrase of a secret URI. This is either the phrase itself,
+/// if it's `0x` prefixed hex, or otherwise the seed given by the phrase as a BIP-39 mnemonic.
+/// Following Substrate, the password is ignored for hex seeds.
+fn seed_from_phrase(phrase: &str, password: Option<&str>) -> Result<[u8; 32], KeypairError> {
+    if let Some(hex_seed) = phrase.strip_prefix("0x") {
+        let mut seed = [0u8; 32];
+        hex::decode_to_slice(hex_seed, &mut seed).map_err(|_| KeypairError::InvalidSeed)?;
+        return Ok(seed);
+    }
+
+    let mnemonic = bip39::Mnemonic::from_phrase(phrase, bip39::Language::English)
+        .map_err(|e| KeypairError::InvalidPhrase(e.to_string()))?;
+    Ok(seed_from_entropy(
+        mnemonic.entropy(),
+        password.unwrap_or(""),
+    ))
+}
+
+/// Substrate derives seeds from the entropy of a mnemonic, rather than from the mnemonic
+/// itself as BIP-39 specifies, and so we can't use the seed that BIP-39 gives us.
+fn seed_from_entropy(entropy: &[u8], password: &str) -> [u8; 32] {
+    let salt = format!("mnemonic{password}");
+    let mut seed = [0u8; 64];
+    pbkdf2::pbkdf2::<Hmac<Sha512>>(entropy, salt.as_bytes(), 2048, &mut seed);
+
+    let mut mini_secret = [0u8; 32];
+    mini_secret.copy_from_slice(&seed[..32]);
+    mini_secret
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn seeds_from_phrases() {
+        let seed = seed_from_phrase(DEV_PHRASE, None).unwrap();
+        assert_eq!(
+            hex::encode(seed),
+            "fac7959dbfe72f052e5a0c3c8d6530f202b02fd8f9f5ca3580ec8deb7797479e"
+        );
+
+        let hex_seed = format!("0x{}", hex::encode(seed));
+        assert_eq!(seed_from_phrase(&hex_seed, Some("ignored")).unwrap(), seed);
+
+        assert_eq!(
+            seed_from_phrase("0x1234", None),
+            Err(KeypairError::InvalidSeed)
+        );
+        assert!(matches!(
+            seed_from_phrase("not a valid mnemonic", None),
+            Err(KeypairError::InvalidPhrase(_))
+        ));
+    }
+}
@@ -0,0 +1,197 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use codec::Encode;
+
+/// The phrase that development accounts like `//Alice` are derived from when a secret URI
+/// doesn't give one.
+pub const DEV_PHRASE: &str =
+    "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+
+/// A secret URI, which describes how to create a keypair, in the format that Substrate uses:
+///
+/// ```text
+/// <phrase>[//hard][/soft]...[///password]
+/// ```
+///
+/// The phrase is either a BIP-39 mnemonic phrase, or a `0x` prefixed, hex encoded 32 byte
+/// seed. It can be left out, in which case [`DEV_PHRASE`] is used, so that (for instance)
+/// `//Alice` is the development account "Alice". It's followed by any number of hard (`//`)
+/// and soft (`/`) derivation junctions, which are applied in order to the keypair that the
+/// phrase gives. The optional password after `///` is combined with a mnemonic phrase to give
+/// the seed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SecretUri {
+    phrase: String,
+    junctions: Vec<DeriveJunction>,
+    password: Option<String>,
+}
+
+impl SecretUri {
+    /// The mnemonic phrase, or `0x` prefixed hex seed.
+    pub fn phrase(&self) -> &str {
+        &self.phrase
+    }
+
+    /// The junctions to derive the keypair along, in order.
+    pub fn junctions(&self) -> &[DeriveJunction] {
+        &self.junctions
+    }
+
+    /// The password, if one was given.
+    pub fn password(&self) -> Option<&str> {
+        self.password.as_deref()
+    }
+}
+
+impl std::str::FromStr for SecretUri {
+    type Err = SecretUriError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (s, password) = match s.split_once("///") {
+            Some((s, password)) => (s, Some(password.to_owned())),
+            None => (s, None),
+        };
+        let (phrase, mut path) = s.split_at(s.find('/').unwrap_or(s.len()));
+
+        let mut junctions = Vec::new();
+        while !path.is_empty() {
+            let (is_hard, rest) = match path.strip_prefix("//") {
+                Some(rest) => (true, rest),
+                None => (false, &path[1..]),
+            };
+            let (code, rest) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+            if code.is_empty() {
+                return Err(SecretUriError::EmptyJunction);
+            }
+            junctions.push(DeriveJunction::from_code(code, is_hard));
+            path = rest;
+        }
+
+        let phrase = match phrase.trim() {
+            "" => DEV_PHRASE,
+            phrase => phrase,
+        };
+        Ok(SecretUri {
+            phrase: phrase.to_owned(),
+            junctions,
+            password,
+        })
+    }
+}
+
+/// An error parsing a [`SecretUri`].
+#[derive(Clone, Copy, Debug, Eq, thiserror::Error, PartialEq)]
+#[non_exhaustive]
+pub enum SecretUriError {
+    /// A derivation junction (the part after a `/` or `//`) is empty.
+    #[error("Derivation junctions cannot be empty")]
+    EmptyJunction,
+}
+
+/// A single step in the derivation of a keypair from another. Hard derivation gives a keypair
+/// whose public key can't be worked out from the public key that it's derived from, whereas
+/// soft derivation does (and so isn't supported by every kind of keypair).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeriveJunction {
+    /// A soft (public) derivation, with its 32 byte chain code.
+    Soft([u8; 32]),
+    /// A hard (private) derivation, with its 32 byte chain code.
+    Hard([u8; 32]),
+}
+
+impl DeriveJunction {
+    /// A soft derivation junction, whose chain code is the SCALE encoded index (hashed with
+    /// blake2-256 if it's longer than 32 bytes).
+    pub fn soft(index: impl Encode) -> Self {
+        DeriveJunction::Soft(chain_code(index))
+    }
+
+    /// A hard derivation junction, whose chain code is the SCALE encoded index (hashed with
+    /// blake2-256 if it's longer than 32 bytes).
+    pub fn hard(index: impl Encode) -> Self {
+        DeriveJunction::Hard(chain_code(index))
+    }
+
+    /// The chain code of this junction.
+    pub fn chain_code(&self) -> [u8; 32] {
+        match self {
+            DeriveJunction::Soft(chain_code) | DeriveJunction::Hard(chain_code) => *chain_code,
+        }
+    }
+
+    /// Is this a hard derivation junction?
+    pub fn is_hard(&self) -> bool {
+        matches!(self, DeriveJunction::Hard(_))
+    }
+
+    // Like Substrate, junctions which are numbers are encoded as a `u64`, and anything
+    // else is encoded as a string.
+    fn from_code(code: &str, is_hard: bool) -> Self {
+        let chain_code = match code.parse::<u64>() {
+            Ok(n) => chain_code(n),
+            Err(_) => chain_code(code),
+        };
+        if is_hard {
+            DeriveJunction::Hard(chain_code)
+        } else {
+            DeriveJunction::Soft(chain_code)
+        }
+    }
+}
+
+fn chain_code(index: impl Encode) -> [u8; 32] {
+    let mut chain_code = [0u8; 32];
+    index.using_encoded(|data| {
+        if data.len() > 32 {
+            chain_code = sp_core_hashing::blake2_256(data);
+        } else {
+            chain_code[..data.len()].copy_from_slice(data);
+        }
+    });
+    chain_code
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn secret_uris_are_parsed() {
+        let uri: SecretUri = "//Alice".parse().unwrap();
+        assert_eq!(uri.phrase(), DEV_PHRASE);
+        assert_eq!(uri.junctions(), &[DeriveJunction::hard("Alice")]);
+        assert_eq!(uri.password(), None);
+
+        let uri: SecretUri = "0x1234//hard/soft/1///pass/word".parse().unwrap();
+        assert_eq!(uri.phrase(), "0x1234");
+        assert_eq!(
+            uri.junctions(),
+            &[
+                DeriveJunction::hard("hard"),
+                DeriveJunction::soft("soft"),
+                DeriveJunction::soft(1u64),
+            ]
+        );
+        assert_eq!(uri.password(), Some("pass/word"));
+
+        let uri: SecretUri = format!("{DEV_PHRASE}///password").parse().unwrap();
+        assert_eq!(uri.phrase(), DEV_PHRASE);
+        assert!(uri.junctions().is_empty());
+        assert_eq!(uri.password(), Some("password"));
+
+        assert_eq!(
+            "//Alice//".parse::<SecretUri>(),
+            Err(SecretUriError::EmptyJunction)
+        );
+    }
+
+    #[test]
+    fn long_chain_codes_are_hashed() {
+        let long = "a".repeat(40);
+        assert_eq!(
+            DeriveJunction::hard(&long).chain_code(),
+            sp_core_hashing::blake2_256(&long.encode())
+        );
+    }
+}
@@ -0,0 +1,188 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! An sr25519 keypair, which is the default kind of keypair used by Substrate based chains.
+//!
+//! ```rust
+//! use subxt::tx::{sr25519, SecretUri};
+//!
+//! // The development account "Alice":
+//! let uri: SecretUri = "//Alice".parse().unwrap();
+//! let alice = sr25519::Keypair::from_uri(&uri).unwrap();
+//!
+//! // An account derived from a BIP-39 mnemonic phrase, with a password:
+//! let phrase = "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
+//! let uri: SecretUri = format!("{phrase}//0///secret").parse().unwrap();
+//! let account = sr25519::Keypair::from_uri(&uri).unwrap();
+//! ```
+
+use super::{seed_from_phrase, DeriveJunction, KeypairError, SecretUri};
+use crate::{
+    tx::Signer,
+    utils::{AccountId32, MultiSignature},
+    Config,
+};
+use schnorrkel::{
+    derive::{ChainCode, Derivation},
+    ExpansionMode, MiniSecretKey,
+};
+
+/// The context that Substrate signs messages in.
+const SIGNING_CTX: &[u8] = b"substrate";
+
+/// An sr25519 keypair. This implements [`Signer`] for any [`Config`] whose accounts are
+/// [`AccountId32`]s and whose signatures can be created from a [`MultiSignature`].
+#[derive(Clone)]
+pub struct Keypair {
+    keypair: schnorrkel::Keypair,
+    account_id: AccountId32,
+}
+
+impl Keypair {
+    /// Create a keypair from a [`SecretUri`], deriving it along any junctions that it gives.
+    pub fn from_uri(uri: &SecretUri) -> Result<Self, KeypairError> {
+        let seed = seed_from_phrase(uri.phrase(), uri.password())?;
+        Ok(Self::from_seed(seed).derive(uri.junctions().iter().copied()))
+    }
+
+    /// Create a keypair from a BIP-39 mnemonic phrase and an optional password.
+    pub fn from_phrase(phrase: &str, password: Option<&str>) -> Result<Self, KeypairError> {
+        let seed = seed_from_phrase(phrase, password)?;
+        Ok(Self::from_seed(seed))
+    }
+
+    /// Create a keypair from a 32 byte seed (also known as a "mini secret key").
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        let keypair = MiniSecretKey::from_bytes(&seed)
+            .expect("seed is 32 bytes; qed")
+            .expand_to_keypair(ExpansionMode::Ed25519);
+        Self::from_keypair(keypair)
+    }
+
+    /// Derive a new keypair from this one along the given junctions. Both hard and soft
+    /// derivation are supported.
+    pub fn derive(&self, junctions: impl IntoIterator<Item = DeriveJunction>) -> Self {
+        let mut secret = self.keypair.secret.clone();
+        for junction in junctions {
+            secret = match junction {
+                DeriveJunction::Soft(chain_code) => {
+                    secret.derived_key_simple(ChainCode(chain_code), b"").0
+                }
+                DeriveJunction::Hard(chain_code) => secret
+                    .hard_derive_mini_secret_key(Some(ChainCode(chain_code)), b"")
+                    .0
+                    .expand(ExpansionMode::Ed25519),
+            };
+        }
+        Self::from_keypair(secret.to_keypair())
+    }
+
+    /// The public key of this keypair.
+    pub fn public_key(&self) -> [u8; 32] {
+        self.keypair.public.to_bytes()
+    }
+
+    /// The account ID of this keypair, which is its public key.
+    pub fn account_id(&self) -> &AccountId32 {
+        &self.account_id
+    }
+
+    /// Sign a message, returning the 64 byte signature.
+    pub fn sign(&self, message: &[u8]) -> [u8; 64] {
+        let context = schnorrkel::signing_context(SIGNING_CTX);
+        self.keypair.sign(context.bytes(message)).to_bytes()
+    }
+
+    /// Does the given signature of the message belong to the keypair with the given
+    /// public key?
+    pub fn verify(signature: &[u8; 64], message: &[u8], public_key: &[u8; 32]) -> bool {
+        let (Ok(signature), Ok(public_key)) = (
+            schnorrkel::Signature::from_bytes(signature),
+            schnorrkel::PublicKey::from_bytes(public_key),
+        ) else {
+            return false;
+        };
+        public_key
+            .verify_simple(SIGNING_CTX, message, &signature)
+            .is_ok()
+    }
+
+    fn from_keypair(keypair: schnorrkel::Keypair) -> Self {
+        let account_id = AccountId32(keypair.public.to_bytes());
+        Keypair {
+            keypair,
+            account_id,
+        }
+    }
+}
+
+// Only the public half of the keypair is shown, so that secrets don't end up in logs.
+impl std::fmt::Debug for Keypair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Keypair")
+            .field("account_id", &self.account_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> Signer<T> for Keypair
+where
+    T: Config<AccountId = AccountId32>,
+    T::Signature: From<MultiSignature>,
+{
+    fn account_id(&self) -> &T::AccountId {
+        &self.account_id
+    }
+
+    fn address(&self) -> T::Address {
+        self.account_id.clone().into()
+    }
+
+    fn sign(&self, signer_payload: &[u8]) -> T::Signature {
+        MultiSignature::Sr25519(Keypair::sign(self, signer_payload)).into()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn keypair(uri: &str) -> Keypair {
+        Keypair::from_uri(&uri.parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn dev_accounts_match_substrate() {
+        // The well known public keys of the development accounts.
+        assert_eq!(
+            hex::encode(keypair("//Alice").public_key()),
+            "d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"
+        );
+        assert_eq!(
+            hex::encode(keypair("//Bob").public_key()),
+            "8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48"
+        );
+    }
+
+    #[test]
+    fn derivation_and_signing() {
+        let alice = keypair("//Alice");
+        assert_eq!(
+            alice.derive([DeriveJunction::soft("soft")]).public_key(),
+            keypair("//Alice/soft").public_key()
+        );
+        assert_ne!(
+            keypair("//Alice/soft").public_key(),
+            keypair("//Alice//soft").public_key()
+        );
+        assert_ne!(
+            keypair("//Alice").public_key(),
+            keypair("//Alice///password").public_key()
+        );
+
+        let signature = alice.sign(b"hello");
+        assert!(Keypair::verify(&signature, b"hello", &alice.public_key()));
+        assert!(!Keypair::verify(&signature, b"bye", &alice.public_key()));
+    }
+}
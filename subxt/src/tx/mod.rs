@@ -9,6 +9,9 @@
 //! additional and signed extra parameters are used when constructing an extrinsic, and is a part
 //! of the chain configuration (see [`crate::config::Config`]).
 
+#[cfg(any(feature = "sr25519", feature = "ed25519"))]
+mod keypair;
+mod nonce_manager;
 mod signer;
 mod tx_client;
 mod tx_payload;
@@ -21,8 +24,17 @@ pub use self::signer::PairSigner;
 #[cfg(feature = "substrate-compat")]
 pub use self::signer::BoolSigner;
 
+// Pure Rust keypairs, which don't need sp_core or sp_runtime.
+#[cfg(feature = "ed25519")]
+pub use self::keypair::ed25519;
+#[cfg(feature = "sr25519")]
+pub use self::keypair::sr25519;
+#[cfg(any(feature = "sr25519", feature = "ed25519"))]
+pub use self::keypair::{DeriveJunction, KeypairError, SecretUri, SecretUriError, DEV_PHRASE};
+
 pub use self::{
-    signer::Signer,
+    nonce_manager::{Nonce, NonceManager},
+    signer::{EcdsaKeccakSigner, Signer},
     tx_client::{SubmittableExtrinsic, TxClient},
     tx_payload::{dynamic, BoxedPayload, DynamicPayload, Payload, TxPayload},
     tx_progress::{TxInBlock, TxProgress, TxStatus},
@@ -0,0 +1,434 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+use crate::{
+    client::OnlineClientT,
+    error::{
+        Error, InvalidTransaction, JsonRpcError, RpcError, TransactionError,
+        TransactionValidityError,
+    },
+    rpc::Rpc,
+    utils::PhantomDataSendSync,
+    Config,
+};
+use codec::{Decode, Encode};
+use derivative::Derivative;
+use serde::Serialize;
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+
+/// Hands out sequential nonces for the transactions of each account, rather than asking
+/// the node for the next nonce every time a transaction is created. This allows many
+/// transactions from the same account to be created and submitted concurrently.
+///
+/// Cloning a [`NonceManager`] is cheap, and clones hand out nonces from the same state,
+/// so a single manager can be shared by every task that submits transactions. Hand it to
+/// [`crate::tx::TxClient::create_signed_with_nonce_manager()`] to use it.
+///
+/// The next nonce of an account is fetched from the node using `system_accountNextIndex`
+/// the first time that it's needed, and again whenever a transaction is refused by the
+/// node because its nonce is stale or in the future. The nonce of a transaction that is
+/// dropped without being submitted is handed out again. If a transaction is reported as
+/// dropped or invalid by its [`crate::tx::TxProgress`] after being submitted, the account
+/// is fetched from the node again too, so that its nonce isn't left as a gap.
+#[derive(Derivative)]
+#[derivative(Clone(bound = ""))]
+pub struct NonceManager<T: Config> {
+    rpc: Rpc<T>,
+    accounts: Arc<Mutex<HashMap<Vec<u8>, AccountNonces>>>,
+}
+
+impl<T: Config> NonceManager<T> {
+    /// Create a new [`NonceManager`] which fetches nonces using the given client.
+    pub fn new(client: &impl OnlineClientT<T>) -> Self {
+        Self {
+            rpc: client.rpc().clone(),
+            accounts: Default::default(),
+        }
+    }
+
+    /// Reserve the next nonce for the given account. The nonce is handed out again if the
+    /// returned [`Nonce`] is dropped before [`Nonce::consume()`] is called on it.
+    pub async fn next_nonce(&self, account_id: &T::AccountId) -> Result<Nonce<T>, Error>
+    where
+        T::AccountId: Serialize,
+    {
+        let key = account_id.encode();
+
+        if let Some(nonce) = self.try_next_nonce(&key, None)? {
+            return Ok(nonce);
+        }
+
+        // Don't hold the lock while waiting for the node. If the next nonce is fetched by
+        // somebody else in the meantime, theirs is used rather than this one.
+        let next_index = self.rpc.system_account_next_index(account_id).await?;
+        let nonce = self
+            .try_next_nonce(&key, Some(next_index))?
+            .expect("the next nonce was provided; qed");
+        Ok(nonce)
+    }
+
+    /// Forget the nonces of the given account, so that the next nonce is fetched from the
+    /// node the next time that one is needed. Nonces that are currently reserved for the
+    /// account are not handed out again when they're dropped.
+    pub fn resync(&self, account_id: &T::AccountId) {
+        resync(&self.accounts, &account_id.encode());
+    }
+
+    fn try_next_nonce(&self, key: &[u8], fetched: Option<u64>) -> Result<Option<Nonce<T>>, Error> {
+        let mut accounts = self.accounts.lock().expect("shouldn't be poisoned");
+        let account = accounts.entry(key.to_vec()).or_default();
+        if account.next.is_none() {
+            account.next = fetched;
+        }
+        let Some(raw) = account.take() else {
+            return Ok(None);
+        };
+
+        if let Err(e) = index_from_u64::<T>(raw) {
+            account.release(raw);
+            return Err(e);
+        }
+        Ok(Some(Nonce {
+            raw,
+            generation: account.generation,
+            key: key.to_vec(),
+            accounts: self.accounts.clone(),
+            consumed: AtomicBool::new(false),
+            _marker: PhantomDataSendSync::new(),
+        }))
+    }
+}
+
+impl<T: Config> std::fmt::Debug for NonceManager<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NonceManager").finish_non_exhaustive()
+    }
+}
+
+/// A nonce reserved for a transaction from some account by a [`NonceManager`]. Unless
+/// [`Nonce::consume()`] is called, the nonce is released when this is dropped, so that
+/// it's handed out again for the next transaction from the account.
+pub struct Nonce<T: Config> {
+    raw: u64,
+    generation: u64,
+    key: Vec<u8>,
+    accounts: Arc<Mutex<HashMap<Vec<u8>, AccountNonces>>>,
+    consumed: AtomicBool,
+    _marker: PhantomDataSendSync<T>,
+}
+
+impl<T: Config> Nonce<T> {
+    /// The nonce to use in the transaction.
+    pub fn value(&self) -> T::Index {
+        index_from_u64::<T>(self.raw).expect("checked when the nonce was reserved; qed")
+    }
+
+    /// Mark the nonce as used, so that it's not handed out again when this is dropped.
+    /// Call this once the transaction using it has been submitted.
+    pub fn consume(&self) {
+        self.consumed.store(true, Ordering::Relaxed);
+    }
+
+    /// Record the outcome of submitting the transaction that uses this nonce. If it was
+    /// accepted, the nonce is consumed. If it was refused because its nonce was stale or
+    /// in the future, the account is resynced with the node.
+    pub(crate) fn submitted<R>(&self, result: &Result<R, Error>) {
+        match result {
+            Ok(_) => self.consume(),
+            Err(e) if is_nonce_error(e) => {
+                self.consume();
+                resync(&self.accounts, &self.key);
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// Record that the transaction using this nonce was dropped from the pool or found to
+    /// be invalid after it was submitted. It may never make it into a block, and so the
+    /// account is resynced with the node rather than leaving a gap in its nonces.
+    pub(crate) fn dropped(&self) {
+        self.consume();
+        resync(&self.accounts, &self.key);
+    }
+}
+
+impl<T: Config> Drop for Nonce<T> {
+    fn drop(&mut self) {
+        if self.consumed.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut accounts = self.accounts.lock().expect("shouldn't be poisoned");
+        if let Some(account) = accounts.get_mut(&self.key) {
+            // Nonces handed out before a resync may have been used since by others.
+            if account.generation == self.generation {
+                account.release(self.raw);
+            }
+        }
+    }
+}
+
+impl<T: Config> std::fmt::Debug for Nonce<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Nonce")
+            .field("value", &self.raw)
+            .field("consumed", &self.consumed.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+/// The nonces of a single account.
+#[derive(Debug, Default)]
+struct AccountNonces {
+    /// The next nonce to hand out, or `None` if it needs fetching from the node.
+    next: Option<u64>,
+    /// Nonces below `next` which were released, to be handed out again first.
+    released: BTreeSet<u64>,
+    /// Incremented on each resync, so that nonces handed out before it aren't released.
+    generation: u64,
+}
+
+impl AccountNonces {
+    fn take(&mut self) -> Option<u64> {
+        if let Some(&nonce) = self.released.iter().next() {
+            self.released.remove(&nonce);
+            return Some(nonce);
+        }
+        let next = self.next.as_mut()?;
+        let nonce = *next;
+        *next += 1;
+        Some(nonce)
+    }
+
+    fn release(&mut self, nonce: u64) {
+        let Some(next) = &mut self.next else {
+            return;
+        };
+        if nonce >= *next {
+            return;
+        }
+        self.released.insert(nonce);
+        // Released nonces at the top can simply be handed out again in sequence.
+        while *next > 0 && self.released.remove(&(*next - 1)) {
+            *next -= 1;
+        }
+    }
+}
+
+fn resync(accounts: &Mutex<HashMap<Vec<u8>, AccountNonces>>, key: &[u8]) {
+    let mut accounts = accounts.lock().expect("shouldn't be poisoned");
+    if let Some(account) = accounts.get_mut(key) {
+        account.next = None;
+        account.released.clear();
+        account.generation += 1;
+    }
+}
+
+/// Did the node refuse a transaction because of its nonce? A nonce that's already in
+/// use by another transaction in the pool shows up as the priority being too low.
+///
+/// Refusals from the legacy submission methods are already decoded into a
+/// [`TransactionError::Validity`], while others are still plain RPC errors.
+fn is_nonce_error(err: &Error) -> bool {
+    let is_stale_or_future = |e: &TransactionValidityError| {
+        matches!(
+            e,
+            TransactionValidityError::Invalid(
+                InvalidTransaction::Stale | InvalidTransaction::Future
+            )
+        )
+    };
+    match err {
+        Error::Transaction(TransactionError::Validity(e)) => is_stale_or_future(e),
+        Error::Rpc(RpcError::Call(e)) => {
+            e.code == JsonRpcError::POOL_TOO_LOW_PRIORITY
+                || e.transaction_validity_error()
+                    .map_or(false, |e| is_stale_or_future(&e))
+        }
+        _ => false,
+    }
+}
+
+/// Nonces are fixed width little endian integers, so the nonce type can be decoded from
+/// the little endian bytes of a `u64`, provided that the value fits.
+fn index_from_u64<T: Config>(nonce: u64) -> Result<T::Index, Error> {
+    let index = T::Index::decode(&mut &nonce.to_le_bytes()[..])?;
+    if index.into() != nonce {
+        return Err(Error::Other(format!(
+            "Nonce {nonce} does not fit in the nonce type of the chain"
+        )));
+    }
+    Ok(index)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::substrate::H256,
+        rpc::{
+            mock_rpc_client::{subscription, to_raw, MockRpcClient},
+            types::{Bytes, RuntimeVersion},
+        },
+        tx::SubmittableExtrinsic,
+        utils::AccountId32,
+        Metadata, OnlineClient, SubstrateConfig,
+    };
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn nonces_are_handed_out_in_sequence() {
+        let mut account = AccountNonces::default();
+        assert_eq!(account.take(), None);
+
+        account.next = Some(5);
+        assert_eq!(account.take(), Some(5));
+        assert_eq!(account.take(), Some(6));
+        assert_eq!(account.take(), Some(7));
+
+        // Released nonces are handed out again, lowest first.
+        account.release(5);
+        assert_eq!(account.take(), Some(5));
+        assert_eq!(account.take(), Some(8));
+
+        // Releasing the most recent nonces winds the sequence back.
+        account.release(8);
+        account.release(7);
+        assert_eq!(account.next, Some(7));
+        assert!(account.released.is_empty());
+    }
+
+    // Submitting the extrinsic `[n]` is answered with the `n`th of these responses.
+    const SUBMIT_RESPONSES: [(i32, &str); 5] = [
+        (0, ""),
+        (1010, "Transaction is outdated"),
+        (1010, "Transaction will be valid in the future"),
+        (1010, "Transaction has a bad signature"),
+        (JsonRpcError::POOL_TOO_LOW_PRIORITY, "Priority is too low"),
+    ];
+
+    // A client whose accounts always have a next index of 5, counting how many times
+    // that's asked for. Transactions are submitted as described by `SUBMIT_RESPONSES`,
+    // and watching them reports that they were dropped.
+    fn client(fetches: Arc<AtomicUsize>) -> OnlineClient<SubstrateConfig> {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_tiny.scale").unwrap();
+        let metadata = Metadata::decode(&mut &*bytes).unwrap();
+
+        let rpc = MockRpcClient::new()
+            .on_request(move |method, params| match method {
+                "system_accountNextIndex" => {
+                    fetches.fetch_add(1, Ordering::SeqCst);
+                    Ok(to_raw(5))
+                }
+                "author_submitExtrinsic" => {
+                    let (tx,): (Bytes,) = serde_json::from_str(params.unwrap().get()).unwrap();
+                    match SUBMIT_RESPONSES[tx.0[0] as usize] {
+                        (0, _) => Ok(to_raw(H256::zero())),
+                        (code, data) => Err(RpcError::Call(JsonRpcError {
+                            code,
+                            message: "Invalid Transaction".to_owned(),
+                            data: Some(data.into()),
+                        })),
+                    }
+                }
+                _ => Err(RpcError::Call(JsonRpcError {
+                    code: JsonRpcError::METHOD_NOT_FOUND,
+                    message: "Method not found".to_owned(),
+                    data: None,
+                })),
+            })
+            .on_subscribe(|_| Ok(subscription([Ok(to_raw("dropped"))])));
+
+        let runtime_version = RuntimeVersion {
+            spec_version: 1,
+            transaction_version: 1,
+            other: Default::default(),
+        };
+        OnlineClient::from_rpc_client_with(H256::zero(), runtime_version, metadata, Arc::new(rpc))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn nonce_errors_resync_the_account() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let client = client(fetches.clone());
+        let manager = NonceManager::new(&client);
+        let account = AccountId32([0; 32]);
+
+        // Submit the extrinsic `[response]`, handing back the nonce that it used.
+        let submit = |response: u8| {
+            let (client, manager, account) = (client.clone(), manager.clone(), account.clone());
+            async move {
+                let nonce = manager.next_nonce(&account).await.unwrap();
+                let value = nonce.value();
+                let res = SubmittableExtrinsic::from_bytes(client, vec![response])
+                    .with_nonce(nonce)
+                    .submit()
+                    .await;
+                (value, res)
+            }
+        };
+
+        // Accepted, so the nonce is used up.
+        let (nonce, res) = submit(0).await;
+        assert!(res.is_ok());
+        assert_eq!((nonce, fetches.load(Ordering::SeqCst)), (5, 1));
+
+        // Refused for some other reason, so the nonce is handed out again.
+        let (nonce, res) = submit(3).await;
+        assert!(matches!(
+            res,
+            Err(Error::Transaction(TransactionError::Validity(
+                TransactionValidityError::Invalid(InvalidTransaction::BadProof)
+            )))
+        ));
+        assert_eq!(nonce, 6);
+        let (nonce, _) = submit(1).await;
+        assert_eq!((nonce, fetches.load(Ordering::SeqCst)), (6, 1));
+
+        // That was refused as stale, and so the account was resynced. So too with nonces
+        // in the future, or in use by another transaction in the pool.
+        for response in [2, 4, 0] {
+            let (nonce, _) = submit(response).await;
+            assert_eq!(nonce, 5);
+        }
+        assert_eq!(fetches.load(Ordering::SeqCst), 4);
+        assert!(!is_nonce_error(&Error::Other("".into())));
+    }
+
+    #[tokio::test]
+    async fn dropped_transactions_resync_the_account() {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let client = client(fetches.clone());
+        let manager = NonceManager::new(&client);
+        let account = AccountId32([0; 32]);
+
+        let nonce = manager.next_nonce(&account).await.unwrap();
+        let progress = SubmittableExtrinsic::from_bytes(client, vec![0])
+            .with_nonce(nonce)
+            .submit_and_watch()
+            .await
+            .unwrap();
+        assert_eq!(manager.next_nonce(&account).await.unwrap().value(), 6);
+
+        let res = progress.wait_for_in_block().await;
+        assert!(matches!(
+            res,
+            Err(Error::Transaction(TransactionError::Dropped))
+        ));
+        assert_eq!(manager.next_nonce(&account).await.unwrap().value(), 5);
+        assert_eq!(fetches.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn nonces_must_fit_the_index_type() {
+        assert_eq!(index_from_u64::<crate::PolkadotConfig>(7).unwrap(), 7u32);
+        assert!(index_from_u64::<crate::PolkadotConfig>(u64::MAX).is_err());
+    }
+}
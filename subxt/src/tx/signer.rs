@@ -24,6 +24,8 @@ pub trait Signer<T: Config> {
     fn sign(&self, signer_payload: &[u8]) -> T::Signature;
 }
 
+pub use ecdsa_keccak_signer::EcdsaKeccakSigner;
+
 #[cfg(feature = "substrate-compat")]
 pub use pair_signer::PairSigner;
 
@@ -170,4 +172,137 @@ mod bool_signer {
             ecdsa_signature.into()
         }
     }
-}
\ No newline at end of file
+}
+
+// A signer suitable for chains with Ethereum style accounts. This only relies on libsecp256k1,
+// and so doesn't need sp_core or sp_runtime to be included.
+mod ecdsa_keccak_signer {
+    use super::Signer;
+    use crate::{
+        utils::{AccountId20, EthereumSignature},
+        Config,
+    };
+    use secp256k1::{Message, PublicKey, SecretKey};
+
+    /// A [`Signer`] implementation for chains with Ethereum style [`AccountId20`] accounts,
+    /// which signs the keccak-256 hash of the signer payload with a SECP256k1 [`SecretKey`]
+    /// to produce an [`EthereumSignature`].
+    #[derive(Clone)]
+    pub struct EcdsaKeccakSigner<T: Config> {
+        account_id: T::AccountId,
+        signer: SecretKey,
+    }
+
+    impl<T> EcdsaKeccakSigner<T>
+    where
+        T: Config,
+        T::AccountId: From<AccountId20>,
+    {
+        /// Creates a new [`Signer`] from a SECP256k1 [`SecretKey`].
+        pub fn new(signer: SecretKey) -> Self {
+            let public_key = PublicKey::from_secret_key(&signer).serialize();
+            let account_id = AccountId20::from_uncompressed_public_key(&public_key);
+            Self {
+                account_id: account_id.into(),
+                signer,
+            }
+        }
+
+        /// Creates a new [`Signer`] from the 32 bytes of a SECP256k1 secret key, failing if
+        /// they aren't a valid secret key.
+        pub fn from_secret_key_bytes(secret_key: &[u8; 32]) -> Result<Self, secp256k1::Error> {
+            SecretKey::parse(secret_key).map(Self::new)
+        }
+
+        /// Returns the [`SecretKey`] used to construct this.
+        pub fn signer(&self) -> &SecretKey {
+            &self.signer
+        }
+
+        /// Return the account ID.
+        pub fn account_id(&self) -> &T::AccountId {
+            &self.account_id
+        }
+    }
+
+    // Don't expose the secret key.
+    impl<T: Config> std::fmt::Debug for EcdsaKeccakSigner<T> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("EcdsaKeccakSigner")
+                .field("account_id", &self.account_id)
+                .finish_non_exhaustive()
+        }
+    }
+
+    impl<T> Signer<T> for EcdsaKeccakSigner<T>
+    where
+        T: Config,
+        T::Signature: From<EthereumSignature>,
+    {
+        fn account_id(&self) -> &T::AccountId {
+            &self.account_id
+        }
+
+        fn address(&self) -> T::Address {
+            self.account_id.clone().into()
+        }
+
+        fn sign(&self, signer_payload: &[u8]) -> T::Signature {
+            let message = Message::parse(&sp_core_hashing::keccak_256(signer_payload));
+            let (signature, recovery_id) = secp256k1::sign(&message, &self.signer);
+            let mut sig = [0u8; 65];
+            sig[..64].copy_from_slice(&signature.serialize());
+            sig[64] = recovery_id.serialize();
+            EthereumSignature(sig).into()
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        use crate::EthereumConfig;
+        use secp256k1::{RecoveryId, Signature};
+
+        fn signer() -> EcdsaKeccakSigner<EthereumConfig> {
+            let mut secret_key = [0u8; 32];
+            secret_key[31] = 1;
+            EcdsaKeccakSigner::from_secret_key_bytes(&secret_key).unwrap()
+        }
+
+        #[test]
+        fn account_id_is_the_ethereum_address() {
+            // The well known address of the secret key `1`.
+            let expected: AccountId20 = "0x7E5F4552091A69125d5DfCb7b8C2659029395Bdf"
+                .parse()
+                .unwrap();
+            assert_eq!(*Signer::account_id(&signer()), expected);
+        }
+
+        #[test]
+        fn signatures_recover_to_the_account_id() {
+            let signer = signer();
+            let payload = b"some signer payload";
+            let EthereumSignature(sig) = Signer::sign(&signer, payload);
+
+            let message = Message::parse(&sp_core_hashing::keccak_256(payload));
+            let signature = Signature::parse(&sig[..64].try_into().unwrap());
+            let recovery_id = RecoveryId::parse(sig[64]).unwrap();
+            let public_key = secp256k1::recover(&message, &signature, &recovery_id).unwrap();
+
+            assert_eq!(
+                AccountId20::from_uncompressed_public_key(&public_key.serialize()),
+                *Signer::account_id(&signer)
+            );
+        }
+
+        #[test]
+        fn debug_output_hides_the_secret_key() {
+            let signer = signer();
+            let expected = format!(
+                "EcdsaKeccakSigner {{ account_id: {:?}, .. }}",
+                signer.account_id
+            );
+            assert_eq!(format!("{signer:?}"), expected);
+        }
+    }
+}
@@ -2,17 +2,19 @@
 // This file is dual-licensed as Apache-2.0 or GPL-3.0.
 // see LICENSE for license details.
 
-use std::borrow::Cow;
+use std::{borrow::Cow, sync::Arc};
 
 use codec::{Compact, Encode};
 use derivative::Derivative;
+use serde::Serialize;
 use sp_core_hashing::blake2_256;
 
 use crate::{
     client::{OfflineClientT, OnlineClientT},
-    config::{Config, ExtrinsicParams, Hasher},
-    error::{Error, MetadataError},
-    tx::{Signer as SignerT, TxPayload, TxProgress},
+    config::{extrinsic_params::Era, Config, ExtrinsicParams, Hasher, Header, MortalParams},
+    error::{BlockError, Error, MetadataError},
+    rpc::types::DryRunResultBytes,
+    tx::{Nonce, NonceManager, Signer as SignerT, TxPayload, TxProgress},
     utils::{Encoded, PhantomDataSendSync},
 };
 
@@ -126,13 +128,14 @@ impl<T: Config, C: OfflineClientT<T>> TxClient<T, C> {
         let additional_and_extra_params = {
             // Obtain spec version and transaction version from the runtime version of the client.
             let runtime = self.client.runtime_version();
-            <T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::new(
+            <T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::new_with_metadata(
                 runtime.spec_version,
                 runtime.transaction_version,
                 account_nonce,
                 self.client.genesis_hash(),
+                &self.client.metadata(),
                 other_params,
-            )
+            )?
         };
 
         // Return these details, ready to construct a signed extrinsic from.
@@ -186,6 +189,62 @@ where
             .await
     }
 
+    /// Make the given parameters mortal, so that a transaction built with them is only valid
+    /// for roughly `period` blocks. The era is aligned to, and begins at, the latest finalized
+    /// block, whose hash (or for long periods, the hash of the block shortly before it that
+    /// the era begins at) is used as the mortality checkpoint.
+    ///
+    /// The period is rounded up to a power of two between 4 and 65536. On `FRAME` based
+    /// runtimes it should not exceed the `BlockHashCount` of the `system` pallet, or the
+    /// transaction will be immediately invalid.
+    pub async fn mortal_params(
+        &self,
+        period: u64,
+        other_params: <T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams,
+    ) -> Result<<T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams, Error>
+    where
+        <T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams:
+            MortalParams<T::Hash>,
+    {
+        let rpc = self.client.rpc();
+        let finalized_hash = rpc.finalized_head().await?;
+        let finalized_number: u64 = match rpc.header(Some(finalized_hash)).await? {
+            Some(header) => header.number().into(),
+            None => return Err(BlockError::not_found(finalized_hash).into()),
+        };
+
+        let era = Era::mortal(period, finalized_number);
+        let birth = era.birth(finalized_number);
+        let checkpoint = if birth == finalized_number {
+            finalized_hash
+        } else {
+            rpc.block_hash(Some(birth.into()))
+                .await?
+                .ok_or(BlockError::NumberNotFound(birth))?
+        };
+
+        Ok(other_params.mortal_from(era, checkpoint))
+    }
+
+    /// Creates a signed extrinsic, without submitting it, which is only valid for roughly
+    /// `period` blocks from the latest finalized block. See [`Self::mortal_params()`].
+    pub async fn create_signed_mortal<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        period: u64,
+        other_params: <T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams,
+    ) -> Result<SubmittableExtrinsic<T, C>, Error>
+    where
+        Call: TxPayload,
+        Signer: SignerT<T>,
+        <T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams:
+            MortalParams<T::Hash>,
+    {
+        let other_params = self.mortal_params(period, other_params).await?;
+        self.create_signed(call, signer, other_params).await
+    }
+
     /// Creates a partial signed extrinsic, without submitting it.
     pub async fn create_partial_signed<Call>(
         &self,
@@ -215,6 +274,31 @@ where
         self.create_signed_with_nonce(call, signer, account_nonce, other_params)
     }
 
+    /// Creates a signed extrinsic, without submitting it, using a nonce handed out by the
+    /// given [`NonceManager`] rather than asking the node for one.
+    ///
+    /// The nonce is handed out again if the returned extrinsic is dropped without being
+    /// submitted, or if submitting it fails for a reason unrelated to its nonce. If the node
+    /// refuses it because its nonce is stale or in the future, or if it's later reported as
+    /// dropped or invalid by [`TxProgress`], the [`NonceManager`] fetches the next nonce for
+    /// the account from the node again.
+    pub async fn create_signed_with_nonce_manager<Call, Signer>(
+        &self,
+        call: &Call,
+        signer: &Signer,
+        nonce_manager: &NonceManager<T>,
+        other_params: <T::ExtrinsicParams as ExtrinsicParams<T::Index, T::Hash>>::OtherParams,
+    ) -> Result<SubmittableExtrinsic<T, C>, Error>
+    where
+        Call: TxPayload,
+        Signer: SignerT<T>,
+        T::AccountId: Serialize,
+    {
+        let nonce = nonce_manager.next_nonce(signer.account_id()).await?;
+        let extrinsic = self.create_signed_with_nonce(call, signer, nonce.value(), other_params)?;
+        Ok(extrinsic.with_nonce(nonce))
+    }
+
     /// Creates and signs an extrinsic and submits it to the chain. Passes default parameters
     /// to construct the "signed extra" and "additional" payloads needed by the extrinsic.
     ///
@@ -396,9 +480,12 @@ where
 }
 
 /// This represents an extrinsic that has been signed and is ready to submit.
-pub struct SubmittableExtrinsic<T, C> {
+pub struct SubmittableExtrinsic<T: Config, C> {
     client: C,
     encoded: Encoded,
+    // The nonce reserved for this extrinsic, if it came from a `NonceManager`. This is
+    // shared with the `TxProgress` once submitted.
+    nonce: Option<Arc<Nonce<T>>>,
     marker: std::marker::PhantomData<T>,
 }
 
@@ -418,10 +505,17 @@ where
         Self {
             client,
             encoded: Encoded(tx_bytes),
+            nonce: None,
             marker: std::marker::PhantomData,
         }
     }
 
+    // Use a nonce reserved by a `NonceManager` for this extrinsic.
+    pub(crate) fn with_nonce(mut self, nonce: Nonce<T>) -> Self {
+        self.nonce = Some(Arc::new(nonce));
+        self
+    }
+
     /// Returns the SCALE encoded extrinsic bytes.
     pub fn encoded(&self) -> &[u8] {
         &self.encoded.0
@@ -429,9 +523,22 @@ where
 
     /// Consumes [`SubmittableExtrinsic`] and returns the SCALE encoded
     /// extrinsic bytes.
+    ///
+    /// If the extrinsic uses a nonce from a [`NonceManager`], the nonce is assumed
+    /// to be used, and so won't be handed out again.
     pub fn into_encoded(self) -> Vec<u8> {
+        if let Some(nonce) = &self.nonce {
+            nonce.consume();
+        }
         self.encoded.0
     }
+
+    fn submitted<R>(&self, result: Result<R, Error>) -> Result<R, Error> {
+        if let Some(nonce) = &self.nonce {
+            nonce.submitted(&result);
+        }
+        result
+    }
 }
 
 impl<T, C> SubmittableExtrinsic<T, C>
@@ -443,14 +550,57 @@ where
     ///
     /// Returns a [`TxProgress`], which can be used to track the status of the transaction
     /// and obtain details about it, once it has made it into a block.
+    ///
+    /// If the node doesn't expose the legacy `author_submitAndWatchExtrinsic` RPC method, this
+    /// falls back to [`Self::submit_and_watch_unstable()`]. In that case, a transaction which
+    /// is dropped or found to be invalid is reported as a [`crate::error::TransactionError::Error`]
+    /// giving the node's reason, rather than as [`crate::tx::TxStatus::Dropped`] or
+    /// [`crate::tx::TxStatus::Invalid`].
     pub async fn submit_and_watch(&self) -> Result<TxProgress<T, C>, Error> {
+        let method = self
+            .client
+            .rpc()
+            .best_method(&[
+                "author_submitAndWatchExtrinsic",
+                "transaction_unstable_submitAndWatch",
+            ])
+            .await?;
+        if method == "transaction_unstable_submitAndWatch" {
+            return self.submit_and_watch_unstable().await;
+        }
+
         // Get a hash of the extrinsic (we'll need this later).
         let ext_hash = T::Hasher::hash_of(&self.encoded);
 
         // Submit and watch for transaction progress.
-        let sub = self.client.rpc().watch_extrinsic(&self.encoded).await?;
+        let sub = self.submitted(self.client.rpc().watch_extrinsic(&self.encoded).await)?;
 
-        Ok(TxProgress::new(sub, self.client.clone(), ext_hash))
+        Ok(TxProgress::new(sub, self.client.clone(), ext_hash).with_nonce(self.nonce.clone()))
+    }
+
+    /// Submits the extrinsic to the chain using the new (and currently unstable)
+    /// `transaction_unstable_submitAndWatch` RPC method, rather than the legacy
+    /// `author_submitAndWatchExtrinsic` method used by [`Self::submit_and_watch()`].
+    /// This is useful for talking to nodes which don't expose the legacy `author` RPC methods.
+    ///
+    /// Returns a [`TxProgress`], which can be used to track the status of the transaction
+    /// and obtain details about it, once it has made it into a block.
+    pub async fn submit_and_watch_unstable(&self) -> Result<TxProgress<T, C>, Error> {
+        // Get a hash of the extrinsic (we'll need this later).
+        let ext_hash = T::Hasher::hash_of(&self.encoded);
+
+        // Submit and watch for transaction progress.
+        let sub = self.submitted(
+            self.client
+                .rpc()
+                .transaction_unstable_submit_and_watch(self.encoded())
+                .await,
+        )?;
+
+        Ok(
+            TxProgress::from_transaction_events(sub, self.client.clone(), ext_hash)
+                .with_nonce(self.nonce.clone()),
+        )
     }
 
     /// Submits the extrinsic to the chain for block inclusion.
@@ -462,27 +612,49 @@ where
     /// Success does not mean the extrinsic has been included in the block, just that it is valid
     /// and has been included in the transaction pool.
     pub async fn submit(&self) -> Result<T::Hash, Error> {
-        self.client.rpc().submit_extrinsic(&self.encoded).await
+        self.submitted(self.client.rpc().submit_extrinsic(&self.encoded).await)
     }
 
     /// Submits the extrinsic to the dry_run RPC, to test if it would succeed.
     ///
     /// Returns `Ok` with a [`DryRunResult`], which is the result of attempting to dry run the extrinsic.
+    ///
+    /// If the node doesn't expose the `system_dryRun` RPC method (it's considered unsafe, and so
+    /// public nodes tend not to), the runtime API that it wraps is called directly instead.
     pub async fn dry_run(&self, at: Option<T::Hash>) -> Result<DryRunResult, Error> {
-        let dry_run_bytes = self.client.rpc().dry_run(self.encoded(), at).await?;
+        let rpc = self.client.rpc();
+        let dry_run_bytes = match rpc.best_method(&["system_dryRun", "state_call"]).await? {
+            "system_dryRun" => rpc.dry_run(self.encoded(), at).await?,
+            _ => {
+                let bytes = rpc
+                    .state_call_raw("BlockBuilder_apply_extrinsic", Some(self.encoded()), at)
+                    .await?;
+                DryRunResultBytes(bytes.0)
+            }
+        };
         dry_run_bytes.into_dry_run_result(&self.client.metadata())
     }
 
     /// This returns an estimate for what the extrinsic is expected to cost to execute, less any tips.
     /// The actual amount paid can vary from block to block based on node traffic and other factors.
+    ///
+    /// This calls the `TransactionPaymentApi_query_info` runtime API, falling back to the legacy
+    /// `payment_queryInfo` RPC method if the node doesn't expose `state_call`.
     pub async fn partial_fee_estimate(&self) -> Result<u128, Error> {
+        let rpc = self.client.rpc();
+        let method = rpc
+            .best_method(&["state_call", "payment_queryInfo"])
+            .await?;
+        if method == "payment_queryInfo" {
+            let info = rpc.payment_query_info(self.encoded(), None).await?;
+            return Ok(info.partial_fee);
+        }
+
         let mut params = self.encoded().to_vec();
         (self.encoded().len() as u32).encode_to(&mut params);
         // destructuring RuntimeDispatchInfo, see type information <https://paritytech.github.io/substrate/master/pallet_transaction_payment_rpc_runtime_api/struct.RuntimeDispatchInfo.html>
         // data layout: {weight_ref_time: Compact<u64>, weight_proof_size: Compact<u64>, class: u8, partial_fee: u128}
-        let (_, _, _, partial_fee) = self
-            .client
-            .rpc()
+        let (_, _, _, partial_fee) = rpc
             .state_call::<(Compact<u64>, Compact<u64>, u8, u128)>(
                 "TransactionPaymentApi_query_info",
                 Some(&params),
@@ -492,3 +664,275 @@ where
         Ok(partial_fee)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        config::substrate::{BlakeTwo256, Digest, SubstrateHeader, H256},
+        error::RpcError,
+        rpc::{
+            mock_rpc_client::{to_raw, MockRpcClient},
+            types::{Bytes, RuntimeVersion},
+        },
+        utils::{AccountId32, MultiAddress, MultiSignature},
+        Metadata, OnlineClient, SubstrateConfig,
+    };
+    use codec::Decode;
+    use std::sync::Mutex;
+
+    // The latest finalized block. Blocks are identified by hashes made from their numbers.
+    const FINALIZED: u64 = 100_005;
+
+    type BlockHashRequests = Arc<Mutex<Vec<u64>>>;
+
+    // A client which records the blocks whose hashes are asked for. If `has_hashes` is
+    // false, the node doesn't know the hashes of any blocks by number.
+    fn client(requests: BlockHashRequests, has_hashes: bool) -> OnlineClient<SubstrateConfig> {
+        let rpc = MockRpcClient::new().on_request(move |method, params| match method {
+            "chain_getFinalizedHead" => Ok(to_raw(H256::from_low_u64_be(FINALIZED))),
+            "chain_getHeader" => {
+                let (hash,): (H256,) = serde_json::from_str(params.unwrap().get()).unwrap();
+                Ok(to_raw(SubstrateHeader::<u32, BlakeTwo256> {
+                    parent_hash: H256::zero(),
+                    number: hash.to_low_u64_be() as u32,
+                    state_root: H256::zero(),
+                    extrinsics_root: H256::zero(),
+                    digest: Digest::default(),
+                }))
+            }
+            "chain_getBlockHash" => {
+                let (number,): (u64,) = serde_json::from_str(params.unwrap().get()).unwrap();
+                requests.lock().unwrap().push(number);
+                Ok(to_raw(has_hashes.then(|| H256::from_low_u64_be(number))))
+            }
+            "state_call" => Ok(to_raw(Bytes(0u32.encode()))),
+            _ => panic!("unexpected method {method}"),
+        });
+        online_client(rpc)
+    }
+
+    // A client for a node which exposes the given methods. The methods and runtime APIs
+    // which are called, other than `rpc_methods`, are recorded.
+    fn fallback_client(
+        methods: &'static [&'static str],
+        requests: Arc<Mutex<Vec<String>>>,
+    ) -> OnlineClient<SubstrateConfig> {
+        let rpc = MockRpcClient::new().on_request(move |method, params| {
+            if method == "rpc_methods" {
+                return Ok(to_raw(
+                    serde_json::json!({ "version": 1, "methods": methods }),
+                ));
+            }
+            let name = match method {
+                "state_call" => {
+                    let params: serde_json::Value =
+                        serde_json::from_str(params.unwrap().get()).unwrap();
+                    params[0].as_str().unwrap().to_owned()
+                }
+                _ => method.to_owned(),
+            };
+            requests.lock().unwrap().push(name.clone());
+            match &*name {
+                "system_dryRun" | "BlockBuilder_apply_extrinsic" => Ok(to_raw(Bytes(vec![0, 0]))),
+                "TransactionPaymentApi_query_info" => Ok(to_raw(Bytes(
+                    (Compact(1u64), Compact(2u64), 0u8, 7u128).encode(),
+                ))),
+                "payment_queryInfo" => Ok(to_raw(serde_json::json!({
+                    "weight": { "refTime": 1, "proofSize": 2 },
+                    "class": "normal",
+                    "partialFee": "8",
+                }))),
+                _ => panic!("unexpected method {name}"),
+            }
+        });
+        online_client(rpc)
+    }
+
+    fn online_client(rpc: MockRpcClient) -> OnlineClient<SubstrateConfig> {
+        let bytes = std::fs::read("../artifacts/polkadot_metadata_tiny.scale").unwrap();
+        let metadata = Metadata::decode(&mut &*bytes).unwrap();
+        let runtime_version = RuntimeVersion {
+            spec_version: 1,
+            transaction_version: 1,
+            other: Default::default(),
+        };
+        OnlineClient::from_rpc_client_with(H256::zero(), runtime_version, metadata, Arc::new(rpc))
+            .unwrap()
+    }
+
+    // The era and checkpoint that the given params make a transaction mortal with.
+    fn mortality(
+        other_params: <<SubstrateConfig as Config>::ExtrinsicParams as ExtrinsicParams<
+            u32,
+            H256,
+        >>::OtherParams,
+    ) -> (Vec<u8>, Vec<u8>) {
+        let params =
+            <<SubstrateConfig as Config>::ExtrinsicParams as ExtrinsicParams<u32, H256>>::new(
+                1,
+                1,
+                0,
+                H256::zero(),
+                other_params,
+            );
+        let mut extra = Vec::new();
+        params.encode_extra_to(&mut extra);
+        let mut additional = Vec::new();
+        params.encode_additional_to(&mut additional);
+        // The era comes first in the extra data, and the checkpoint last in the additional data.
+        (
+            extra[..2].to_vec(),
+            additional[additional.len() - 32..].to_vec(),
+        )
+    }
+
+    // A payload which encodes to the given bytes, whatever the metadata.
+    struct RawCall(Vec<u8>);
+
+    impl TxPayload for RawCall {
+        fn encode_call_data_to(
+            &self,
+            _metadata: &Metadata,
+            out: &mut Vec<u8>,
+        ) -> Result<(), Error> {
+            out.extend_from_slice(&self.0);
+            Ok(())
+        }
+    }
+
+    // A signer which remembers the last payload that it signed.
+    struct RecordingSigner(AccountId32, Mutex<Vec<u8>>);
+
+    impl SignerT<SubstrateConfig> for RecordingSigner {
+        fn account_id(&self) -> &AccountId32 {
+            &self.0
+        }
+        fn address(&self) -> MultiAddress<AccountId32, u32> {
+            self.0.clone().into()
+        }
+        fn sign(&self, signer_payload: &[u8]) -> MultiSignature {
+            *self.1.lock().unwrap() = signer_payload.to_vec();
+            MultiSignature::Sr25519([0; 64])
+        }
+    }
+
+    #[tokio::test]
+    async fn short_periods_are_checkpointed_at_the_finalized_block() {
+        let requests = BlockHashRequests::default();
+        let client = client(requests.clone(), true);
+
+        let other_params = client
+            .tx()
+            .mortal_params(64, Default::default())
+            .await
+            .unwrap();
+
+        let (era, checkpoint) = mortality(other_params);
+        assert_eq!(era, Era::mortal(64, FINALIZED).encode());
+        assert_eq!(checkpoint, H256::from_low_u64_be(FINALIZED).encode());
+        assert!(requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn long_periods_are_checkpointed_at_the_start_of_the_era() {
+        let requests = BlockHashRequests::default();
+        let client = client(requests.clone(), true);
+
+        // Phases of long periods are quantised to multiples of 16, so the era begins at the
+        // last block before the finalized one whose number is a multiple of 16.
+        let era = Era::mortal(65536, FINALIZED);
+        assert_eq!(era.birth(FINALIZED), 100_000);
+
+        let other_params = client
+            .tx()
+            .mortal_params(65536, Default::default())
+            .await
+            .unwrap();
+
+        let (encoded_era, checkpoint) = mortality(other_params);
+        assert_eq!(encoded_era, era.encode());
+        assert_eq!(checkpoint, H256::from_low_u64_be(100_000).encode());
+        assert_eq!(*requests.lock().unwrap(), vec![100_000]);
+    }
+
+    #[tokio::test]
+    async fn missing_checkpoint_blocks_are_block_errors() {
+        let client = client(BlockHashRequests::default(), false);
+
+        let err = client
+            .tx()
+            .mortal_params(65536, Default::default())
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(err, Error::Block(BlockError::NumberNotFound(100_000))),
+            "{err:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn mortal_extrinsics_sign_the_era_and_checkpoint() {
+        let client = client(BlockHashRequests::default(), true);
+        let signer = RecordingSigner(AccountId32([1; 32]), Mutex::default());
+
+        client
+            .tx()
+            .create_signed_mortal(&RawCall(vec![7, 7]), &signer, 65536, Default::default())
+            .await
+            .unwrap();
+
+        // The payload is the call data, followed by the extra data (which begins with the
+        // era) and the additional data (which ends with the checkpoint).
+        let payload = signer.1.lock().unwrap();
+        let era = Era::mortal(65536, FINALIZED).encode();
+        assert_eq!(payload[..4], [&[7, 7][..], &era].concat());
+        assert!(payload.ends_with(H256::from_low_u64_be(100_000).as_ref()));
+    }
+
+    #[tokio::test]
+    async fn dry_runs_fall_back_to_the_runtime_api() {
+        for (methods, expected) in [
+            (&["system_dryRun", "state_call"] as &[_], "system_dryRun"),
+            (&["state_call"] as &[_], "BlockBuilder_apply_extrinsic"),
+        ] {
+            let requests = Arc::default();
+            let client = fallback_client(methods, Arc::clone(&requests));
+            let tx = SubmittableExtrinsic::<SubstrateConfig, _>::from_bytes(client, vec![1, 2, 3]);
+
+            assert_eq!(tx.dry_run(None).await.unwrap(), DryRunResult::Success);
+            assert_eq!(*requests.lock().unwrap(), vec![expected]);
+        }
+    }
+
+    #[tokio::test]
+    async fn fee_estimates_fall_back_to_payment_query_info() {
+        for (methods, expected, fee) in [
+            (
+                &["state_call", "payment_queryInfo"] as &[_],
+                "TransactionPaymentApi_query_info",
+                7,
+            ),
+            (&["payment_queryInfo"] as &[_], "payment_queryInfo", 8),
+        ] {
+            let requests = Arc::default();
+            let client = fallback_client(methods, Arc::clone(&requests));
+            let tx = SubmittableExtrinsic::<SubstrateConfig, _>::from_bytes(client, vec![1, 2, 3]);
+
+            assert_eq!(tx.partial_fee_estimate().await.unwrap(), fee);
+            assert_eq!(*requests.lock().unwrap(), vec![expected]);
+        }
+    }
+
+    #[tokio::test]
+    async fn unsupported_methods_are_reported() {
+        let client = fallback_client(&["chain_getHeader"], Arc::default());
+        let tx = SubmittableExtrinsic::<SubstrateConfig, _>::from_bytes(client, vec![1, 2, 3]);
+
+        let err = tx.partial_fee_estimate().await.unwrap_err();
+        assert!(
+            matches!(&err, Error::Rpc(RpcError::MethodNotSupported(m)) if m == "state_call"),
+            "{err:?}"
+        );
+    }
+}
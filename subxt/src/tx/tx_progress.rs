@@ -10,19 +10,34 @@ use crate::{
     client::OnlineClientT,
     error::{DispatchError, Error, RpcError, TransactionError},
     events::EventsClient,
-    rpc::types::{Subscription, SubstrateTxStatus},
+    rpc::types::{Subscription, SubstrateTxStatus, TransactionEvent},
+    tx::Nonce,
     Config,
 };
 use derivative::Derivative;
 use futures::{Stream, StreamExt};
+use std::sync::Arc;
 
 /// This struct represents a subscription to the progress of some transaction.
 #[derive(Derivative)]
 #[derivative(Debug(bound = "C: std::fmt::Debug"))]
 pub struct TxProgress<T: Config, C> {
-    sub: Option<Subscription<SubstrateTxStatus<T::Hash, T::Hash>>>,
+    sub: Option<TxProgressSubscription<T::Hash>>,
     ext_hash: T::Hash,
     client: C,
+    // The last block that the transaction was reported to be in, if any.
+    last_block_hash: Option<T::Hash>,
+    // The nonce that the transaction uses, if it came from a `NonceManager`.
+    nonce: Option<Arc<Nonce<T>>>,
+}
+
+// The subscriptions that we know how to turn into a stream of [`TxStatus`]es.
+#[derive(Debug)]
+enum TxProgressSubscription<Hash> {
+    // Via `author_submitAndWatchExtrinsic`.
+    Author(Subscription<SubstrateTxStatus<Hash, Hash>>),
+    // Via `transaction_unstable_submitAndWatch`.
+    Transaction(Subscription<TransactionEvent<Hash>>),
 }
 
 // The above type is not `Unpin` by default unless the generic param `T` is,
@@ -38,9 +53,36 @@ impl<T: Config, C> TxProgress<T, C> {
         ext_hash: T::Hash,
     ) -> Self {
         Self {
-            sub: Some(sub),
+            sub: Some(TxProgressSubscription::Author(sub)),
+            client,
+            ext_hash,
+            last_block_hash: None,
+            nonce: None,
+        }
+    }
+
+    /// Instantiate a new [`TxProgress`] from a subscription to [`TransactionEvent`]s, as
+    /// handed back from the `transaction_unstable_submitAndWatch` RPC method. These events
+    /// are mapped to [`TxStatus`]es as follows:
+    ///
+    /// - `Validated` becomes [`TxStatus::Ready`].
+    /// - `Broadcasted` becomes [`TxStatus::Broadcast`]. No peers are given.
+    /// - `BestChainBlockIncluded` becomes [`TxStatus::InBlock`], or [`TxStatus::Retracted`]
+    ///   if the transaction is no longer in a best block.
+    /// - `Finalized` becomes [`TxStatus::Finalized`].
+    /// - `Invalid`, `Dropped` and `Error` are handed back as a [`TransactionError::Error`],
+    ///   so that the reason given by the node isn't lost.
+    pub fn from_transaction_events(
+        sub: Subscription<TransactionEvent<T::Hash>>,
+        client: C,
+        ext_hash: T::Hash,
+    ) -> Self {
+        Self {
+            sub: Some(TxProgressSubscription::Transaction(sub)),
             client,
             ext_hash,
+            last_block_hash: None,
+            nonce: None,
         }
     }
 
@@ -48,6 +90,21 @@ impl<T: Config, C> TxProgress<T, C> {
     pub fn extrinsic_hash(&self) -> T::Hash {
         self.ext_hash
     }
+
+    // Track the nonce that the transaction uses, so that the `NonceManager` it came from
+    // can resync the account if the transaction is dropped or found to be invalid.
+    pub(crate) fn with_nonce(mut self, nonce: Option<Arc<Nonce<T>>>) -> Self {
+        self.nonce = nonce;
+        self
+    }
+
+    // The transaction won't make it into a block (at least, via this node).
+    fn dropped(&mut self) {
+        self.sub = None;
+        if let Some(nonce) = self.nonce.take() {
+            nonce.dropped();
+        }
+    }
 }
 
 impl<T, C> TxProgress<T, C>
@@ -147,9 +204,13 @@ impl<T: Config, C: Clone> Stream for TxProgress<T, C> {
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Self::Item>> {
+        if let Some(TxProgressSubscription::Transaction(_)) = &self.sub {
+            return self.poll_next_transaction(cx);
+        }
+
         let sub = match self.sub.as_mut() {
-            Some(sub) => sub,
-            None => return Poll::Ready(None),
+            Some(TxProgressSubscription::Author(sub)) => sub,
+            _ => return Poll::Ready(None),
         };
 
         sub.poll_next_unpin(cx).map_ok(|status| {
@@ -192,11 +253,11 @@ impl<T: Config, C: Clone> Stream for TxProgress<T, C> {
                     TxStatus::Usurped(hash)
                 }
                 SubstrateTxStatus::Dropped => {
-                    self.sub = None;
+                    self.dropped();
                     TxStatus::Dropped
                 }
                 SubstrateTxStatus::Invalid => {
-                    self.sub = None;
+                    self.dropped();
                     TxStatus::Invalid
                 }
             }
@@ -204,6 +265,72 @@ impl<T: Config, C: Clone> Stream for TxProgress<T, C> {
     }
 }
 
+impl<T: Config, C: Clone> TxProgress<T, C> {
+    // Poll for the next status when we're subscribed to `TransactionEvent`s.
+    fn poll_next_transaction(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Result<TxStatus<T, C>, Error>>> {
+        loop {
+            let Some(TxProgressSubscription::Transaction(sub)) = self.sub.as_mut() else {
+                return Poll::Ready(None);
+            };
+
+            let event = match futures::ready!(sub.poll_next_unpin(cx)) {
+                Some(Ok(event)) => event,
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => return Poll::Ready(None),
+            };
+
+            let status = match event {
+                TransactionEvent::Validated => TxStatus::Ready,
+                TransactionEvent::Broadcasted(_) => TxStatus::Broadcast(Vec::new()),
+                TransactionEvent::BestChainBlockIncluded(Some(block)) => {
+                    self.last_block_hash = Some(block.hash);
+                    TxStatus::InBlock(TxInBlock::new(
+                        block.hash,
+                        self.ext_hash,
+                        self.client.clone(),
+                    ))
+                }
+                TransactionEvent::BestChainBlockIncluded(None) => {
+                    match self.last_block_hash.take() {
+                        Some(hash) => TxStatus::Retracted(hash),
+                        // We haven't reported the transaction as being in a block, so
+                        // there is nothing to retract; wait for the next event.
+                        None => continue,
+                    }
+                }
+                // Like with `author_submitAndWatchExtrinsic`, the following events are final,
+                // and no more events will be produced after them.
+                TransactionEvent::Finalized(block) => {
+                    self.sub = None;
+                    TxStatus::Finalized(TxInBlock::new(
+                        block.hash,
+                        self.ext_hash,
+                        self.client.clone(),
+                    ))
+                }
+                TransactionEvent::Invalid(e) => {
+                    self.dropped();
+                    let reason = format!("the transaction is invalid: {}", e.error);
+                    return Poll::Ready(Some(Err(TransactionError::Error(reason).into())));
+                }
+                TransactionEvent::Dropped(e) => {
+                    self.dropped();
+                    let reason = format!("the transaction was dropped: {}", e.error);
+                    return Poll::Ready(Some(Err(TransactionError::Error(reason).into())));
+                }
+                TransactionEvent::Error(e) => {
+                    self.sub = None;
+                    return Poll::Ready(Some(Err(TransactionError::Error(e.error).into())));
+                }
+            };
+            return Poll::Ready(Some(Ok(status)));
+        }
+    }
+}
+
 //* Dev note: The below is adapted from the substrate docs on `TxStatus`, which this
 //* enum was adapted from (and which is an exact copy of `SubstrateTxStatus` in this crate).
 //* Note that the number of finality watchers is, at the time of writing, found in the constant
@@ -268,7 +395,9 @@ pub enum TxStatus<T: Config, C> {
     Future,
     /// The transaction is part of the "ready" queue.
     Ready,
-    /// The transaction has been broadcast to the given peers.
+    /// The transaction has been broadcast to the given peers. The peers are not
+    /// known when following a transaction submitted via the new transaction API,
+    /// and so this will be empty in that case.
     Broadcast(Vec<String>),
     /// The transaction has been included in a block with given hash.
     InBlock(TxInBlock<T, C>),
@@ -412,14 +541,14 @@ impl<T: Config, C: OnlineClientT<T>> TxInBlock<T, C> {
 mod test {
     use std::pin::Pin;
 
-    use futures::Stream;
+    use futures::{Stream, StreamExt};
 
     use crate::{
         client::{OfflineClientT, OnlineClientT},
         config::{extrinsic_params::BaseExtrinsicParams, polkadot::PlainTip, WithExtrinsicParams},
         error::RpcError,
         rpc::{types::SubstrateTxStatus, RpcSubscription, Subscription},
-        tx::TxProgress,
+        tx::{TxProgress, TxStatus},
         Config, Error, SubstrateConfig,
     };
 
@@ -491,6 +620,80 @@ mod test {
         ));
     }
 
+    #[tokio::test]
+    async fn transaction_events_map_to_tx_statuses() {
+        let block_hash = MockHash::repeat_byte(1);
+        let tx_progress = mock_transaction_events_tx_progress(vec![
+            r#"{"event":"validated"}"#.to_owned(),
+            r#"{"event":"broadcasted","numPeers":"2"}"#.to_owned(),
+            format!(
+                r#"{{"event":"bestChainBlockIncluded","block":{{"hash":"{block_hash:?}","index":"0"}}}}"#
+            ),
+            r#"{"event":"bestChainBlockIncluded","block":null}"#.to_owned(),
+            format!(r#"{{"event":"finalized","block":{{"hash":"{block_hash:?}","index":"0"}}}}"#),
+        ]);
+
+        let statuses: Vec<_> = tx_progress.map(|s| s.unwrap()).collect().await;
+        assert!(matches!(
+            &statuses[..],
+            [
+                TxStatus::Ready,
+                TxStatus::Broadcast(peers),
+                TxStatus::InBlock(in_block),
+                TxStatus::Retracted(retracted),
+                TxStatus::Finalized(finalized),
+            ] if peers.is_empty()
+                && in_block.block_hash() == block_hash
+                && *retracted == block_hash
+                && finalized.block_hash() == block_hash
+        ));
+    }
+
+    #[tokio::test]
+    async fn wait_for_finalized_returns_err_when_transaction_event_is_invalid() {
+        let tx_progress = mock_transaction_events_tx_progress(vec![
+            r#"{"event":"validated"}"#.to_owned(),
+            r#"{"event":"invalid","error":"bad nonce"}"#.to_owned(),
+        ]);
+        let finalized_result = tx_progress.wait_for_finalized().await;
+        assert!(matches!(
+            finalized_result,
+            Err(Error::Transaction(crate::error::TransactionError::Error(e)))
+                if e == "the transaction is invalid: bad nonce"
+        ));
+    }
+
+    #[tokio::test]
+    async fn wait_for_finalized_returns_err_when_transaction_event_is_dropped() {
+        let tx_progress = mock_transaction_events_tx_progress(vec![
+            r#"{"event":"validated"}"#.to_owned(),
+            r#"{"event":"dropped","broadcasted":false,"error":"pool is full"}"#.to_owned(),
+        ]);
+        let finalized_result = tx_progress.wait_for_finalized().await;
+        assert!(matches!(
+            finalized_result,
+            Err(Error::Transaction(crate::error::TransactionError::Error(e)))
+                if e == "the transaction was dropped: pool is full"
+        ));
+    }
+
+    #[tokio::test]
+    async fn wait_for_finalized_returns_err_when_transaction_event_is_error() {
+        let tx_progress = mock_transaction_events_tx_progress(vec![
+            r#"{"event":"error","error":"oops"}"#.to_owned(),
+        ]);
+        let finalized_result = tx_progress.wait_for_finalized().await;
+        assert!(matches!(
+            finalized_result,
+            Err(Error::Transaction(crate::error::TransactionError::Error(e))) if e == "oops"
+        ));
+    }
+
+    fn mock_transaction_events_tx_progress(events: Vec<String>) -> MockTxProgress {
+        let sub = create_subscription(events);
+        TxProgress::from_transaction_events(sub, MockClient, Default::default())
+    }
+
     fn mock_tx_progress(statuses: Vec<MockSubstrateTxStatus>) -> MockTxProgress {
         let sub = create_substrate_tx_status_subscription(statuses);
         TxProgress::new(sub, MockClient, Default::default())
@@ -499,10 +702,18 @@ mod test {
     fn create_substrate_tx_status_subscription(
         elements: Vec<MockSubstrateTxStatus>,
     ) -> Subscription<MockSubstrateTxStatus> {
+        create_subscription(
+            elements
+                .into_iter()
+                .map(|e| serde_json::to_string(&e).unwrap())
+                .collect(),
+        )
+    }
+
+    fn create_subscription<Res>(elements: Vec<String>) -> Subscription<Res> {
         let rpc_substription_stream: Pin<
             Box<dyn Stream<Item = Result<Box<RawValue>, RpcError>> + Send + 'static>,
-        > = Box::pin(futures::stream::iter(elements.into_iter().map(|e| {
-            let s = serde_json::to_string(&e).unwrap();
+        > = Box::pin(futures::stream::iter(elements.into_iter().map(|s| {
             let r: Box<RawValue> = RawValue::from_string(s).unwrap();
             Ok(r)
         })));
@@ -512,7 +723,6 @@ mod test {
             id: None,
         };
 
-        let sub: Subscription<MockSubstrateTxStatus> = Subscription::new(rpc_subscription);
-        sub
+        Subscription::new(rpc_subscription)
     }
 }
@@ -0,0 +1,197 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! The 20 byte, Ethereum style AccountId used by chains whose accounts are ECDSA keys,
+//! such as EVM compatible chains. This is displayed and serialized as an EIP-55 checksummed
+//! hex address.
+
+use codec::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+/// A 20-byte, Ethereum style account identifier, which is the last 20 bytes of the keccak-256
+/// hash of the account's uncompressed ECDSA public key.
+#[derive(
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Hash,
+    Encode,
+    Decode,
+    Debug,
+    scale_encode::EncodeAsType,
+    scale_decode::DecodeAsType,
+)]
+pub struct AccountId20(pub [u8; 20]);
+
+impl AsRef<[u8]> for AccountId20 {
+    fn as_ref(&self) -> &[u8] {
+        &self.0[..]
+    }
+}
+
+impl AsRef<[u8; 20]> for AccountId20 {
+    fn as_ref(&self) -> &[u8; 20] {
+        &self.0
+    }
+}
+
+impl From<[u8; 20]> for AccountId20 {
+    fn from(x: [u8; 20]) -> Self {
+        AccountId20(x)
+    }
+}
+
+impl From<AccountId20> for [u8; 20] {
+    fn from(x: AccountId20) -> Self {
+        x.0
+    }
+}
+
+impl AccountId20 {
+    /// The account ID of the given uncompressed (65 byte, `0x04` prefixed) ECDSA public key.
+    pub fn from_uncompressed_public_key(public_key: &[u8; 65]) -> Self {
+        let hash = sp_core_hashing::keccak_256(&public_key[1..]);
+        let mut account_id = [0u8; 20];
+        account_id.copy_from_slice(&hash[12..]);
+        AccountId20(account_id)
+    }
+
+    /// Return the EIP-55 checksummed hex address for this account, which is the lower case
+    /// hex address with each letter upper cased if the corresponding nibble of the keccak-256
+    /// hash of the lower case address is 8 or more.
+    pub fn checksum(&self) -> String {
+        let hex_address = hex::encode(self.0);
+        let hash = sp_core_hashing::keccak_256(hex_address.as_bytes());
+
+        let mut checksum = String::with_capacity(42);
+        checksum.push_str("0x");
+        for (i, c) in hex_address.chars().enumerate() {
+            let hash_nibble = if i % 2 == 0 {
+                hash[i / 2] >> 4
+            } else {
+                hash[i / 2] & 0x0f
+            };
+            if hash_nibble >= 8 {
+                checksum.push(c.to_ascii_uppercase());
+            } else {
+                checksum.push(c);
+            }
+        }
+        checksum
+    }
+
+    // Addresses which are entirely lower or upper case carry no checksum, and so are accepted
+    // as they are. Mixed case addresses must have the correct checksum.
+    fn from_checksum(s: &str) -> Result<Self, FromChecksumError> {
+        let hex_address = s.strip_prefix("0x").unwrap_or(s);
+        if hex_address.len() != 40 {
+            return Err(FromChecksumError::BadLength);
+        }
+
+        let mut account_id = [0u8; 20];
+        hex::decode_to_slice(hex_address, &mut account_id)
+            .map_err(|_| FromChecksumError::InvalidHex)?;
+        let account_id = AccountId20(account_id);
+
+        let is_lower = hex_address == hex_address.to_ascii_lowercase();
+        let is_upper = hex_address == hex_address.to_ascii_uppercase();
+        if !is_lower && !is_upper && account_id.checksum()[2..] != *hex_address {
+            return Err(FromChecksumError::InvalidChecksum);
+        }
+        Ok(account_id)
+    }
+}
+
+/// An error obtained from trying to interpret a hex address into an AccountId20
+#[derive(thiserror::Error, Clone, Copy, Eq, PartialEq, Debug)]
+#[allow(missing_docs)]
+pub enum FromChecksumError {
+    #[error("Length is bad")]
+    BadLength,
+    #[error("Invalid hex")]
+    InvalidHex,
+    #[error("Invalid checksum")]
+    InvalidChecksum,
+}
+
+impl Serialize for AccountId20 {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.checksum())
+    }
+}
+
+impl<'de> Deserialize<'de> for AccountId20 {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        AccountId20::from_checksum(&String::deserialize(deserializer)?)
+            .map_err(|e| serde::de::Error::custom(format!("{e:?}")))
+    }
+}
+
+impl std::fmt::Display for AccountId20 {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.checksum())
+    }
+}
+
+impl std::str::FromStr for AccountId20 {
+    type Err = FromChecksumError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AccountId20::from_checksum(s)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Test vectors from EIP-55.
+    const CHECKSUMMED: &[&str] = &[
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn checksums_match_eip55() {
+        for &address in CHECKSUMMED {
+            let account_id: AccountId20 = address.parse().unwrap();
+            assert_eq!(account_id.to_string(), address);
+
+            // Unchecksummed addresses are accepted as they are.
+            let lower: AccountId20 = address.to_ascii_lowercase().parse().unwrap();
+            assert_eq!(lower, account_id);
+        }
+
+        // But a mixed case address with the wrong checksum is not.
+        assert_eq!(
+            "0x5AAeb6053F3E94C9b9A09f33669435E7Ef1BeAed".parse::<AccountId20>(),
+            Err(FromChecksumError::InvalidChecksum)
+        );
+        assert_eq!(
+            "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeA".parse::<AccountId20>(),
+            Err(FromChecksumError::BadLength)
+        );
+    }
+
+    #[test]
+    fn serde_roundtrips_via_checksum() {
+        let account_id: AccountId20 = CHECKSUMMED[0].parse().unwrap();
+        let json = serde_json::to_string(&account_id).unwrap();
+        assert_eq!(json, format!("\"{}\"", CHECKSUMMED[0]));
+        assert_eq!(
+            serde_json::from_str::<AccountId20>(&json).unwrap(),
+            account_id
+        );
+    }
+}
@@ -0,0 +1,36 @@
+// Copyright 2019-2023 Parity Technologies (UK) Ltd.
+// This file is dual-licensed as Apache-2.0 or GPL-3.0.
+// see LICENSE for license details.
+
+//! The signature used by chains whose accounts are Ethereum style [`super::AccountId20`]s.
+
+use codec::{Decode, Encode};
+
+/// A recoverable ECDSA/SECP256k1 signature of the keccak-256 hash of a payload: the 64 byte
+/// `r` and `s` values, followed by a 1 byte recovery ID of 0 or 1.
+#[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Encode, Decode, Debug)]
+pub struct EthereumSignature(pub [u8; 65]);
+
+impl AsRef<[u8]> for EthereumSignature {
+    fn as_ref(&self) -> &[u8] {
+        &self.0[..]
+    }
+}
+
+impl From<[u8; 65]> for EthereumSignature {
+    fn from(x: [u8; 65]) -> Self {
+        EthereumSignature(x)
+    }
+}
+
+// Improve compat with the substrate version if we're using those crates:
+#[cfg(feature = "substrate-compat")]
+mod substrate_impls {
+    use super::*;
+
+    impl From<sp_core::ecdsa::Signature> for EthereumSignature {
+        fn from(value: sp_core::ecdsa::Signature) -> Self {
+            Self(value.0)
+        }
+    }
+}
@@ -5,7 +5,9 @@
 //! Miscellaneous utility helpers.
 
 mod account_id;
+mod account_id20;
 pub mod bits;
+mod ethereum_signature;
 mod multi_address;
 mod multi_signature;
 mod static_type;
@@ -15,6 +17,8 @@ use codec::{Decode, Encode};
 use derivative::Derivative;
 
 pub use account_id::AccountId32;
+pub use account_id20::AccountId20;
+pub use ethereum_signature::EthereumSignature;
 pub use multi_address::MultiAddress;
 pub use multi_signature::MultiSignature;
 pub use static_type::Static;